@@ -0,0 +1,335 @@
+use syn::ext::IdentExt;
+use syn::meta::ParseNestedMeta;
+use syn::punctuated::Punctuated;
+use syn::{
+    Attribute, Data, DeriveInput, Error, ExprPath, Fields, LitStr, Token, Type, WherePredicate,
+};
+
+use crate::case::RenameRule;
+
+/// How an enum marks which variant a value holds.
+pub(crate) enum Tagging {
+    /// `{"Variant": content}`, or `"Variant"` for unit variants.
+    External,
+    /// `{"<tag>": "Variant", ...fields}`.
+    Internal { tag: String },
+    /// `{"<tag>": "Variant", "<content>": content}`.
+    Adjacent { tag: String, content: String },
+    /// Just the content; deserialization tries the variants in order.
+    Untagged,
+}
+
+/// The key under which an internally tagged newtype variant writes content
+/// that is not an object, next to the tag.
+pub(crate) const NEWTYPE_CONTENT_KEY: &str = "value";
+
+/// Options from the `#[json(...)]` attributes on the type itself.
+pub(crate) struct Container {
+    pub tagging: Tagging,
+    /// Where-clause predicates replacing the inferred bounds on the type
+    /// parameters, from `#[json(bound = "...")]`.
+    pub bound: Option<Vec<WherePredicate>>,
+    /// Renames the fields of a struct or the variants of an enum.
+    pub rename_all: Option<RenameRule>,
+    /// Where the fields missing from an object come from.
+    pub default: Option<DefaultValue>,
+}
+
+/// A value to use in place of a missing member, from `#[json(default)]` or
+/// `#[json(default = "path")]`.
+pub(crate) enum DefaultValue {
+    /// `Default::default()`.
+    Trait,
+    /// A function called without arguments.
+    Path(ExprPath),
+}
+
+impl Container {
+    pub(crate) fn from_ast(ast: &DeriveInput) -> syn::Result<Container> {
+        let mut tag = None;
+        let mut content = None;
+        let mut untagged = false;
+        let mut bound = None;
+        let mut rename_all = None;
+        let mut default = None;
+        for attr in json_attrs(&ast.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("tag") {
+                    tag = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("content") {
+                    content = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("untagged") {
+                    untagged = true;
+                } else if meta.path.is_ident("bound") {
+                    let predicates = meta
+                        .value()?
+                        .parse::<LitStr>()?
+                        .parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)?;
+                    bound = Some(predicates.into_iter().collect());
+                } else if meta.path.is_ident("rename_all") {
+                    rename_all = Some(rename_rule(&meta)?);
+                } else if meta.path.is_ident("default") {
+                    default = Some(default_value(&meta)?);
+                } else {
+                    return Err(meta.error("unknown json container attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        let tagging = match (tag, content, untagged) {
+            (None, None, false) => Tagging::External,
+            (Some(tag), None, false) => Tagging::Internal { tag },
+            (Some(tag), Some(content), false) => Tagging::Adjacent { tag, content },
+            (None, None, true) => Tagging::Untagged,
+            (None, Some(_), _) => {
+                return Err(Error::new_spanned(&ast.ident, "`content` requires `tag`"))
+            }
+            (Some(_), _, true) => {
+                return Err(Error::new_spanned(
+                    &ast.ident,
+                    "`untagged` cannot be combined with `tag`",
+                ))
+            }
+        };
+
+        match &ast.data {
+            Data::Enum(data) => {
+                if let Tagging::Internal { tag } = &tagging {
+                    let tuple = data.variants.iter().find(
+                        |variant| matches!(&variant.fields, Fields::Unnamed(fields) if fields.unnamed.len() > 1),
+                    );
+                    if let Some(variant) = tuple {
+                        return Err(Error::new_spanned(
+                            &variant.ident,
+                            "internally tagged enums cannot have tuple variants",
+                        ));
+                    }
+                    // The tag is added to the content, so types that never
+                    // serialize to an object are rejected. Other content
+                    // that is not an object goes under a fixed key.
+                    let mut newtypes = data
+                        .variants
+                        .iter()
+                        .filter_map(|variant| match &variant.fields {
+                            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                                Some(&fields.unnamed[0].ty)
+                            }
+                            _ => None,
+                        })
+                        .peekable();
+                    if newtypes.peek().is_some() && tag == NEWTYPE_CONTENT_KEY {
+                        return Err(Error::new_spanned(
+                            &ast.ident,
+                            "the tag of an enum with newtype variants cannot be \"value\"",
+                        ));
+                    }
+                    if let Some(ty) = newtypes.find(|ty| is_primitive(ty)) {
+                        return Err(Error::new_spanned(
+                            ty,
+                            "cannot serialize tagged newtype variant containing a primitive or `Option`",
+                        ));
+                    }
+                }
+            }
+            _ if !matches!(tagging, Tagging::External) => {
+                return Err(Error::new_spanned(
+                    &ast.ident,
+                    "`tag`, `content` and `untagged` are only allowed on enums",
+                ))
+            }
+            _ => {}
+        }
+        if default.is_some()
+            && !matches!(&ast.data, Data::Struct(data) if matches!(data.fields, Fields::Named(_)))
+        {
+            return Err(Error::new_spanned(
+                &ast.ident,
+                "`default` is only allowed on structs with named fields",
+            ));
+        }
+        Ok(Container {
+            tagging,
+            bound,
+            rename_all,
+            default,
+        })
+    }
+}
+
+/// Options from the `#[json(...)]` attributes on an enum variant.
+pub(crate) struct Variant {
+    /// The name marking the variant in JSON.
+    pub name: String,
+    /// Other names accepted when deserializing.
+    pub aliases: Vec<String>,
+    pub fields: Vec<Field>,
+}
+
+impl Variant {
+    /// Reads the attributes of `variant`, naming it by `rename_all` from
+    /// the enum unless it is renamed itself.
+    pub(crate) fn from_ast(
+        variant: &syn::Variant,
+        rename_all: Option<RenameRule>,
+    ) -> syn::Result<Variant> {
+        let mut name = None;
+        let mut aliases = Vec::new();
+        let mut fields_rename_all = None;
+        for attr in json_attrs(&variant.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    name = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("alias") {
+                    aliases.push(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("rename_all") {
+                    fields_rename_all = Some(rename_rule(&meta)?);
+                } else {
+                    return Err(meta.error("unknown json variant attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        let name = name.unwrap_or_else(|| {
+            let ident = variant.ident.unraw().to_string();
+            match rename_all {
+                Some(rule) => rule.apply_to_variant(&ident),
+                None => ident,
+            }
+        });
+        Ok(Variant {
+            name,
+            aliases,
+            fields: Field::from_fields(&variant.fields, fields_rename_all)?,
+        })
+    }
+}
+
+/// Options from the `#[json(...)]` attributes on a field.
+pub(crate) struct Field {
+    /// The member key of a named field.
+    pub key: String,
+    /// Other keys accepted when deserializing.
+    pub aliases: Vec<String>,
+    /// Leaves the field out of the JSON entirely; deserialization fills it
+    /// from its default.
+    pub skip: bool,
+    /// A function taking the field by reference that returns `true` when
+    /// the field should not be serialized.
+    pub skip_serializing_if: Option<ExprPath>,
+    /// Fills the field in when its member is missing.
+    pub default: Option<DefaultValue>,
+}
+
+impl Field {
+    /// Reads the attributes of each of `fields`, keying named fields by
+    /// `rename_all` unless they are renamed themselves.
+    pub(crate) fn from_fields(
+        fields: &Fields,
+        rename_all: Option<RenameRule>,
+    ) -> syn::Result<Vec<Field>> {
+        fields
+            .iter()
+            .map(|field| Field::from_ast(field, rename_all))
+            .collect()
+    }
+
+    fn from_ast(field: &syn::Field, rename_all: Option<RenameRule>) -> syn::Result<Field> {
+        let mut key = None;
+        let mut aliases = Vec::new();
+        let mut skip = false;
+        let mut skip_serializing_if = None;
+        let mut default = None;
+        for attr in json_attrs(&field.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    key = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("alias") {
+                    aliases.push(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                } else if meta.path.is_ident("skip_serializing_if") {
+                    skip_serializing_if = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                } else if meta.path.is_ident("default") {
+                    default = Some(default_value(&meta)?);
+                } else {
+                    return Err(meta.error("unknown json field attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        if field.ident.is_none() && (skip || skip_serializing_if.is_some() || default.is_some()) {
+            return Err(Error::new_spanned(
+                field,
+                "`skip`, `skip_serializing_if` and `default` are only allowed on named fields",
+            ));
+        }
+
+        let key = match (key, &field.ident) {
+            (Some(key), Some(_)) => key,
+            (None, Some(ident)) => {
+                let ident = ident.unraw().to_string();
+                match rename_all {
+                    Some(rule) => rule.apply_to_field(&ident),
+                    None => ident,
+                }
+            }
+            (None, None) if aliases.is_empty() => String::new(),
+            (_, None) => {
+                return Err(Error::new_spanned(
+                    field,
+                    "`rename` and `alias` are only allowed on named fields",
+                ))
+            }
+        };
+        Ok(Field {
+            key,
+            aliases,
+            skip,
+            skip_serializing_if,
+            default,
+        })
+    }
+}
+
+fn rename_rule(meta: &ParseNestedMeta) -> syn::Result<RenameRule> {
+    let name = meta.value()?.parse::<LitStr>()?;
+    RenameRule::from_name(&name.value())
+        .ok_or_else(|| Error::new_spanned(&name, format!("unknown rename rule `{}`", name.value())))
+}
+
+/// Whether `ty` is clearly a type that does not serialize to an object:
+/// a primitive, string, sequence, tuple or `Option`, or a reference to one.
+fn is_primitive(ty: &Type) -> bool {
+    const PRIMITIVES: [&str; 20] = [
+        "bool", "char", "str", "String", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16",
+        "i32", "i64", "i128", "isize", "f32", "f64", "Vec", "Option",
+    ];
+    match ty {
+        Type::Array(_) | Type::Slice(_) | Type::Tuple(_) => true,
+        Type::Reference(reference) => is_primitive(&reference.elem),
+        Type::Paren(paren) => is_primitive(&paren.elem),
+        Type::Group(group) => is_primitive(&group.elem),
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| PRIMITIVES.iter().any(|name| segment.ident == name)),
+        _ => false,
+    }
+}
+
+/// Parses `default` or `default = "path"`.
+fn default_value(meta: &ParseNestedMeta) -> syn::Result<DefaultValue> {
+    if meta.input.peek(Token![=]) {
+        let path = meta.value()?.parse::<LitStr>()?.parse()?;
+        Ok(DefaultValue::Path(path))
+    } else {
+        Ok(DefaultValue::Trait)
+    }
+}
+
+fn json_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("json"))
+}
@@ -0,0 +1,269 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Error, Fields, PathArguments, Type, Variant};
+
+use crate::attr::{self, Container, DefaultValue, Tagging, NEWTYPE_CONTENT_KEY};
+use crate::impl_generics;
+
+pub(crate) fn expand(ast: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let container = Container::from_ast(ast)?;
+
+    let body = match &ast.data {
+        Data::Struct(data) => {
+            let fields = attr::Field::from_fields(&data.fields, container.rename_all)?;
+            fields_from_json(
+                quote!(Self),
+                &data.fields,
+                &fields,
+                container.default.as_ref(),
+                quote!(value),
+            )
+        }
+        Data::Enum(data) => {
+            let variants = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    Ok((
+                        variant,
+                        attr::Variant::from_ast(variant, container.rename_all)?,
+                    ))
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            enum_from_json(&container.tagging, &variants)
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                name,
+                "Deserializable trait only supports structs and enums",
+            ))
+        }
+    };
+
+    let generics = impl_generics(ast, &container, quote!(::yi_json::JsonDeserializable));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::yi_json::JsonDeserializable for #name #ty_generics #where_clause {
+            fn from_json(value: &::yi_json::JsonValue) -> ::std::option::Option<Self> {
+                #body
+            }
+        }
+    })
+}
+
+/// Builds `from_json` for an enum from its variants and their attributes.
+fn enum_from_json(tagging: &Tagging, variants: &[(&Variant, attr::Variant)]) -> TokenStream {
+    let construct = |(variant, attrs): &(&Variant, attr::Variant), content: TokenStream| {
+        let ident = &variant.ident;
+        fields_from_json(
+            quote!(Self::#ident),
+            &variant.fields,
+            &attrs.fields,
+            None,
+            content,
+        )
+    };
+    let (units, others): (Vec<_>, Vec<_>) = variants
+        .iter()
+        .partition(|(variant, _)| matches!(variant.fields, Fields::Unit));
+    let unit_names = units.iter().map(|(_, attrs)| name_pattern(attrs));
+    let unit_idents = units.iter().map(|(variant, _)| &variant.ident);
+
+    match tagging {
+        Tagging::External => {
+            let mut arms = Vec::new();
+            if !units.is_empty() {
+                arms.push(quote! {
+                    ::yi_json::JsonValue::String(name) => match name.as_str() {
+                        #(#unit_names => ::std::option::Option::Some(Self::#unit_idents),)*
+                        _ => ::std::option::Option::None,
+                    },
+                });
+            }
+            if !others.is_empty() {
+                let names = others.iter().map(|(_, attrs)| name_pattern(attrs));
+                let constructs = others
+                    .iter()
+                    .map(|variant| construct(variant, quote!(content)));
+                arms.push(quote! {
+                    ::yi_json::JsonValue::Object(members) if members.len() == 1 => {
+                        let (name, content) = members.iter().next()?;
+                        match name.as_str() {
+                            #(#names => #constructs,)*
+                            _ => ::std::option::Option::None,
+                        }
+                    }
+                });
+            }
+            quote! {
+                match value {
+                    #(#arms)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+        Tagging::Internal { tag } => {
+            let names = variants.iter().map(|(_, attrs)| name_pattern(attrs));
+            let constructs = variants.iter().map(|variant| match variant.0.fields {
+                Fields::Unit => {
+                    let ident = &variant.0.ident;
+                    quote!(::std::option::Option::Some(Self::#ident))
+                }
+                // Content that is not an object was written next to the tag.
+                Fields::Unnamed(_) => {
+                    let whole = construct(variant, quote!(value));
+                    let inner = construct(variant, quote!(value.get(#NEWTYPE_CONTENT_KEY)?));
+                    quote! {
+                        (|| -> ::std::option::Option<Self> { #whole })()
+                            .or_else(|| -> ::std::option::Option<Self> { #inner })
+                    }
+                }
+                // The content is the whole object, tag included.
+                _ => construct(variant, quote!(value)),
+            });
+            quote! {
+                match value.get(#tag)?.as_str()? {
+                    #(#names => #constructs,)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+        Tagging::Adjacent { tag, content } => {
+            let names = variants.iter().map(|(_, attrs)| name_pattern(attrs));
+            let constructs = variants.iter().map(|variant| match variant.0.fields {
+                Fields::Unit => {
+                    let ident = &variant.0.ident;
+                    quote!(::std::option::Option::Some(Self::#ident))
+                }
+                _ => construct(variant, quote!(value.get(#content)?)),
+            });
+            quote! {
+                match value.get(#tag)?.as_str()? {
+                    #(#names => #constructs,)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+        Tagging::Untagged => {
+            let attempts = variants.iter().enumerate().map(|(i, variant)| {
+                let attempt = format_ident!("__variant{}", i);
+                let construct = construct(variant, quote!(value));
+                quote! {
+                    let #attempt = || -> ::std::option::Option<Self> { #construct };
+                    if let ::std::option::Option::Some(found) = #attempt() {
+                        return ::std::option::Option::Some(found);
+                    }
+                }
+            });
+            quote! {
+                #(#attempts)*
+                ::std::option::Option::None
+            }
+        }
+    }
+}
+
+/// An expression producing `default`.
+fn default_value(default: &DefaultValue) -> TokenStream {
+    match default {
+        DefaultValue::Trait => quote!(::std::default::Default::default()),
+        DefaultValue::Path(path) => quote!(#path()),
+    }
+}
+
+/// Whether `ty` is spelled as an `Option`, whose fields become `None` when
+/// their member is missing.
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(ty) if ty.qself.is_none() => ty.path.segments.last().is_some_and(|segment| {
+            segment.ident == "Option"
+                && matches!(segment.arguments, PathArguments::AngleBracketed(_))
+        }),
+        _ => false,
+    }
+}
+
+/// A pattern matching the name of a variant or any of its aliases.
+fn name_pattern(variant: &attr::Variant) -> TokenStream {
+    let name = &variant.name;
+    let aliases = &variant.aliases;
+    quote!(#name #(| #aliases)*)
+}
+
+/// An expression of type `Option<Self>` that reads `fields` from `value`
+/// and builds them with `path`, the inverse of the serializer's
+/// `fields_to_json`. Missing fields of a struct with a container `default`
+/// are taken from that default.
+fn fields_from_json(
+    path: TokenStream,
+    fields: &Fields,
+    attrs: &[attr::Field],
+    container_default: Option<&DefaultValue>,
+    value: TokenStream,
+) -> TokenStream {
+    let types = fields.iter().map(|field| &field.ty);
+    match fields {
+        Fields::Named(named) => {
+            let inits = named.named.iter().zip(attrs).map(|(field, attrs)| {
+                let ident = &field.ident;
+                let ty = &field.ty;
+                let fallback = match (&attrs.default, container_default) {
+                    (Some(default), _) => default_value(default),
+                    (None, Some(_)) => quote!(__default.#ident),
+                    (None, None) if attrs.skip => default_value(&DefaultValue::Trait),
+                    (None, None) if is_option(ty) => quote!(::std::option::Option::None),
+                    (None, None) => quote!(return ::std::option::Option::None),
+                };
+                if attrs.skip {
+                    return quote!(#ident: #fallback);
+                }
+                let key = &attrs.key;
+                let aliases = &attrs.aliases;
+                quote! {
+                    #ident: match value.get(#key)#(.or_else(|| value.get(#aliases)))* {
+                        ::std::option::Option::Some(member) => {
+                            <#ty as ::yi_json::JsonDeserializable>::from_json(member)?
+                        }
+                        ::std::option::Option::None => #fallback,
+                    }
+                }
+            });
+            let default = container_default.map(|default| {
+                let default = default_value(default);
+                quote!(let __default: Self = #default;)
+            });
+            quote!({
+                let value = #value.as_object()?;
+                #default
+                ::std::option::Option::Some(#path {
+                    #(#inits,)*
+                })
+            })
+        }
+        Fields::Unnamed(_) if fields.len() == 1 => quote! {
+            ::std::option::Option::Some(#path(
+                #(<#types as ::yi_json::JsonDeserializable>::from_json(#value)?)*
+            ))
+        },
+        Fields::Unnamed(_) => {
+            let elements: Vec<_> = (0..fields.len())
+                .map(|i| format_ident!("__element{}", i))
+                .collect();
+            quote! {
+                match #value.as_array()?.as_slice() {
+                    [#(#elements),*] => ::std::option::Option::Some(#path(
+                        #(<#types as ::yi_json::JsonDeserializable>::from_json(#elements)?,)*
+                    )),
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+        Fields::Unit => quote! {
+            match #value {
+                ::yi_json::JsonValue::Null => ::std::option::Option::Some(#path),
+                _ => ::std::option::Option::None,
+            }
+        },
+    }
+}
@@ -1,76 +1,108 @@
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Fields};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, DeriveInput, Fields, Generics, Ident, Variant};
+
+use attr::Container;
 
 extern crate proc_macro;
 
-#[proc_macro_derive(JsonDeserializable)]
+mod attr;
+mod case;
+mod de;
+mod ser;
+
+/// Derives `JsonDeserializable`, reading the representation the
+/// `JsonSerializable` derive writes. Missing members, extra array elements
+/// and values of the wrong type make `from_json` return `None`; members the
+/// type does not know are ignored.
+///
+/// A missing member is not an error for a field that has a default or is
+/// spelled as `Option<..>`, which becomes `None`. `#[json(default)]` on a
+/// field uses `Default::default()` and `#[json(default = "path")]` calls
+/// `path()`. On a struct, `#[json(default)]` takes every missing field from
+/// the struct's own default instead.
+#[proc_macro_derive(JsonDeserializable, attributes(json))]
 pub fn json_deserializable_derive(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
-    let name = &ast.ident;
-
-    let gen = match ast.data {
-        Data::Struct(data) => match &data.fields {
-            Fields::Named(fields) => {
-                let mut names = Vec::new();
-                let field_deserialization = fields.named.iter().map(|field| {
-                    let field_name = &field.ident;
-                    let field_type = &field.ty;
-                    names.push(field_name);
-                    quote! {
-                        let #field_name = value.get(stringify!(#field_name))?;
-                        let #field_name = <#field_type>::from_json(#field_name)?;
-                    }
-                });
-                quote! {
-                    impl JsonDeserializable for #name {
-                        fn from_json(value: &JsonValue) -> Option<Self> {
-                            #(#field_deserialization)*
-                            Some (Self { #( #names,)* })
-                        }
-                    }
-                }
-            }
-            _ => {
-                quote! { compile_error!("Deserializable trait only supports structs with named fields"); }
-            }
-        },
-        _ => quote! {compile_error!("Deserializable trait only supports structs");},
-    };
-
-    gen.into()
+    de::expand(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
-#[proc_macro_derive(JsonSerializable)]
+/// Derives `JsonSerializable`.
+///
+/// Structs with named fields become objects, newtype structs their inner
+/// value, tuple structs arrays and unit structs `null`. Enum variants are
+/// written the same way and marked by name: by default as
+/// `{"Variant": content}`, or just `"Variant"` for unit variants. On the
+/// enum, `#[json(tag = "type")]` puts the name into the content object
+/// instead, `#[json(tag = "t", content = "c")]` writes
+/// `{"t": "Variant", "c": content}` and `#[json(untagged)]` writes only the
+/// content. With just a `tag`, a newtype variant whose content is not an
+/// object is written as `{"<tag>": "Variant", "value": content}`; variants
+/// holding a primitive or an `Option` are rejected here.
+///
+/// Field keys and variant names are the Rust identifiers unless renamed:
+/// `#[json(rename = "...")]` renames a named field or a variant, and
+/// `#[json(rename_all = "camelCase")]` renames all fields of a struct, the
+/// variants of an enum, or on a variant, the variant's fields. The rules are
+/// `lowercase`, `UPPERCASE`, `PascalCase`, `camelCase`, `snake_case`,
+/// `SCREAMING_SNAKE_CASE`, `kebab-case` and `SCREAMING-KEBAB-CASE`.
+/// `#[json(alias = "...")]` adds another name accepted when deserializing.
+///
+/// `#[json(skip)]` leaves a field out in both directions, filling it from
+/// its default when deserializing, and `#[json(skip_serializing_if = "path")]`
+/// leaves it out whenever `path(&field)` returns `true`.
+///
+/// Both derives require every type parameter to implement the derived trait.
+/// `#[json(bound = "T::Id: JsonSerializable")]` on the type replaces those
+/// bounds with the given where-clause predicates.
+#[proc_macro_derive(JsonSerializable, attributes(json))]
 pub fn json_serializable_derive(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
-    let name = &ast.ident;
+    ser::expand(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-    let gen = match ast.data {
-        Data::Struct(data) => match &data.fields {
-            Fields::Named(fields) => {
-                let field_serialization = fields.named.iter().map(|field| {
-                    let field_name = &field.ident;
-                    quote! {
-                        (stringify!(#field_name).to_string(), self.#field_name.to_json())
-                    }
-                });
-                quote! {
-                    impl JsonSerializable for #name {
-                        fn to_json(&self) -> JsonValue {
-                            let mut value = Vec::new();
-                            #(value.push(#field_serialization);)*
-                            JsonValue::Object(value)
-                        }
-                    }
-                }
-            }
-            _ => {
-                quote! { compile_error!("Serializable trait only supports structs with named fields"); }
-            }
-        },
-        _ => quote! {compile_error!("Serializable trait only supports structs");},
+/// The generics of the derived impl of `bound`: the type's own, with the
+/// `#[json(bound = "...")]` predicates if given and otherwise `T: bound` for
+/// each type parameter `T`.
+fn impl_generics(ast: &DeriveInput, container: &Container, bound: TokenStream2) -> Generics {
+    let mut generics = ast.generics.clone();
+    let predicates = match &container.bound {
+        Some(predicates) => predicates.clone(),
+        None => ast
+            .generics
+            .type_params()
+            .map(|param| {
+                let ident = &param.ident;
+                parse_quote!(#ident: #bound)
+            })
+            .collect(),
     };
+    generics.make_where_clause().predicates.extend(predicates);
+    generics
+}
+
+/// Names to bind each field of a variant to in a pattern.
+fn bindings(fields: &Fields) -> Vec<Ident> {
+    (0..fields.len())
+        .map(|i| format_ident!("__field{}", i))
+        .collect()
+}
 
-    gen.into()
+/// A pattern matching `variant` that binds its fields to [`bindings`].
+fn variant_pattern(variant: &Variant) -> TokenStream2 {
+    let ident = &variant.ident;
+    let bindings = bindings(&variant.fields);
+    match &variant.fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|field| &field.ident);
+            quote!(Self::#ident { #(#names: #bindings),* })
+        }
+        Fields::Unnamed(_) => quote!(Self::#ident(#(#bindings),*)),
+        Fields::Unit => quote!(Self::#ident),
+    }
 }
@@ -0,0 +1,282 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, Index};
+
+use crate::attr::{self, Container, Tagging, NEWTYPE_CONTENT_KEY};
+use crate::{bindings, impl_generics, variant_pattern};
+
+pub(crate) fn expand(ast: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let container = Container::from_ast(ast)?;
+
+    let (to_json, write_json) = match &ast.data {
+        Data::Struct(data) => {
+            let accessors: Vec<_> = data
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| match &field.ident {
+                    Some(ident) => quote!(&self.#ident),
+                    None => {
+                        let index = Index::from(i);
+                        quote!(&self.#index)
+                    }
+                })
+                .collect();
+            let fields = attr::Field::from_fields(&data.fields, container.rename_all)?;
+            (
+                fields_to_json(&data.fields, &fields, &accessors),
+                write_fields(&data.fields, &fields, &accessors),
+            )
+        }
+        Data::Enum(data) => {
+            let mut to_json = Vec::new();
+            let mut write_json = Vec::new();
+            for variant in &data.variants {
+                let attrs = attr::Variant::from_ast(variant, container.rename_all)?;
+                let pattern = variant_pattern(variant);
+                let bindings: Vec<_> = bindings(&variant.fields)
+                    .into_iter()
+                    .map(|ident| quote!(#ident))
+                    .collect();
+                let (to_json_body, write_json_body) =
+                    variant_bodies(&container.tagging, &attrs, &variant.fields, &bindings);
+                to_json.push(quote!(#pattern => #to_json_body,));
+                write_json.push(quote!(#pattern => #write_json_body,));
+            }
+            (
+                quote!(match self { #(#to_json)* }),
+                quote!(match self { #(#write_json)* }),
+            )
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                name,
+                "Serializable trait only supports structs and enums",
+            ))
+        }
+    };
+
+    let generics = impl_generics(ast, &container, quote!(::yi_json::JsonSerializable));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::yi_json::JsonSerializable for #name #ty_generics #where_clause {
+            fn to_json(&self) -> ::yi_json::JsonValue {
+                #to_json
+            }
+
+            fn write_json<__W: ::std::io::Write>(
+                &self,
+                writer: &mut ::yi_json::JsonWriter<__W>,
+            ) -> ::std::result::Result<(), ::yi_json::WriteError> {
+                #write_json
+            }
+        }
+    })
+}
+
+/// Returns the `to_json` and `write_json` bodies for one variant, whose
+/// fields are bound to `bindings`.
+fn variant_bodies(
+    tagging: &Tagging,
+    variant: &attr::Variant,
+    fields: &Fields,
+    bindings: &[TokenStream],
+) -> (TokenStream, TokenStream) {
+    let name = &variant.name;
+    let content = fields_to_json(fields, &variant.fields, bindings);
+    let write_content = write_fields(fields, &variant.fields, bindings);
+    match tagging {
+        Tagging::External => match fields {
+            Fields::Unit => (
+                quote!(::yi_json::JsonValue::String(#name.to_string())),
+                quote!(writer.string(#name)),
+            ),
+            _ => (
+                quote!({
+                    let mut members = ::yi_json::JsonMap::with_capacity(1);
+                    members.insert(#name.to_string(), #content);
+                    ::yi_json::JsonValue::Object(members)
+                }),
+                quote!({
+                    writer.begin_object()?;
+                    writer.key(#name)?;
+                    #write_content?;
+                    writer.end_object()
+                }),
+            ),
+        },
+        Tagging::Internal { tag } => match fields {
+            // The tag is added to the content if it is an object, and
+            // otherwise written next to the content.
+            Fields::Unnamed(_) => (
+                quote!({
+                    let mut members = ::yi_json::JsonMap::new();
+                    members.insert(#tag.to_string(), ::yi_json::JsonValue::from(#name));
+                    match #content {
+                        ::yi_json::JsonValue::Object(content) => members.extend(content),
+                        content => {
+                            members.insert(#NEWTYPE_CONTENT_KEY.to_string(), content);
+                        }
+                    }
+                    ::yi_json::JsonValue::Object(members)
+                }),
+                quote!(writer.value(&::yi_json::JsonSerializable::to_json(self))),
+            ),
+            _ => {
+                let members = insert_members(&variant.fields, bindings);
+                let write_members = write_members(&variant.fields, bindings);
+                (
+                    quote!({
+                        let mut members = ::yi_json::JsonMap::new();
+                        members.insert(#tag.to_string(), ::yi_json::JsonValue::from(#name));
+                        #members
+                        ::yi_json::JsonValue::Object(members)
+                    }),
+                    quote!({
+                        writer.begin_object()?;
+                        writer.key(#tag)?;
+                        writer.string(#name)?;
+                        #write_members
+                        writer.end_object()
+                    }),
+                )
+            }
+        },
+        Tagging::Adjacent {
+            tag,
+            content: content_key,
+        } => match fields {
+            Fields::Unit => (
+                quote!({
+                    let mut members = ::yi_json::JsonMap::with_capacity(1);
+                    members.insert(#tag.to_string(), ::yi_json::JsonValue::from(#name));
+                    ::yi_json::JsonValue::Object(members)
+                }),
+                quote!({
+                    writer.begin_object()?;
+                    writer.key(#tag)?;
+                    writer.string(#name)?;
+                    writer.end_object()
+                }),
+            ),
+            _ => (
+                quote!({
+                    let mut members = ::yi_json::JsonMap::with_capacity(2);
+                    members.insert(#tag.to_string(), ::yi_json::JsonValue::from(#name));
+                    members.insert(#content_key.to_string(), #content);
+                    ::yi_json::JsonValue::Object(members)
+                }),
+                quote!({
+                    writer.begin_object()?;
+                    writer.key(#tag)?;
+                    writer.string(#name)?;
+                    writer.key(#content_key)?;
+                    #write_content?;
+                    writer.end_object()
+                }),
+            ),
+        },
+        Tagging::Untagged => (content, write_content),
+    }
+}
+
+/// An expression building the `JsonValue` for fields reached through
+/// `accessors`: an object for named fields, the value itself for a single
+/// unnamed field, an array for several and `null` for none.
+fn fields_to_json(
+    fields: &Fields,
+    attrs: &[attr::Field],
+    accessors: &[TokenStream],
+) -> TokenStream {
+    match fields {
+        Fields::Named(_) => {
+            let members = insert_members(attrs, accessors);
+            let len = accessors.len();
+            quote!({
+                let mut members = ::yi_json::JsonMap::with_capacity(#len);
+                #members
+                ::yi_json::JsonValue::Object(members)
+            })
+        }
+        Fields::Unnamed(_) if accessors.len() == 1 => {
+            let accessor = &accessors[0];
+            quote!(::yi_json::JsonSerializable::to_json(#accessor))
+        }
+        Fields::Unnamed(_) => quote! {
+            ::yi_json::JsonValue::Array(::std::vec![
+                #(::yi_json::JsonSerializable::to_json(#accessors),)*
+            ])
+        },
+        Fields::Unit => quote!(::yi_json::JsonValue::Null),
+    }
+}
+
+/// Statements inserting named fields into a `JsonMap` called `members`.
+fn insert_members(attrs: &[attr::Field], accessors: &[TokenStream]) -> TokenStream {
+    each_member(
+        attrs,
+        accessors,
+        |key, accessor| quote!(members.insert(#key.to_string(), ::yi_json::JsonSerializable::to_json(#accessor));),
+    )
+}
+
+/// Like [`fields_to_json`], but an expression writing the fields through
+/// `writer`.
+fn write_fields(fields: &Fields, attrs: &[attr::Field], accessors: &[TokenStream]) -> TokenStream {
+    match fields {
+        Fields::Named(_) => {
+            let members = write_members(attrs, accessors);
+            quote!({
+                writer.begin_object()?;
+                #members
+                writer.end_object()
+            })
+        }
+        Fields::Unnamed(_) if accessors.len() == 1 => {
+            let accessor = &accessors[0];
+            quote!(::yi_json::JsonSerializable::write_json(#accessor, writer))
+        }
+        Fields::Unnamed(_) => quote!({
+            writer.begin_array()?;
+            #(::yi_json::JsonSerializable::write_json(#accessors, writer)?;)*
+            writer.end_array()
+        }),
+        Fields::Unit => quote!(writer.null()),
+    }
+}
+
+/// Statements writing the keys and values of named fields.
+fn write_members(attrs: &[attr::Field], accessors: &[TokenStream]) -> TokenStream {
+    each_member(attrs, accessors, |key, accessor| {
+        quote! {
+            writer.key(#key)?;
+            ::yi_json::JsonSerializable::write_json(#accessor, writer)?;
+        }
+    })
+}
+
+/// Joins the statements `member` makes for each named field that is
+/// serialized, guarding them with the field's `skip_serializing_if`.
+fn each_member(
+    attrs: &[attr::Field],
+    accessors: &[TokenStream],
+    member: impl Fn(&str, &TokenStream) -> TokenStream,
+) -> TokenStream {
+    attrs
+        .iter()
+        .zip(accessors)
+        .filter(|(field, _)| !field.skip)
+        .map(|(field, accessor)| {
+            let statements = member(&field.key, accessor);
+            match &field.skip_serializing_if {
+                Some(predicate) => quote! {
+                    if !#predicate(#accessor) {
+                        #statements
+                    }
+                },
+                None => statements,
+            }
+        })
+        .collect()
+}
@@ -30,13 +30,13 @@ fn main() {
 
     // Parse the JSON string into a JsonValue
     match parser.parse() {
-        Some(json_value) => {
+        Ok(json_value) => {
             // Display the parsed JSON value
             println!("Parsed JSON:\n{:#?}", json_value);
         }
-        None => {
+        Err(err) => {
             // Handle parsing errors
-            eprintln!("Error parsing JSON");
+            eprintln!("Error parsing JSON: {}", err);
         }
     }
 }
\ No newline at end of file
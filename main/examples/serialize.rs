@@ -1,4 +1,4 @@
-use yi_json::{JsonSerializable, JsonDeserializable, JsonValue};
+use yi_json::{JsonSerializable, JsonDeserializable, PrettyPrinter};
 
 #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
 struct Person {
@@ -19,7 +19,11 @@ fn main() {
     let json_representation = person.to_json();
 
     // Displaying the serialized JSON
-    println!("Serialized JSON: {:?}", json_representation);
+    println!("Serialized JSON: {}", json_representation);
+    println!(
+        "Pretty JSON:\n{}",
+        PrettyPrinter::new().to_string(&json_representation)
+    );
 
     // Deserializing JSON back to a Person instance
     let deserialized_person = Person::from_json(&json_representation);
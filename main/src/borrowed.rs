@@ -0,0 +1,144 @@
+use std::borrow::Cow;
+
+use crate::json_value::JsonValue;
+use crate::number::JsonNumber;
+
+/// A JSON value whose strings and object keys borrow from the input where
+/// possible, as produced by
+/// [`JsonParser::parse_borrowed`](crate::parser::JsonParser::parse_borrowed).
+///
+/// Strings containing escape sequences have to be unescaped and are owned;
+/// all others are slices of the input. Values parsed from a reader are
+/// always owned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BorrowedJsonValue<'a> {
+    Object(Vec<(Cow<'a, str>, BorrowedJsonValue<'a>)>),
+    Array(Vec<BorrowedJsonValue<'a>>),
+    String(Cow<'a, str>),
+    Number(JsonNumber),
+    Bool(bool),
+    Null,
+}
+
+impl<'a> BorrowedJsonValue<'a> {
+    pub fn get_arr(&self, index: usize) -> Option<&BorrowedJsonValue<'a>> {
+        match self {
+            BorrowedJsonValue::Array(arr) => arr.get(index),
+            _ => None,
+        }
+    }
+
+    /// Looks up a member by key. Objects keep every member as parsed, so
+    /// with duplicate keys the last one wins, as in [`JsonValue`].
+    pub fn get(&self, index: &str) -> Option<&BorrowedJsonValue<'a>> {
+        match self {
+            BorrowedJsonValue::Object(obj) => obj
+                .iter()
+                .rev()
+                .find(|(name, _)| name == index)
+                .map(|(_, val)| val),
+            _ => None,
+        }
+    }
+
+    /// Converts into a [`JsonValue`], copying the strings that are still
+    /// borrowed.
+    pub fn into_owned(self) -> JsonValue {
+        match self {
+            BorrowedJsonValue::Object(obj) => JsonValue::Object(
+                obj.into_iter()
+                    .map(|(name, val)| (name.into_owned(), val.into_owned()))
+                    .collect(),
+            ),
+            BorrowedJsonValue::Array(arr) => {
+                JsonValue::Array(arr.into_iter().map(Self::into_owned).collect())
+            }
+            BorrowedJsonValue::String(s) => JsonValue::String(s.into_owned()),
+            BorrowedJsonValue::Number(n) => JsonValue::Number(n),
+            BorrowedJsonValue::Bool(b) => JsonValue::Bool(b),
+            BorrowedJsonValue::Null => JsonValue::Null,
+        }
+    }
+}
+
+impl From<BorrowedJsonValue<'_>> for JsonValue {
+    fn from(value: BorrowedJsonValue<'_>) -> Self {
+        value.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::JsonParser;
+
+    /// Returns the contents of a string value and whether they are borrowed.
+    fn string_of<'v>(value: Option<&'v BorrowedJsonValue<'_>>) -> (&'v str, bool) {
+        match value {
+            Some(BorrowedJsonValue::String(s)) => (s, matches!(s, Cow::Borrowed(_))),
+            other => panic!("expected a string: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_borrowed() {
+        let input = r#"{"name": "John", "quote": "say \"hi\"", "tags": ["a", "éé"], "n": 1.5}"#;
+        let value = JsonParser::new(input).parse_borrowed().unwrap();
+
+        let BorrowedJsonValue::Object(members) = &value else {
+            panic!("expected an object: {:?}", value);
+        };
+        assert!(members
+            .iter()
+            .all(|(name, _)| matches!(name, Cow::Borrowed(_))));
+        assert_eq!(string_of(value.get("name")), ("John", true));
+        assert_eq!(string_of(value.get("quote")), ("say \"hi\"", false));
+        assert_eq!(
+            string_of(value.get("tags").and_then(|tags| tags.get_arr(1))),
+            ("\u{e9}\u{e9}", true)
+        );
+
+        assert_eq!(value.into_owned(), JsonParser::new(input).parse().unwrap());
+    }
+
+    #[test]
+    fn test_parse_borrowed_errors_and_positions() {
+        let err = JsonParser::new("{\n  \"a\": \"x\",\n  \"b\" 2\n}")
+            .parse_borrowed()
+            .unwrap_err();
+        assert_eq!((err.offset, err.line, err.column), (20, 3, 7));
+
+        let err = JsonParser::new("[\"abc]").parse_borrowed().unwrap_err();
+        assert_eq!(err.kind, crate::ParseErrorKind::UnclosedString);
+
+        let err = JsonParser::new("[\"a\u{1}\"]")
+            .with_strict(true)
+            .parse_borrowed()
+            .unwrap_err();
+        assert_eq!(err.kind, crate::ParseErrorKind::ControlCharacter);
+    }
+
+    #[test]
+    fn test_parse_borrowed_duplicate_keys() {
+        let input = r#"{"a": 1, "b": 2, "a": 3}"#;
+        let borrowed = JsonParser::new(input).parse_borrowed().unwrap();
+        let owned = JsonParser::new(input).parse().unwrap();
+
+        assert_eq!(
+            borrowed.get("a"),
+            Some(&BorrowedJsonValue::Number(3.into()))
+        );
+        assert_eq!(owned.get("a"), Some(&JsonValue::Number(3.into())));
+        assert_eq!(borrowed.into_owned(), owned);
+    }
+
+    #[test]
+    fn test_parse_borrowed_from_reader_is_owned() {
+        let input = r#"["a", "b"]"#;
+        let value = JsonParser::from_reader(input.as_bytes())
+            .parse_borrowed()
+            .unwrap();
+
+        assert_eq!(string_of(value.get_arr(0)), ("a", false));
+    }
+}
@@ -0,0 +1,373 @@
+use std::fmt;
+
+use crate::json_value::JsonValue;
+use crate::map::JsonMap;
+use crate::number::JsonNumber;
+use crate::pointer::JsonPointer;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// Compares two values with the default [`DiffOptions`] and lists where
+/// they differ.
+pub fn json_diff(a: &JsonValue, b: &JsonValue) -> JsonDiff {
+    DiffOptions::new().diff(a, b)
+}
+
+/// How [`json_diff`] compares values.
+///
+/// By default object members may appear in any order, like with `==`, and
+/// numbers have to be equal.
+#[derive(Debug, Clone, Copy)]
+pub struct DiffOptions {
+    ignore_key_order: bool,
+    float_epsilon: Option<f64>,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            ignore_key_order: true,
+            float_epsilon: None,
+        }
+    }
+}
+
+impl DiffOptions {
+    pub fn new() -> DiffOptions {
+        Self::default()
+    }
+
+    /// With `false`, an object whose members are in a different order is
+    /// reported as changed, even if the members are equal.
+    pub fn with_ignore_key_order(mut self, ignore: bool) -> Self {
+        self.ignore_key_order = ignore;
+        self
+    }
+
+    /// Treats two numbers as equal if they differ by at most `epsilon`.
+    pub fn with_float_epsilon(mut self, epsilon: f64) -> Self {
+        self.float_epsilon = Some(epsilon);
+        self
+    }
+
+    pub fn diff(&self, a: &JsonValue, b: &JsonValue) -> JsonDiff {
+        let mut changes = Vec::new();
+        self.walk(a, b, &mut JsonPointer::root(), &mut changes);
+        JsonDiff { changes }
+    }
+
+    fn walk(
+        &self,
+        a: &JsonValue,
+        b: &JsonValue,
+        path: &mut JsonPointer,
+        changes: &mut Vec<Change>,
+    ) {
+        match (a, b) {
+            (JsonValue::Object(a_obj), JsonValue::Object(b_obj)) => {
+                for (key, old) in a_obj {
+                    path.push(key.as_str());
+                    match b_obj.get(key) {
+                        Some(new) => self.walk(old, new, path, changes),
+                        None => changes.push(Change::Removed {
+                            path: path.clone(),
+                            value: old.clone(),
+                        }),
+                    }
+                    path.pop();
+                }
+                for (key, new) in b_obj.iter().filter(|(key, _)| !a_obj.contains_key(key)) {
+                    path.push(key.as_str());
+                    changes.push(Change::Added {
+                        path: path.clone(),
+                        value: new.clone(),
+                    });
+                    path.pop();
+                }
+                if !self.ignore_key_order && !same_key_order(a_obj, b_obj) {
+                    changes.push(Change::Changed {
+                        path: path.clone(),
+                        old: a.clone(),
+                        new: b.clone(),
+                    });
+                }
+            }
+            (JsonValue::Array(a_arr), JsonValue::Array(b_arr)) => {
+                for (i, (old, new)) in a_arr.iter().zip(b_arr).enumerate() {
+                    path.push_index(i);
+                    self.walk(old, new, path, changes);
+                    path.pop();
+                }
+                for (i, old) in a_arr.iter().enumerate().skip(b_arr.len()) {
+                    path.push_index(i);
+                    changes.push(Change::Removed {
+                        path: path.clone(),
+                        value: old.clone(),
+                    });
+                    path.pop();
+                }
+                for (i, new) in b_arr.iter().enumerate().skip(a_arr.len()) {
+                    path.push_index(i);
+                    changes.push(Change::Added {
+                        path: path.clone(),
+                        value: new.clone(),
+                    });
+                    path.pop();
+                }
+            }
+            (JsonValue::Number(a_num), JsonValue::Number(b_num))
+                if self.numbers_equal(a_num, b_num) => {}
+            _ if a == b => {}
+            _ => changes.push(Change::Changed {
+                path: path.clone(),
+                old: a.clone(),
+                new: b.clone(),
+            }),
+        }
+    }
+
+    fn numbers_equal(&self, a: &JsonNumber, b: &JsonNumber) -> bool {
+        match self.float_epsilon {
+            Some(epsilon) => a == b || (a.as_f64() - b.as_f64()).abs() <= epsilon,
+            None => a == b,
+        }
+    }
+}
+
+/// Whether the members both objects have come in the same order.
+fn same_key_order(a: &JsonMap, b: &JsonMap) -> bool {
+    a.keys()
+        .filter(|key| b.contains_key(key))
+        .eq(b.keys().filter(|key| a.contains_key(key)))
+}
+
+/// A difference found by [`json_diff`], located by a JSON Pointer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The member or element is only in the second value.
+    Added { path: JsonPointer, value: JsonValue },
+    /// The member or element is only in the first value.
+    Removed { path: JsonPointer, value: JsonValue },
+    Changed {
+        path: JsonPointer,
+        old: JsonValue,
+        new: JsonValue,
+    },
+}
+
+impl Change {
+    pub fn path(&self) -> &JsonPointer {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. } => path,
+        }
+    }
+
+    fn render(&self, f: &mut fmt::Formatter<'_>, color: bool) -> fmt::Result {
+        let (sign, paint) = match self {
+            Change::Added { .. } => ('+', GREEN),
+            Change::Removed { .. } => ('-', RED),
+            Change::Changed { .. } => ('~', YELLOW),
+        };
+        let (paint, reset) = match color {
+            true => (paint, RESET),
+            false => ("", ""),
+        };
+        let path = match self.path().is_root() {
+            true => "(root)".to_string(),
+            false => self.path().to_string(),
+        };
+        write!(f, "{}{} {}: ", paint, sign, path)?;
+        match self {
+            Change::Added { value, .. } | Change::Removed { value, .. } => write!(f, "{}", value)?,
+            Change::Changed { old, new, .. } => write!(f, "{} -> {}", old, new)?,
+        }
+        f.write_str(reset)
+    }
+}
+
+/// The differences between two values, in document order.
+///
+/// Displaying it lists one change per line, like `~ /a/0: 1 -> 2`, with `+`
+/// for added and `-` for removed values; [`colored`](Self::colored) adds
+/// ANSI colors for terminal output.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct JsonDiff {
+    changes: Vec<Change>,
+}
+
+impl JsonDiff {
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Change> {
+        self.changes.iter()
+    }
+
+    /// Returns a rendering of the changes colored with ANSI escapes: green
+    /// for added, red for removed and yellow for changed values.
+    pub fn colored(&self) -> Colored<'_> {
+        Colored(self)
+    }
+
+    fn render(&self, f: &mut fmt::Formatter<'_>, color: bool) -> fmt::Result {
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            change.render(f, color)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for JsonDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.render(f, false)
+    }
+}
+
+/// A [`JsonDiff`] displayed with colors, from [`JsonDiff::colored`].
+pub struct Colored<'a>(&'a JsonDiff);
+
+impl fmt::Display for Colored<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.render(f, true)
+    }
+}
+
+impl<'a> IntoIterator for &'a JsonDiff {
+    type Item = &'a Change;
+    type IntoIter = std::slice::Iter<'a, Change>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for JsonDiff {
+    type Item = Change;
+    type IntoIter = std::vec::IntoIter<Change>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_json_eq, json};
+
+    fn pointer(s: &str) -> JsonPointer {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_changes() {
+        let a = json!({
+            "name": "John",
+            "courses": [{"title": "Math", "credits": 5}, {"title": "History"}],
+            "age": 30,
+        });
+        let b = json!({
+            "name": "John",
+            "courses": [{"title": "Math", "credits": 4}],
+            "city": "NY",
+        });
+
+        assert_eq!(
+            json_diff(&a, &b).changes(),
+            [
+                Change::Changed {
+                    path: pointer("/courses/0/credits"),
+                    old: json!(5),
+                    new: json!(4),
+                },
+                Change::Removed {
+                    path: pointer("/courses/1"),
+                    value: json!({"title": "History"}),
+                },
+                Change::Removed {
+                    path: pointer("/age"),
+                    value: json!(30),
+                },
+                Change::Added {
+                    path: pointer("/city"),
+                    value: json!("NY"),
+                },
+            ]
+        );
+        assert!(json_diff(&a, &a).is_empty());
+        assert_eq!(
+            json_diff(&json!(1), &json!("1")).changes()[0].path(),
+            &JsonPointer::root()
+        );
+    }
+
+    #[test]
+    fn test_key_order() {
+        let a = json!({"a": 1, "b": 2});
+        let b = json!({"b": 2, "a": 1});
+
+        assert!(json_diff(&a, &b).is_empty());
+        let diff = DiffOptions::new().with_ignore_key_order(false).diff(&a, &b);
+        assert_eq!(
+            diff.changes(),
+            [Change::Changed {
+                path: JsonPointer::root(),
+                old: a.clone(),
+                new: b.clone(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_float_epsilon() {
+        let a = json!({"x": 0.1 + 0.2, "y": 1});
+        let b = json!({"x": 0.3, "y": 1.05});
+
+        assert_eq!(json_diff(&a, &b).len(), 2);
+        let options = DiffOptions::new().with_float_epsilon(1e-9);
+        assert_eq!(options.diff(&a, &b).changes()[0].path(), &pointer("/y"));
+        assert!(DiffOptions::new()
+            .with_float_epsilon(0.1)
+            .diff(&a, &b)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_rendering() {
+        let diff = json_diff(&json!({"a": [1, 2], "b": true}), &json!({"a": [1, 3, 4]}));
+
+        assert_eq!(diff.to_string(), "~ /a/1: 2 -> 3\n+ /a/2: 4\n- /b: true");
+        assert_eq!(
+            diff.colored().to_string(),
+            "\x1b[33m~ /a/1: 2 -> 3\x1b[0m\n\x1b[32m+ /a/2: 4\x1b[0m\n\x1b[31m- /b: true\x1b[0m"
+        );
+        assert_eq!(
+            json_diff(&json!(null), &json!([])).to_string(),
+            "~ (root): null -> []"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "~ /a/0: 1 -> 2")]
+    fn test_assert_json_eq() {
+        assert_json_eq!(json!({"b": 1, "a": [1]}), json!({"a": [1], "b": 1}));
+        assert_json_eq!(json!({"a": [1]}), json!({"a": [2]}));
+    }
+}
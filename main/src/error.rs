@@ -0,0 +1,322 @@
+use std::sync::Arc;
+use std::{fmt, io};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnexpectedEof,
+    UnexpectedToken,
+    UnclosedString,
+    InvalidEscape,
+    LoneSurrogate,
+    ControlCharacter,
+    InvalidNumber,
+    ExpectedKey,
+    ExpectedColon,
+    ExpectedComma,
+    TrailingCharacters,
+    RecursionLimitExceeded,
+    InvalidUtf8,
+    Io,
+}
+
+impl ParseErrorKind {
+    fn description(&self) -> &'static str {
+        match self {
+            ParseErrorKind::UnexpectedEof => "unexpected end of input",
+            ParseErrorKind::UnexpectedToken => "unexpected token",
+            ParseErrorKind::UnclosedString => "unclosed string",
+            ParseErrorKind::InvalidEscape => "invalid escape sequence",
+            ParseErrorKind::LoneSurrogate => "lone or mismatched UTF-16 surrogate",
+            ParseErrorKind::ControlCharacter => "unescaped control character in string",
+            ParseErrorKind::InvalidNumber => "invalid number",
+            ParseErrorKind::ExpectedKey => "expected string as object key",
+            ParseErrorKind::ExpectedColon => "expected colon after object key",
+            ParseErrorKind::ExpectedComma => "expected comma or closing bracket",
+            ParseErrorKind::TrailingCharacters => "trailing characters after value",
+            ParseErrorKind::RecursionLimitExceeded => "recursion limit exceeded",
+            ParseErrorKind::InvalidUtf8 => "invalid UTF-8",
+            ParseErrorKind::Io => "I/O error",
+        }
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+/// Error returned when the input is not valid JSON, or could not be read.
+///
+/// `offset` is a byte offset into the input, `line` and `column` are
+/// 1-based, with the column counted in characters. For
+/// [`ParseErrorKind::Io`] they give the position reached when the reader
+/// failed, and [`ParseError::io_error`] gives the underlying error.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    io_error: Option<Arc<io::Error>>,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, offset: usize, line: usize, column: usize) -> ParseError {
+        ParseError {
+            kind,
+            offset,
+            line,
+            column,
+            io_error: None,
+        }
+    }
+
+    pub(crate) fn from_io(err: io::Error, offset: usize, line: usize, column: usize) -> ParseError {
+        ParseError {
+            io_error: Some(Arc::new(err)),
+            ..ParseError::new(ParseErrorKind::Io, offset, line, column)
+        }
+    }
+
+    pub fn is_io(&self) -> bool {
+        self.kind == ParseErrorKind::Io
+    }
+
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.io_error.as_deref()
+    }
+}
+
+/// Compares kind and position only; the underlying I/O errors of two
+/// [`ParseErrorKind::Io`] errors are not compared.
+impl PartialEq for ParseError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.offset == other.offset
+            && self.line == other.line
+            && self.column == other.column
+    }
+}
+
+impl Eq for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.io_error {
+            Some(err) => write!(f, "{}: {}", self.kind, err)?,
+            None => write!(f, "{}", self.kind)?,
+        }
+        write!(f, " at line {} column {}", self.line, self.column)
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.io_error
+            .as_deref()
+            .map(|err| err as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// Error returned by [`JsonWriter`](crate::writer::JsonWriter) when the
+/// underlying writer fails or the calls do not describe a single well-formed
+/// JSON value.
+#[derive(Debug)]
+pub enum WriteError {
+    Io(io::Error),
+    /// `key` was called outside an object or twice in a row.
+    UnexpectedKey,
+    /// A value was written inside an object without a preceding `key`.
+    MissingKey,
+    /// `end_object`/`end_array` did not match the innermost open container,
+    /// or an object was closed right after a key.
+    MismatchedEnd,
+    /// A second value was written at the top level.
+    MultipleRootValues,
+    /// `finish` was called with containers still open or before any value.
+    Incomplete,
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::Io(err) => write!(f, "I/O error: {}", err),
+            WriteError::UnexpectedKey => f.write_str("key written outside an object"),
+            WriteError::MissingKey => f.write_str("object value written without a key"),
+            WriteError::MismatchedEnd => f.write_str("container end does not match its start"),
+            WriteError::MultipleRootValues => f.write_str("more than one top-level value"),
+            WriteError::Incomplete => f.write_str("document is incomplete"),
+        }
+    }
+}
+
+impl std::error::Error for WriteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WriteError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WriteError {
+    fn from(err: io::Error) -> Self {
+        WriteError::Io(err)
+    }
+}
+
+/// Error returned when parsing a [`JsonPointer`](crate::pointer::JsonPointer)
+/// or when it cannot be used to insert or remove a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerError {
+    /// A non-empty pointer does not start with `/`.
+    MissingSlash,
+    /// `~` is not followed by `0` or `1`.
+    InvalidEscape,
+    /// An array was indexed with a token that is not a number without
+    /// leading zeros, or `-` where only existing elements are allowed.
+    InvalidIndex,
+    /// An array index is past the end of the array.
+    IndexOutOfBounds,
+    /// A member or a value on the way to the target does not exist.
+    NotFound,
+    /// A value on the way to the target is neither an object nor an array.
+    NotAContainer,
+    /// The operation needs a parent, which the root pointer does not have.
+    Root,
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PointerError::MissingSlash => "pointer does not start with '/'",
+            PointerError::InvalidEscape => "invalid '~' escape in pointer",
+            PointerError::InvalidIndex => "invalid array index in pointer",
+            PointerError::IndexOutOfBounds => "array index out of bounds",
+            PointerError::NotFound => "pointer does not refer to an existing value",
+            PointerError::NotAContainer => "pointer goes through a value that is not a container",
+            PointerError::Root => "operation is not allowed on the root pointer",
+        })
+    }
+}
+
+impl std::error::Error for PointerError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathErrorKind {
+    UnexpectedEof,
+    UnexpectedCharacter,
+    /// An index or slice bound is malformed or outside the range of exactly
+    /// representable integers, ±(2^53 - 1).
+    InvalidNumber,
+    InvalidEscape,
+    UnknownFunction,
+    /// A function was called with the wrong number or kind of arguments,
+    /// or its result was used where it does not fit.
+    InvalidFunctionCall,
+    /// A query that can select more than one node was used in a comparison.
+    NonSingularQuery,
+    /// Parentheses, filters or function calls are nested too deeply.
+    NestingTooDeep,
+}
+
+impl PathErrorKind {
+    fn description(&self) -> &'static str {
+        match self {
+            PathErrorKind::UnexpectedEof => "unexpected end of query",
+            PathErrorKind::UnexpectedCharacter => "unexpected character",
+            PathErrorKind::InvalidNumber => "invalid number",
+            PathErrorKind::InvalidEscape => "invalid escape sequence",
+            PathErrorKind::UnknownFunction => "unknown function",
+            PathErrorKind::InvalidFunctionCall => "invalid function call",
+            PathErrorKind::NonSingularQuery => "non-singular query in comparison",
+            PathErrorKind::NestingTooDeep => "nesting too deep",
+        }
+    }
+}
+
+impl fmt::Display for PathErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+/// Error returned when a JSONPath query is malformed. `offset` is the byte
+/// offset into the query where the problem was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathError {
+    pub kind: PathErrorKind,
+    pub offset: usize,
+}
+
+impl PathError {
+    pub fn new(kind: PathErrorKind, offset: usize) -> PathError {
+        PathError { kind, offset }
+    }
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.kind, self.offset)
+    }
+}
+
+impl std::error::Error for PathError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchErrorKind {
+    /// The patch is not an array.
+    NotAnArray,
+    /// The operation is not an object with a known `op` and the members
+    /// that operation needs.
+    InvalidOperation,
+    /// `path` or `from` is malformed, or does not lead to a place the
+    /// operation can act on.
+    Pointer(PointerError),
+    /// A `test` operation found a different value.
+    TestFailed,
+    /// A `move` operation would move a value into one of its own children.
+    MoveIntoChild,
+}
+
+impl fmt::Display for PatchErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchErrorKind::NotAnArray => f.write_str("patch is not an array"),
+            PatchErrorKind::InvalidOperation => f.write_str("invalid operation"),
+            PatchErrorKind::Pointer(err) => write!(f, "{}", err),
+            PatchErrorKind::TestFailed => f.write_str("test failed"),
+            PatchErrorKind::MoveIntoChild => f.write_str("cannot move a value into itself"),
+        }
+    }
+}
+
+impl From<PointerError> for PatchErrorKind {
+    fn from(err: PointerError) -> Self {
+        PatchErrorKind::Pointer(err)
+    }
+}
+
+/// Error returned when a [`JsonPatch`](crate::patch::JsonPatch) cannot be
+/// read or applied. `index` is the position of the failing operation in the
+/// patch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatchError {
+    pub kind: PatchErrorKind,
+    pub index: usize,
+}
+
+impl PatchError {
+    pub fn new(kind: PatchErrorKind, index: usize) -> PatchError {
+        PatchError { kind, index }
+    }
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in operation {}", self.kind, self.index)
+    }
+}
+
+impl std::error::Error for PatchError {}
@@ -0,0 +1,411 @@
+use crate::error::{ParseError, ParseErrorKind};
+use crate::lexer::{Lexer, Token};
+use crate::number::JsonNumber;
+use crate::parser::DEFAULT_MAX_DEPTH;
+
+/// A single step of a JSON document, as produced by [`EventParser`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonEvent {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key(String),
+    String(String),
+    Number(JsonNumber),
+    Bool(bool),
+    Null,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// A value is expected: at the root, after a colon or after a comma in
+    /// an array.
+    Value,
+    /// Right after `[`.
+    ValueOrEnd,
+    /// Right after `{`.
+    KeyOrEnd,
+    /// After a comma in an object.
+    Key,
+    Colon,
+    CommaOrEnd,
+    /// The root value is complete; only the end of input may follow.
+    Finished,
+    /// An error was returned; no further events are produced.
+    Failed,
+}
+
+/// Pull parser that reports a document as a sequence of [`JsonEvent`]s
+/// instead of building a [`JsonValue`](crate::JsonValue).
+///
+/// Every event comes with its depth, the number of arrays and objects
+/// enclosing it. A `StartObject` or `StartArray` has the same depth as its
+/// matching end event, and keys and values directly inside it are one
+/// deeper.
+///
+/// The input must hold exactly one value, as for
+/// [`JsonParser::parse`](crate::parser::JsonParser::parse), but errors are
+/// only reported once the parser reaches them, after the events preceding
+/// them have been returned.
+pub struct EventParser<'a> {
+    lexer: Lexer<'a>,
+    /// One entry per open container, `true` for objects.
+    stack: Vec<bool>,
+    state: State,
+    max_depth: usize,
+    /// An event read by `skip_value` that did not belong to the skipped
+    /// value.
+    pending: Option<(JsonEvent, usize)>,
+}
+
+impl<'a> EventParser<'a> {
+    pub fn new(input: &str) -> EventParser<'_> {
+        Self::from_lexer(Lexer::new(input))
+    }
+
+    /// See [`JsonParser::from_reader`](crate::parser::JsonParser::from_reader).
+    pub fn from_reader<R: std::io::Read + 'a>(reader: R) -> EventParser<'a> {
+        Self::from_lexer(Lexer::from_reader(reader))
+    }
+
+    pub fn from_lexer(lexer: Lexer<'_>) -> EventParser<'_> {
+        EventParser {
+            lexer,
+            stack: Vec::new(),
+            state: State::Value,
+            max_depth: DEFAULT_MAX_DEPTH,
+            pending: None,
+        }
+    }
+
+    /// See [`JsonParser::with_max_depth`](crate::parser::JsonParser::with_max_depth).
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// See [`Lexer::with_strict`].
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.lexer = self.lexer.with_strict(strict);
+        self
+    }
+
+    /// See [`Lexer::with_lossy_surrogates`].
+    pub fn with_lossy_surrogates(mut self, lossy_surrogates: bool) -> Self {
+        self.lexer = self.lexer.with_lossy_surrogates(lossy_surrogates);
+        self
+    }
+
+    /// See [`Lexer::with_arbitrary_precision`].
+    pub fn with_arbitrary_precision(mut self, arbitrary_precision: bool) -> Self {
+        self.lexer = self.lexer.with_arbitrary_precision(arbitrary_precision);
+        self
+    }
+
+    /// Returns the next event and its depth, or `None` at the end of the
+    /// document. After an error has been returned, always returns `None`.
+    pub fn next_event(&mut self) -> Result<Option<(JsonEvent, usize)>, ParseError> {
+        if let Some(event) = self.pending.take() {
+            return Ok(Some(event));
+        }
+        let event = self.read_event();
+        if event.is_err() {
+            self.state = State::Failed;
+        }
+        event
+    }
+
+    /// Consumes the next value, including everything nested in it, without
+    /// building it or allocating for its strings.
+    ///
+    /// Call it after a `Key` event to skip that member's value, or where an
+    /// array element may follow. If called where a key is expected, the
+    /// whole member is skipped. Returns `false` without consuming anything
+    /// if the enclosing container ends instead; its end event is returned
+    /// by the next call to [`next_event`](Self::next_event).
+    pub fn skip_value(&mut self) -> Result<bool, ParseError> {
+        if self.pending.is_some() {
+            return Ok(false);
+        }
+        self.lexer.set_skipping(true);
+        let skipped = self.skip_events();
+        self.lexer.set_skipping(false);
+        skipped
+    }
+
+    fn skip_events(&mut self) -> Result<bool, ParseError> {
+        let mut open = 0usize;
+        loop {
+            let event = match self.next_event()? {
+                Some((event, _)) => event,
+                None => return Ok(false),
+            };
+            match event {
+                JsonEvent::StartObject | JsonEvent::StartArray => open += 1,
+                JsonEvent::EndObject | JsonEvent::EndArray if open == 0 => {
+                    self.pending = Some((event, self.stack.len()));
+                    return Ok(false);
+                }
+                JsonEvent::EndObject | JsonEvent::EndArray => open -= 1,
+                JsonEvent::Key(_) => continue,
+                _ => {}
+            }
+            if open == 0 {
+                return Ok(true);
+            }
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind, token: &Option<Token>) -> ParseError {
+        let kind = match token {
+            Some(_) => kind,
+            None => ParseErrorKind::UnexpectedEof,
+        };
+        let span = self.lexer.token_span();
+        ParseError::new(kind, span.start, span.line, span.column)
+    }
+
+    fn read_event(&mut self) -> Result<Option<(JsonEvent, usize)>, ParseError> {
+        loop {
+            if self.state == State::Failed {
+                return Ok(None);
+            }
+            let token = self.lexer.next_token()?;
+            match self.state {
+                State::Value => return self.value_event(token).map(Some),
+                State::ValueOrEnd => {
+                    if token == Some(Token::RBracket) {
+                        return Ok(Some(self.end_container(JsonEvent::EndArray)));
+                    }
+                    return self.value_event(token).map(Some);
+                }
+                State::KeyOrEnd | State::Key => match token {
+                    Some(Token::Str(key)) => {
+                        self.state = State::Colon;
+                        return Ok(Some((JsonEvent::Key(key), self.stack.len())));
+                    }
+                    Some(Token::RBrace) if self.state == State::KeyOrEnd => {
+                        return Ok(Some(self.end_container(JsonEvent::EndObject)));
+                    }
+                    _ => return Err(self.error(ParseErrorKind::ExpectedKey, &token)),
+                },
+                State::Colon => {
+                    if token != Some(Token::Colon) {
+                        return Err(self.error(ParseErrorKind::ExpectedColon, &token));
+                    }
+                    self.state = State::Value;
+                }
+                State::CommaOrEnd => {
+                    let in_object = self.stack.last() == Some(&true);
+                    match token {
+                        Some(Token::Comma) if in_object => self.state = State::Key,
+                        Some(Token::Comma) => self.state = State::Value,
+                        Some(Token::RBrace) if in_object => {
+                            return Ok(Some(self.end_container(JsonEvent::EndObject)));
+                        }
+                        Some(Token::RBracket) if !in_object => {
+                            return Ok(Some(self.end_container(JsonEvent::EndArray)));
+                        }
+                        _ => return Err(self.error(ParseErrorKind::ExpectedComma, &token)),
+                    }
+                }
+                State::Finished => {
+                    return match token {
+                        None => Ok(None),
+                        Some(_) => Err(self.error(ParseErrorKind::TrailingCharacters, &token)),
+                    };
+                }
+                State::Failed => unreachable!(),
+            }
+        }
+    }
+
+    fn value_event(&mut self, token: Option<Token>) -> Result<(JsonEvent, usize), ParseError> {
+        let depth = self.stack.len();
+        let event = match token {
+            Some(Token::LBrace) | Some(Token::LBracket) => {
+                if depth >= self.max_depth {
+                    return Err(self.error(ParseErrorKind::RecursionLimitExceeded, &token));
+                }
+                let is_object = token == Some(Token::LBrace);
+                self.stack.push(is_object);
+                if is_object {
+                    self.state = State::KeyOrEnd;
+                    return Ok((JsonEvent::StartObject, depth));
+                }
+                self.state = State::ValueOrEnd;
+                return Ok((JsonEvent::StartArray, depth));
+            }
+            Some(Token::Str(s)) => JsonEvent::String(s),
+            Some(Token::Number(n)) => JsonEvent::Number(n),
+            Some(Token::Bool(b)) => JsonEvent::Bool(b),
+            Some(Token::Null) => JsonEvent::Null,
+            _ => return Err(self.error(ParseErrorKind::UnexpectedToken, &token)),
+        };
+        self.after_value();
+        Ok((event, depth))
+    }
+
+    fn end_container(&mut self, event: JsonEvent) -> (JsonEvent, usize) {
+        self.stack.pop();
+        self.after_value();
+        (event, self.stack.len())
+    }
+
+    fn after_value(&mut self) {
+        self.state = if self.stack.is_empty() {
+            State::Finished
+        } else {
+            State::CommaOrEnd
+        };
+    }
+}
+
+impl Iterator for EventParser<'_> {
+    type Item = Result<(JsonEvent, usize), ParseError>;
+
+    /// Yields events until the end of the document or the first error.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(input: &str) -> Vec<(JsonEvent, usize)> {
+        EventParser::new(input).collect::<Result<_, _>>().unwrap()
+    }
+
+    fn key(key: &str) -> JsonEvent {
+        JsonEvent::Key(key.to_string())
+    }
+
+    #[test]
+    fn test_events_with_depth() {
+        let input = r#"{"name": "John", "grades": [90, {}], "info": {"ok": true, "none": null}}"#;
+
+        assert_eq!(
+            events(input),
+            vec![
+                (JsonEvent::StartObject, 0),
+                (key("name"), 1),
+                (JsonEvent::String("John".to_string()), 1),
+                (key("grades"), 1),
+                (JsonEvent::StartArray, 1),
+                (JsonEvent::Number(90.into()), 2),
+                (JsonEvent::StartObject, 2),
+                (JsonEvent::EndObject, 2),
+                (JsonEvent::EndArray, 1),
+                (key("info"), 1),
+                (JsonEvent::StartObject, 1),
+                (key("ok"), 2),
+                (JsonEvent::Bool(true), 2),
+                (key("none"), 2),
+                (JsonEvent::Null, 2),
+                (JsonEvent::EndObject, 1),
+                (JsonEvent::EndObject, 0),
+            ]
+        );
+        assert_eq!(
+            events("[]"),
+            vec![(JsonEvent::StartArray, 0), (JsonEvent::EndArray, 0)]
+        );
+        assert_eq!(events(" 1.5 "), vec![(JsonEvent::Number(1.5.into()), 0)]);
+    }
+
+    #[test]
+    fn test_skip_value() {
+        let mut parser = EventParser::new(
+            r#"{"skip": {"a": [1, "x", {"b": null}]}, "keep": "yes", "rest": [1]}"#,
+        );
+
+        assert_eq!(parser.next_event(), Ok(Some((JsonEvent::StartObject, 0))));
+        assert_eq!(parser.next_event(), Ok(Some((key("skip"), 1))));
+        assert_eq!(parser.skip_value(), Ok(true));
+        assert_eq!(parser.next_event(), Ok(Some((key("keep"), 1))));
+        assert_eq!(
+            parser.next_event(),
+            Ok(Some((JsonEvent::String("yes".to_string()), 1)))
+        );
+        // In key position the whole member is skipped.
+        assert_eq!(parser.skip_value(), Ok(true));
+        assert_eq!(parser.skip_value(), Ok(false));
+        assert_eq!(parser.next_event(), Ok(Some((JsonEvent::EndObject, 0))));
+        assert_eq!(parser.next_event(), Ok(None));
+    }
+
+    #[test]
+    fn test_skip_array_elements() {
+        let mut parser = EventParser::new(r#"[[1, 2], "x", 3]"#);
+
+        assert_eq!(parser.next_event(), Ok(Some((JsonEvent::StartArray, 0))));
+        assert_eq!(parser.skip_value(), Ok(true));
+        assert_eq!(parser.skip_value(), Ok(true));
+        assert_eq!(
+            parser.next_event(),
+            Ok(Some((JsonEvent::Number(3.into()), 1)))
+        );
+        assert_eq!(parser.skip_value(), Ok(false));
+        assert_eq!(parser.skip_value(), Ok(false));
+        assert_eq!(parser.next_event(), Ok(Some((JsonEvent::EndArray, 0))));
+        assert_eq!(parser.skip_value(), Ok(false));
+        assert_eq!(parser.next_event(), Ok(None));
+
+        let mut parser = EventParser::new(r#"[{"a": "bé"}]"#);
+        assert!(parser.skip_value().unwrap());
+        assert_eq!(parser.next_event(), Ok(None));
+    }
+
+    #[test]
+    fn test_event_errors() {
+        let cases = [
+            ("", ParseErrorKind::UnexpectedEof),
+            ("[1, 2", ParseErrorKind::UnexpectedEof),
+            ("[1 2]", ParseErrorKind::ExpectedComma),
+            ("[1}", ParseErrorKind::ExpectedComma),
+            ("[1,]", ParseErrorKind::UnexpectedToken),
+            ("{\"a\" 1}", ParseErrorKind::ExpectedColon),
+            ("{1: 2}", ParseErrorKind::ExpectedKey),
+            ("{\"a\": 1,}", ParseErrorKind::ExpectedKey),
+            ("[\"abc]", ParseErrorKind::UnclosedString),
+            ("{} {}", ParseErrorKind::TrailingCharacters),
+            ("[[[1]]]", ParseErrorKind::RecursionLimitExceeded),
+        ];
+
+        for (input, kind) in cases {
+            let mut parser = EventParser::new(input).with_max_depth(2);
+            let err = parser.by_ref().find_map(Result::err);
+            assert_eq!(err.map(|err| err.kind), Some(kind), "{}", input);
+            assert_eq!(parser.next(), None, "{}", input);
+        }
+
+        let mut parser = EventParser::new("[1, {\"a\": [x]}]");
+        assert!(parser.skip_value().is_err());
+        assert_eq!(parser.next_event(), Ok(None));
+    }
+
+    #[test]
+    fn test_events_error_position() {
+        let err = EventParser::new("{\n  \"a\": 1,\n  \"b\" 2\n}")
+            .find_map(Result::err)
+            .unwrap();
+
+        assert_eq!(
+            err,
+            ParseError::new(ParseErrorKind::ExpectedColon, 18, 3, 7)
+        );
+    }
+
+    #[test]
+    fn test_events_from_reader() {
+        let input = r#"{"a": [1, "two", null]}"#;
+        let from_reader: Vec<_> = EventParser::from_reader(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(from_reader, events(input));
+    }
+}
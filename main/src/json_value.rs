@@ -1,35 +1,497 @@
-#[derive(Debug, PartialEq)]
+use std::ops::{Index, IndexMut};
+
+use crate::map::{Entry, JsonMap};
+use crate::number::JsonNumber;
+
+/// A parsed JSON document.
+///
+/// Objects keep their members in insertion order, which parsing and
+/// serialization preserve. Equality does not depend on that order: two
+/// objects are equal if they have the same keys with equal values, like in
+/// JSON itself. Where the order matters, compare the serialized output or
+/// [`JsonMap::keys`] instead.
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum JsonValue {
-    Object(Vec<(String, JsonValue)>),
+    Object(JsonMap),
     Array(Vec<JsonValue>),
     String(String),
-    Number(f64),
+    Number(JsonNumber),
     Bool(bool),
+    #[default]
     Null,
 }
 
+static NULL: JsonValue = JsonValue::Null;
+
 impl JsonValue {
     pub fn new_obj() -> JsonValue {
-        JsonValue::Object(Vec::new())
+        JsonValue::Object(JsonMap::new())
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::Null)
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self, JsonValue::Bool(_))
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, JsonValue::Number(_))
+    }
+
+    pub fn is_string(&self) -> bool {
+        matches!(self, JsonValue::String(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, JsonValue::Array(_))
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self, JsonValue::Object(_))
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool_mut(&mut self) -> Option<&mut bool> {
+        match self {
+            JsonValue::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&JsonNumber> {
+        match self {
+            JsonValue::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_number_mut(&mut self) -> Option<&mut JsonNumber> {
+        match self {
+            JsonValue::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_str_mut(&mut self) -> Option<&mut String> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
     }
 
-    pub fn get_arr<'a>(&'a self, index: usize) -> Option<&'a JsonValue> {
+    pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&JsonMap> {
+        match self {
+            JsonValue::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut JsonMap> {
+        match self {
+            JsonValue::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    /// Returns the number as `i64`; see [`JsonNumber::as_i64`].
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            JsonValue::Number(n) => n.as_i64(),
+            _ => None,
+        }
+    }
+
+    /// Returns the number as `u64`; see [`JsonNumber::as_u64`].
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            JsonValue::Number(n) => n.as_u64(),
+            _ => None,
+        }
+    }
+
+    /// Returns the number as `f64`; see [`JsonNumber::as_f64`].
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(n) => Some(n.as_f64()),
+            _ => None,
+        }
+    }
+
+    pub fn get_arr(&self, index: usize) -> Option<&JsonValue> {
         match self {
             JsonValue::Array(arr) => arr.get(index),
             _ => None,
         }
     }
-    pub fn get<'a>(&'a self, index: &str) -> Option<&'a JsonValue> {
+
+    pub fn get_arr_mut(&mut self, index: usize) -> Option<&mut JsonValue> {
         match self {
-            JsonValue::Object(obj) => {
-                for (name, val) in obj {
-                    if name == index {
-                        return Some(val);
-                    }
-                }
-                None
-            }
+            JsonValue::Array(arr) => arr.get_mut(index),
             _ => None,
         }
     }
+
+    pub fn get(&self, index: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(obj) => obj.get(index),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, index: &str) -> Option<&mut JsonValue> {
+        match self {
+            JsonValue::Object(obj) => obj.get_mut(index),
+            _ => None,
+        }
+    }
+
+    /// Returns the members of an object, turning `null` into an empty
+    /// object first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is neither an object nor `null`.
+    fn object_mut(&mut self) -> &mut JsonMap {
+        if let JsonValue::Null = self {
+            *self = JsonValue::new_obj();
+        }
+        match self {
+            JsonValue::Object(obj) => obj,
+            other => panic!("cannot access members of non-object {:?}", other),
+        }
+    }
+
+    /// Sets the member `key` of an object and returns its previous value.
+    /// `null` is turned into an object first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is neither an object nor `null`.
+    pub fn insert(&mut self, key: impl Into<String>, value: JsonValue) -> Option<JsonValue> {
+        self.object_mut().insert(key.into(), value)
+    }
+
+    /// Removes the member `key` of an object and returns its value.
+    /// Returns `None` if there is no such member or the value is not an
+    /// object.
+    pub fn remove(&mut self, key: &str) -> Option<JsonValue> {
+        match self {
+            JsonValue::Object(obj) => obj.remove(key),
+            _ => None,
+        }
+    }
+
+    /// Returns the member `key` of an object for in-place insertion or
+    /// update; see [`JsonMap::entry`]. `null` is turned into an object first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is neither an object nor `null`.
+    pub fn entry(&mut self, key: impl Into<String>) -> Entry<'_> {
+        self.object_mut().entry(key)
+    }
+
+    /// Appends an element to an array. `null` is turned into an array
+    /// first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is neither an array nor `null`.
+    pub fn push(&mut self, value: JsonValue) {
+        if let JsonValue::Null = self {
+            *self = JsonValue::Array(Vec::new());
+        }
+        match self {
+            JsonValue::Array(arr) => arr.push(value),
+            other => panic!("cannot push onto non-array {:?}", other),
+        }
+    }
+
+    /// Moves the value out, leaving `null` in its place.
+    pub fn take(&mut self) -> JsonValue {
+        std::mem::take(self)
+    }
+
+    /// Stores `value` in place of this one and returns the old value.
+    pub fn replace(&mut self, value: JsonValue) -> JsonValue {
+        std::mem::replace(self, value)
+    }
+}
+
+/// Returns `null` if the value is not an object or has no member `key`.
+impl Index<&str> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, key: &str) -> &JsonValue {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+/// Returns `null` if the value is not an array or `index` is out of bounds.
+impl Index<usize> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, index: usize) -> &JsonValue {
+        self.get_arr(index).unwrap_or(&NULL)
+    }
+}
+
+/// Inserts `null` if there is no member `key`, so that it can be assigned
+/// to. `null` is turned into an object first.
+///
+/// # Panics
+///
+/// Panics if the value is neither an object nor `null`.
+impl IndexMut<&str> for JsonValue {
+    fn index_mut(&mut self, key: &str) -> &mut JsonValue {
+        self.object_mut().entry(key).or_null()
+    }
+}
+
+/// # Panics
+///
+/// Panics if the value is not an array or `index` is out of bounds.
+impl IndexMut<usize> for JsonValue {
+    fn index_mut(&mut self, index: usize) -> &mut JsonValue {
+        match self {
+            JsonValue::Array(arr) => {
+                let len = arr.len();
+                arr.get_mut(index).unwrap_or_else(|| {
+                    panic!("index {} out of bounds for array of length {}", index, len)
+                })
+            }
+            other => panic!("cannot index into non-array {:?} with {}", other, index),
+        }
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(b: bool) -> Self {
+        JsonValue::Bool(b)
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(s: &str) -> Self {
+        JsonValue::String(s.to_string())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(s: String) -> Self {
+        JsonValue::String(s)
+    }
+}
+
+impl From<JsonNumber> for JsonValue {
+    fn from(n: JsonNumber) -> Self {
+        JsonValue::Number(n)
+    }
+}
+
+macro_rules! from_number {
+    ($($t:ty),*) => {$(
+        impl From<$t> for JsonValue {
+            fn from(n: $t) -> Self {
+                JsonValue::Number(n.into())
+            }
+        }
+    )*};
+}
+
+from_number!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+impl From<JsonMap> for JsonValue {
+    fn from(obj: JsonMap) -> Self {
+        JsonValue::Object(obj)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Vec<T>> for JsonValue {
+    fn from(arr: Vec<T>) -> Self {
+        JsonValue::Array(arr.into_iter().map(Into::into).collect())
+    }
+}
+
+/// `None` becomes `null`.
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(JsonValue::Null, Into::into)
+    }
+}
+
+/// Collects into an array.
+impl<T: Into<JsonValue>> FromIterator<T> for JsonValue {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        JsonValue::Array(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Collects key-value pairs into an object.
+impl<K: Into<String>, V: Into<JsonValue>> FromIterator<(K, V)> for JsonValue {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        JsonValue::Object(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::JsonParser;
+
+    fn parse(input: &str) -> JsonValue {
+        JsonParser::new(input).parse().unwrap()
+    }
+
+    #[test]
+    fn test_edit_in_place() {
+        let mut value = parse(r#"{"name": "John", "tags": ["a"], "info": {"age": 30}}"#);
+
+        assert_eq!(
+            value.insert("name", JsonValue::String("Jane".to_string())),
+            Some(JsonValue::String("John".to_string()))
+        );
+        assert_eq!(value.insert("ok", JsonValue::Bool(true)), None);
+        value.get_mut("tags").unwrap().push(JsonValue::Null);
+        *value.get_mut("tags").unwrap().get_arr_mut(0).unwrap() = JsonValue::Bool(false);
+        *value["info"].entry("age").or_null() = JsonValue::Number(31.into());
+        value["info"]["city"] = JsonValue::String("NY".to_string());
+        value["new"].push(JsonValue::Number(1.into()));
+        assert_eq!(value.remove("missing"), None);
+        assert_eq!(value["tags"].remove("x"), None);
+
+        assert_eq!(
+            value.to_string(),
+            r#"{"name":"Jane","tags":[false,null],"info":{"age":31,"city":"NY"},"ok":true,"new":[1]}"#
+        );
+    }
+
+    #[test]
+    fn test_index_misses_return_null() {
+        let value = parse(r#"{"a": [1, {"b": true}]}"#);
+
+        assert_eq!(value["a"][1]["b"], JsonValue::Bool(true));
+        assert_eq!(value["missing"], JsonValue::Null);
+        assert_eq!(value["a"][5], JsonValue::Null);
+        assert_eq!(value["a"]["b"], JsonValue::Null);
+        assert_eq!(value["a"][0][0], JsonValue::Null);
+    }
+
+    #[test]
+    fn test_take_and_replace() {
+        let mut value = parse(r#"{"a": [1, 2], "b": "x"}"#);
+
+        let a = value["a"].take();
+        assert_eq!(a, parse("[1, 2]"));
+        assert_eq!(value["a"], JsonValue::Null);
+        assert_eq!(
+            value["b"].replace(JsonValue::Bool(true)),
+            JsonValue::String("x".to_string())
+        );
+        assert_eq!(value, parse(r#"{"a": null, "b": true}"#));
+    }
+
+    #[test]
+    fn test_typed_accessors() {
+        let mut value = parse(r#"{"s": "x", "n": 1.5, "b": true, "a": [null], "o": {}}"#);
+
+        assert_eq!(value["s"].as_str(), Some("x"));
+        assert_eq!(value["n"].as_f64(), Some(1.5));
+        assert_eq!(value["b"].as_bool(), Some(true));
+        assert_eq!(value["a"].as_array(), Some(&vec![JsonValue::Null]));
+        assert_eq!(value["o"].as_object(), Some(&JsonMap::new()));
+        assert_eq!(value["s"].as_f64(), None);
+        assert_eq!(value["n"].as_str(), None);
+        assert_eq!(value["a"].as_object(), None);
+        assert!(value["a"][0].is_null() && value["s"].is_string() && value["n"].is_number());
+        assert!(value["b"].is_bool() && value["a"].is_array() && value["o"].is_object());
+        assert!(!value["o"].is_array() && !value["missing"].is_string());
+
+        value["s"].as_str_mut().unwrap().push('y');
+        *value["b"].as_bool_mut().unwrap() = false;
+        value["a"].as_array_mut().unwrap().clear();
+        value["o"]
+            .as_object_mut()
+            .unwrap()
+            .insert("k".to_string(), 1.into());
+        *value["n"].as_number_mut().unwrap() = 2.into();
+        assert_eq!(
+            value,
+            parse(r#"{"s": "xy", "n": 2, "b": false, "a": [], "o": {"k": 1}}"#)
+        );
+    }
+
+    #[test]
+    fn test_from_conversions() {
+        let value: JsonValue = vec![
+            JsonValue::from("a"),
+            "b".to_string().into(),
+            1u8.into(),
+            (-2i64).into(),
+            u64::MAX.into(),
+            (-(1i128 << 64)).into(),
+            0.5f32.into(),
+            true.into(),
+            None::<i32>.into(),
+            Some("c").into(),
+            vec![1, 2].into(),
+        ]
+        .into();
+        assert_eq!(
+            value.to_string(),
+            r#"["a","b",1,-2,18446744073709551615,-18446744073709551616,0.5,true,null,"c",[1,2]]"#
+        );
+
+        let arr: JsonValue = (1..4).collect();
+        assert_eq!(arr, parse("[1, 2, 3]"));
+        let obj: JsonValue = [("a", 1), ("b", 2)].into_iter().collect();
+        assert_eq!(obj, parse(r#"{"a": 1, "b": 2}"#));
+        assert_eq!(obj.clone(), obj);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot access members of non-object")]
+    fn test_insert_into_non_object_panics() {
+        JsonValue::Bool(true).insert("a", JsonValue::Null);
+    }
+
+    #[test]
+    #[should_panic(expected = "index 2 out of bounds")]
+    fn test_index_mut_out_of_bounds_panics() {
+        let mut value = parse("[1]");
+        value[2] = JsonValue::Null;
+    }
 }
@@ -1,5 +1,10 @@
+use std::io;
 use std::str::Chars;
 
+use crate::error::{ParseError, ParseErrorKind};
+use crate::number::JsonNumber;
+use crate::reader::{ReadError, Utf8Reader};
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum Token {
     LBrace,
@@ -7,7 +12,7 @@ pub enum Token {
     LBracket,
     RBracket,
     Str(String),
-    Number(f64),
+    Number(JsonNumber),
     Colon,
     Comma,
     Bool(bool),
@@ -15,25 +20,144 @@ pub enum Token {
     Unknown(String),
 }
 
+/// Location of a token in the source.
+///
+/// `start` and `end` are byte offsets (`end` is exclusive); `line` and
+/// `column` are 1-based and refer to `start`, with the column counted in
+/// characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+enum Input<'a> {
+    Chars(Chars<'a>),
+    Reader(Utf8Reader<'a>),
+}
+
 pub struct Lexer<'a> {
-    input: Chars<'a>,
+    input: Input<'a>,
     current: Option<char>,
-    current_pos: u64,
+    /// Failure of the underlying reader. The lexer sees it as the end of
+    /// input, and it replaces whatever error that leads to.
+    input_error: Option<ParseError>,
+    position: Position,
+    token_start: Position,
+    strict: bool,
+    lossy_surrogates: bool,
+    arbitrary_precision: bool,
+    /// Reused for the text of numbers and keywords, and of strings while
+    /// skipping, so that those tokens don't allocate.
+    scratch: String,
+    skipping: bool,
+    borrowing: bool,
+    /// Contents of the last string token when it was borrowed from the input.
+    borrowed: Option<&'a str>,
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(source: &str) -> Lexer {
+    pub fn new(source: &str) -> Lexer<'_> {
         let input = source.chars();
         Self::from_chars(input)
     }
 
-    pub fn from_chars(mut input: Chars) -> Lexer {
-        let current = input.next();
-        Lexer {
+    pub fn from_chars(input: Chars<'_>) -> Lexer<'_> {
+        Lexer::from_input(Input::Chars(input))
+    }
+
+    /// Lexes UTF-8 text read incrementally from `reader` through a bounded
+    /// buffer. Invalid UTF-8 is reported as [`ParseErrorKind::InvalidUtf8`]
+    /// and failures of the reader as [`ParseErrorKind::Io`].
+    pub fn from_reader<R: io::Read + 'a>(reader: R) -> Lexer<'a> {
+        Lexer::from_input(Input::Reader(Utf8Reader::new(reader)))
+    }
+
+    fn from_input(input: Input<'a>) -> Lexer<'a> {
+        let position = Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        };
+        let mut lexer = Lexer {
             input,
-            current,
-            current_pos: 0,
-        }
+            current: None,
+            input_error: None,
+            position,
+            token_start: position,
+            strict: false,
+            lossy_surrogates: false,
+            arbitrary_precision: false,
+            scratch: String::new(),
+            skipping: false,
+            borrowing: false,
+            borrowed: None,
+        };
+        lexer.current = lexer.read_char();
+        lexer
+    }
+
+    /// Rejects input that RFC 8259 does not allow but that is otherwise
+    /// accepted: unknown escape sequences like `\q`, a leading `+` or
+    /// leading zeros on numbers, empty fractions like `1.`, unescaped control
+    /// characters in strings and whitespace other than space, tab, line feed
+    /// and carriage return.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Replaces lone or mismatched UTF-16 surrogate escapes with U+FFFD
+    /// instead of failing with [`ParseErrorKind::LoneSurrogate`].
+    pub fn with_lossy_surrogates(mut self, lossy_surrogates: bool) -> Self {
+        self.lossy_surrogates = lossy_surrogates;
+        self
+    }
+
+    /// Keeps every number as its exact text instead of converting it to
+    /// `u64`, `i64` or `f64`, so that no precision is lost and numbers are
+    /// serialized exactly as they were written. Numbers of any size are
+    /// accepted. Numbers that are only accepted outside of strict mode, like
+    /// `+1` or `01`, are converted as usual.
+    pub fn with_arbitrary_precision(mut self, arbitrary_precision: bool) -> Self {
+        self.arbitrary_precision = arbitrary_precision;
+        self
+    }
+
+    /// While skipping, string tokens are validated but returned empty so
+    /// that no allocation is made for them.
+    pub(crate) fn set_skipping(&mut self, skipping: bool) {
+        self.skipping = skipping;
+    }
+
+    /// While borrowing, a string without escapes that lies in a `&str`
+    /// input is returned as an empty token and its contents are available
+    /// from [`take_borrowed`](Self::take_borrowed) instead.
+    pub(crate) fn set_borrowing(&mut self, borrowing: bool) {
+        self.borrowing = borrowing;
+    }
+
+    pub(crate) fn take_borrowed(&mut self) -> Option<&'a str> {
+        self.borrowed.take()
     }
 
     fn current(&mut self) -> Option<char> {
@@ -41,13 +165,94 @@ impl<'a> Lexer<'a> {
     }
 
     fn advance(&mut self) -> Option<char> {
-        self.current = self.input.next();
-        self.current_pos += 1;
+        if let Some(c) = self.current {
+            self.position.offset += c.len_utf8();
+            if c == '\n' {
+                self.position.line += 1;
+                self.position.column = 1;
+            } else {
+                self.position.column += 1;
+            }
+        }
+        self.current = self.read_char();
         self.current
     }
 
-    pub fn next_token(&mut self) -> Option<Token> {
+    fn read_char(&mut self) -> Option<char> {
+        let reader = match &mut self.input {
+            Input::Chars(chars) => return chars.next(),
+            Input::Reader(reader) => reader,
+        };
+        if self.input_error.is_some() {
+            return None;
+        }
+        match reader.next_char() {
+            Ok(c) => c,
+            Err(err) => {
+                let Position {
+                    offset,
+                    line,
+                    column,
+                } = self.position;
+                self.input_error = Some(match err {
+                    ReadError::Io(err) => ParseError::from_io(err, offset, line, column),
+                    ReadError::InvalidUtf8 => {
+                        ParseError::new(ParseErrorKind::InvalidUtf8, offset, line, column)
+                    }
+                });
+                None
+            }
+        }
+    }
+
+    fn error_at(&self, kind: ParseErrorKind, position: Position) -> ParseError {
+        ParseError::new(kind, position.offset, position.line, position.column)
+    }
+
+    fn token_error(&self, kind: ParseErrorKind) -> ParseError {
+        self.error_at(kind, self.token_start)
+    }
+
+    pub fn next_token(&mut self) -> Result<Option<Token>, ParseError> {
+        Ok(self.next_spanned_token()?.map(|(token, _)| token))
+    }
+
+    /// Lexes the next token together with its location in the source.
+    /// At the end of input the span is empty and points just past the last
+    /// character, so it can still be used to report where input ended.
+    pub fn next_spanned_token(&mut self) -> Result<Option<(Token, Span)>, ParseError> {
+        let token = self.lex_token();
+        if let Some(err) = self.input_error.take() {
+            return Err(err);
+        }
+        Ok(token?.map(|token| (token, self.token_span())))
+    }
+
+    /// Span of the most recently lexed token, or the empty span at the end
+    /// of input once the lexer is exhausted.
+    pub fn token_span(&self) -> Span {
+        Span {
+            start: self.token_start.offset,
+            end: self.position.offset,
+            line: self.token_start.line,
+            column: self.token_start.column,
+        }
+    }
+
+    /// Turns the lexer into an iterator of tokens with their spans.
+    /// Unlike the plain `Iterator` impl it reports lexing errors, and stops
+    /// after the first one.
+    pub fn spanned_tokens(self) -> SpannedTokens<'a> {
+        SpannedTokens {
+            lexer: self,
+            done: false,
+        }
+    }
+
+    fn lex_token(&mut self) -> Result<Option<Token>, ParseError> {
+        self.borrowed = None;
         self.skip_whitespace();
+        self.token_start = self.position;
 
         if let Some(c) = self.current() {
             let token = match c {
@@ -75,25 +280,32 @@ impl<'a> Lexer<'a> {
                     self.advance();
                     Token::Colon
                 }
-                '"' => self.lex_string(),
-                '+' | '-' | '0'..='9' => self.lex_number(),
+                '"' => self.lex_string()?,
+                '+' | '-' | '0'..='9' => self.lex_number()?,
                 _ if c.is_alphabetic() => self.lex_keywords(),
                 _ => {
-                    let mut str = String::new();
-                    str.push(c);
-                    Token::Unknown(str)
+                    self.advance();
+                    Token::Unknown(c.to_string())
                 }
             };
 
-            Some(token)
+            Ok(Some(token))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn is_whitespace(&self, c: char) -> bool {
+        if self.strict {
+            matches!(c, ' ' | '\t' | '\n' | '\r')
         } else {
-            None
+            c.is_whitespace()
         }
     }
 
     fn skip_whitespace(&mut self) {
         while let Some(c) = self.current() {
-            if !c.is_whitespace() {
+            if !self.is_whitespace(c) {
                 break;
             }
             self.advance();
@@ -101,7 +313,8 @@ impl<'a> Lexer<'a> {
     }
 
     fn lex_keywords(&mut self) -> Token {
-        let mut id = String::new();
+        let mut id = std::mem::take(&mut self.scratch);
+        id.clear();
         while let Some(c) = self.current() {
             if c.is_alphanumeric() {
                 id.push(c);
@@ -110,104 +323,226 @@ impl<'a> Lexer<'a> {
                 break;
             }
         }
-        match id.as_str() {
+        let token = match id.as_str() {
             "true" => Token::Bool(true),
             "false" => Token::Bool(false),
             "null" => Token::Null,
-            _ => Token::Unknown(id),
-        }
+            _ => Token::Unknown(id.clone()),
+        };
+        self.scratch = id;
+        token
     }
 
-    fn consume_int_string(&mut self) -> Option<String> {
-        let mut int_str = String::new();
+    /// Appends a run of ASCII digits to `out` and returns how many there were.
+    fn consume_digits(&mut self, out: &mut String) -> usize {
+        let mut count = 0;
 
         while let Some(c) = self.current() {
             match c {
                 '0'..='9' => {
-                    int_str.push(c);
+                    out.push(c);
                     self.advance();
+                    count += 1;
                 }
                 _ => break,
             }
         }
 
-        Some(int_str)
+        count
     }
 
-    fn lex_number(&mut self) -> Token {
-        let mut num_str = String::new();
+    fn lex_number(&mut self) -> Result<Token, ParseError> {
+        let mut num_str = std::mem::take(&mut self.scratch);
+        num_str.clear();
+        let token = self.lex_number_into(&mut num_str);
+        self.scratch = num_str;
+        token
+    }
 
-        if self.current() == Some('-') {
-            num_str.push('-');
-            self.advance();
-        } else if self.current() == Some('+') {
-            self.advance();
+    fn lex_number_into(&mut self, num_str: &mut String) -> Result<Token, ParseError> {
+        // Whether the number is valid under the RFC 8259 grammar.
+        let mut valid = true;
+        match self.current() {
+            Some('-') => {
+                num_str.push('-');
+                self.advance();
+            }
+            Some('+') if !self.strict => {
+                valid = false;
+                self.advance();
+            }
+            _ => {}
         }
 
-        {
-            let s = self
-                .consume_int_string()
-                .unwrap_or_else(|| panic!("Invalid number"));
-            num_str += &s;
+        let int_start = num_str.len();
+        let int_len = self.consume_digits(num_str);
+        if int_len == 0 || (int_len > 1 && num_str[int_start..].starts_with('0')) {
+            if self.strict {
+                return Err(self.token_error(ParseErrorKind::InvalidNumber));
+            }
+            valid = false;
         }
 
         if let Some('.') = self.current() {
             self.advance();
-            let s = self
-                .consume_int_string()
-                .unwrap_or_else(|| panic!("Invalid fraction"));
             num_str.push('.');
-            num_str += &s;
+            if self.consume_digits(num_str) == 0 {
+                if self.strict {
+                    return Err(self.token_error(ParseErrorKind::InvalidNumber));
+                }
+                valid = false;
+            }
         }
 
-        if matches!(self.current(), Some('e') | Some('E')) {
-            num_str.push('e');
-            match self.advance() {
-                Some('-') => {
-                    num_str.push('-');
-                    self.advance();
+        if let Some(e @ ('e' | 'E')) = self.current() {
+            num_str.push(e);
+            if let Some(sign @ ('-' | '+')) = self.advance() {
+                num_str.push(sign);
+                self.advance();
+            }
+            if self.consume_digits(num_str) == 0 {
+                valid = false;
+            }
+        }
+
+        if self.arbitrary_precision && valid {
+            return Ok(Token::Number(JsonNumber::from_text(num_str)));
+        }
+        let is_integer = !num_str[int_start..].contains(['.', 'e', 'E']);
+        match JsonNumber::parse(num_str, is_integer) {
+            Some(n) => Ok(Token::Number(n)),
+            None => Err(self.token_error(ParseErrorKind::InvalidNumber)),
+        }
+    }
+
+    /// Decodes the escape sequence whose backslash starts at `start`; the
+    /// lexer is positioned on the character following the backslash.
+    fn lex_escape(&mut self, string: &mut String, start: Position) -> Result<(), ParseError> {
+        let c = match self.current() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{0008}',
+            Some('f') => '\u{000c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => return self.lex_unicode_escape(string, start),
+            Some(_) if self.strict => {
+                return Err(self.error_at(ParseErrorKind::InvalidEscape, start))
+            }
+            Some(c) => c,
+            None => return Err(self.token_error(ParseErrorKind::UnclosedString)),
+        };
+        self.advance();
+        string.push(c);
+        Ok(())
+    }
+
+    fn lex_unicode_escape(
+        &mut self,
+        string: &mut String,
+        start: Position,
+    ) -> Result<(), ParseError> {
+        let mut unit = 0;
+        for _ in 0..4 {
+            match self.advance().and_then(|c| c.to_digit(16)) {
+                Some(digit) => unit = unit * 16 + digit,
+                None if self.current().is_none() => {
+                    return Err(self.token_error(ParseErrorKind::UnclosedString))
                 }
-                Some('+') => {
-                    self.advance();
+                None => return Err(self.error_at(ParseErrorKind::InvalidEscape, start)),
+            }
+        }
+        self.advance();
+
+        let c = match unit {
+            0xD800..=0xDBFF => match self.peek_low_surrogate() {
+                Some(low) => {
+                    for _ in 0..6 {
+                        self.advance();
+                    }
+                    char::from_u32(0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00))
                 }
-                _ => {}
-            };
-            let s = self
-                .consume_int_string()
-                .unwrap_or_else(|| panic!("Invalid exponent"));
-            num_str += &s;
+                None => None,
+            },
+            _ => char::from_u32(unit),
+        };
+
+        match c {
+            Some(c) => string.push(c),
+            None if self.lossy_surrogates => string.push(char::REPLACEMENT_CHARACTER),
+            None => return Err(self.error_at(ParseErrorKind::LoneSurrogate, start)),
         }
-        Token::Number(num_str.parse::<f64>().unwrap())
+        Ok(())
     }
 
-    fn handle_escape_char(&mut self) -> Option<char> {
-        match self.current() {
-            Some('"') => Some('"'),
-            Some('\\') => Some('\\'),
-            Some('/') => Some('/'),
-            Some('b') => Some('\u{0008}'),
-            Some('f') => Some('\u{000c}'),
-            Some('n') => Some('\n'),
-            Some('r') => Some('\r'),
-            Some('t') => Some('\t'),
-            Some('u') => unimplemented!(),
-            c => c,
+    /// Looks ahead for a `\uXXXX` escape encoding a low surrogate without
+    /// consuming it, so that a mismatched escape is lexed on its own.
+    fn peek_low_surrogate(&mut self) -> Option<u32> {
+        if self.current != Some('\\') {
+            return None;
+        }
+        let next = match &mut self.input {
+            Input::Chars(chars) => chars.as_str().as_bytes(),
+            Input::Reader(reader) => reader.peek(5),
+        };
+        let hex = next.strip_prefix(b"u")?.get(..4)?;
+        if !hex.iter().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let unit = u32::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?;
+        (0xDC00..=0xDFFF).contains(&unit).then_some(unit)
+    }
+
+    /// Returns the contents of the string starting at the current `"` as a
+    /// slice of the input if they need no unescaping, and moves past the
+    /// closing quote.
+    fn borrow_string(&mut self) -> Option<&'a str> {
+        let rest = match &self.input {
+            Input::Chars(chars) => chars.as_str(),
+            Input::Reader(_) => return None,
+        };
+        let strict = self.strict;
+        let len = rest.find(|c: char| c == '"' || c == '\\' || (strict && c < '\u{20}'))?;
+        if !rest[len..].starts_with('"') {
+            return None;
+        }
+        let contents = &rest[..len];
+        self.advance();
+        for _ in contents.chars() {
+            self.advance();
         }
+        self.advance();
+        Some(contents)
     }
 
-    fn lex_string(&mut self) -> Token {
-        let mut string = String::new();
+    fn lex_string(&mut self) -> Result<Token, ParseError> {
+        if self.borrowing && !self.skipping {
+            if let Some(contents) = self.borrow_string() {
+                self.borrowed = Some(contents);
+                return Ok(Token::Str(String::new()));
+            }
+        }
+        let mut string = if self.skipping {
+            let mut scratch = std::mem::take(&mut self.scratch);
+            scratch.clear();
+            scratch
+        } else {
+            String::new()
+        };
         self.advance();
 
         while let Some(c) = self.current() {
             match c {
                 '"' => break,
                 '\\' => {
+                    let start = self.position;
                     self.advance();
-                    string.push(
-                        self.handle_escape_char()
-                            .unwrap_or_else(|| panic!("Unclosed string")),
-                    );
+                    self.lex_escape(&mut string, start)?;
+                }
+                '\u{0000}'..='\u{001f}' if self.strict => {
+                    return Err(self.error_at(ParseErrorKind::ControlCharacter, self.position));
                 }
                 _ => {
                     self.advance();
@@ -215,18 +550,41 @@ impl<'a> Lexer<'a> {
                 }
             }
         }
-        if let None = self.current() {
-            panic!("Unclosed string");
+        if self.current().is_none() {
+            return Err(self.token_error(ParseErrorKind::UnclosedString));
         }
         self.advance();
-        Token::Str(string)
+        if self.skipping {
+            self.scratch = string;
+            return Ok(Token::Str(String::new()));
+        }
+        Ok(Token::Str(string))
     }
 }
 
+pub struct SpannedTokens<'a> {
+    lexer: Lexer<'a>,
+    done: bool,
+}
+
+impl<'a> Iterator for SpannedTokens<'a> {
+    type Item = Result<(Token, Span), ParseError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.lexer.next_spanned_token().transpose();
+        self.done = !matches!(result, Some(Ok(_)));
+        result
+    }
+}
+
+/// Yields tokens until the end of input or the first lexing error.
+/// Use [`Lexer::next_token`] directly to observe the error.
 impl<'a> Iterator for Lexer<'a> {
     type Item = Token;
     fn next(&mut self) -> Option<Self::Item> {
-        self.next_token()
+        self.next_token().ok().flatten()
     }
 }
 
@@ -239,7 +597,7 @@ mod tests {
         let input = "";
         let mut lexer = Lexer::new(input);
 
-        assert_eq!(lexer.next_token(), None);
+        assert_eq!(lexer.next_token(), Ok(None));
     }
 
     #[test]
@@ -266,7 +624,7 @@ mod tests {
             Token::Comma,
             Token::Str("age".to_string()),
             Token::Colon,
-            Token::Number(30.0),
+            Token::Number(30.into()),
             Token::Comma,
             Token::Str("is_student".to_string()),
             Token::Colon,
@@ -275,11 +633,11 @@ mod tests {
             Token::Str("grades".to_string()),
             Token::Colon,
             Token::LBracket,
-            Token::Number(90.5),
+            Token::Number(90.5.into()),
             Token::Comma,
-            Token::Number(85.2),
+            Token::Number(85.2.into()),
             Token::Comma,
-            Token::Number(88.8),
+            Token::Number(88.8.into()),
             Token::RBracket,
             Token::Comma,
             Token::Str("address".to_string()),
@@ -302,12 +660,200 @@ mod tests {
 
         let mut lexer = Lexer::new(input);
         for expected_token in expected_tokens {
-            if let Some(token) = lexer.next_token() {
+            if let Some(token) = lexer.next_token().unwrap() {
                 assert_eq!(token, expected_token);
             } else {
                 panic!("Expected more tokens but lexer reached the end.");
             }
         }
-        assert_eq!(lexer.next_token(), None);
+        assert_eq!(lexer.next_token(), Ok(None));
+    }
+
+    #[test]
+    fn test_lexer_escapes() {
+        let mut lexer = Lexer::new(r#""a\"b\\c\nd""#);
+
+        assert_eq!(
+            lexer.next_token(),
+            Ok(Some(Token::Str("a\"b\\c\nd".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_lexer_unclosed_string() {
+        let mut lexer = Lexer::new("\n  \"abc");
+        let err = lexer.next_token().unwrap_err();
+
+        assert_eq!(err.kind, ParseErrorKind::UnclosedString);
+        assert_eq!((err.offset, err.line, err.column), (3, 2, 3));
+    }
+
+    #[test]
+    fn test_lexer_invalid_number() {
+        for input in ["-", "1e", "-e5", "+", "1e400", "-1e400"] {
+            let err = Lexer::new(input).next_token().unwrap_err();
+            assert_eq!(err.kind, ParseErrorKind::InvalidNumber, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_lexer_unknown_character_advances() {
+        let tokens: Vec<Token> = Lexer::new("@ 1").collect();
+
+        assert_eq!(
+            tokens,
+            vec![Token::Unknown("@".to_string()), Token::Number(1.into())]
+        );
+    }
+
+    #[test]
+    fn test_spanned_tokens() {
+        let input = "{\"é\": [1.5,\n  true]}";
+        let tokens: Vec<(Token, Span)> = Lexer::new(input)
+            .spanned_tokens()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let span = |start, end, line, column| Span {
+            start,
+            end,
+            line,
+            column,
+        };
+        assert_eq!(
+            tokens,
+            vec![
+                (Token::LBrace, span(0, 1, 1, 1)),
+                (Token::Str("é".to_string()), span(1, 5, 1, 2)),
+                (Token::Colon, span(5, 6, 1, 5)),
+                (Token::LBracket, span(7, 8, 1, 7)),
+                (Token::Number(1.5.into()), span(8, 11, 1, 8)),
+                (Token::Comma, span(11, 12, 1, 11)),
+                (Token::Bool(true), span(15, 19, 2, 3)),
+                (Token::RBracket, span(19, 20, 2, 7)),
+                (Token::RBrace, span(20, 21, 2, 8)),
+            ]
+        );
+        for (_, span) in &tokens {
+            assert!(input.get(span.start..span.end).is_some());
+        }
+    }
+
+    #[test]
+    fn test_spanned_tokens_stop_after_error() {
+        let mut tokens = Lexer::new("[\"abc").spanned_tokens();
+
+        assert!(matches!(tokens.next(), Some(Ok((Token::LBracket, _)))));
+        assert!(matches!(tokens.next(), Some(Err(_))));
+        assert!(tokens.next().is_none());
+    }
+
+    fn lex_str(mut lexer: Lexer) -> Result<String, ParseError> {
+        match lexer.next_token()? {
+            Some(Token::Str(s)) => Ok(s),
+            token => panic!("expected string, got {:?}", token),
+        }
+    }
+
+    #[test]
+    fn test_lexer_unicode_escapes() {
+        let cases = [
+            (r#""\u0041\u00e9""#, "Aé"),
+            (r#""\u4E2D\u6587""#, "中文"),
+            (r#""\ud83d\ude00!""#, "😀!"),
+            (r#""\uD834\uDD1E""#, "𝄞"),
+            (r#""\u0000""#, "\0"),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(lex_str(Lexer::new(input)).unwrap(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_lexer_lone_surrogates() {
+        for input in [
+            r#""\ud800""#,
+            r#""\udc00""#,
+            r#""\ud800x""#,
+            r#""\ud800A""#,
+            r#""\ud800\ud800""#,
+        ] {
+            let err = lex_str(Lexer::new(input)).unwrap_err();
+            assert_eq!(err.kind, ParseErrorKind::LoneSurrogate, "{}", input);
+            assert_eq!(err.offset, 1);
+        }
+    }
+
+    #[test]
+    fn test_lexer_lossy_surrogates() {
+        let cases = [
+            (r#""\ud800""#, "\u{fffd}"),
+            (r#""a\udc00b""#, "a\u{fffd}b"),
+            (r#""\ud800A""#, "\u{fffd}A"),
+            (r#""\ud800\ud83d\ude00""#, "\u{fffd}😀"),
+        ];
+
+        for (input, expected) in cases {
+            let lexer = Lexer::new(input).with_lossy_surrogates(true);
+            assert_eq!(lex_str(lexer).unwrap(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_lexer_invalid_escapes() {
+        for input in [r#""\u12""#, r#""\u12g4""#, r#""\x""#] {
+            let err = lex_str(Lexer::new(input).with_strict(true)).unwrap_err();
+            assert_eq!(err.kind, ParseErrorKind::InvalidEscape, "{}", input);
+        }
+
+        let err = lex_str(Lexer::new(r#""\u12"#)).unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::UnclosedString);
+
+        assert_eq!(lex_str(Lexer::new(r#""\q""#)).unwrap(), "q");
+    }
+
+    #[test]
+    fn test_lexer_strict_numbers() {
+        for input in ["+1", "01", "-01", "1.", "-.5", "1.e3"] {
+            let err = Lexer::new(input)
+                .with_strict(true)
+                .next_token()
+                .unwrap_err();
+            assert_eq!(err.kind, ParseErrorKind::InvalidNumber, "{}", input);
+        }
+
+        assert_eq!(
+            Lexer::new("+1").next_token(),
+            Ok(Some(Token::Number(1.into())))
+        );
+        assert_eq!(
+            Lexer::new("1.").next_token(),
+            Ok(Some(Token::Number(1.into())))
+        );
+        for (input, expected) in [("0", 0.0), ("-0.5", -0.5), ("10e-1", 1.0)] {
+            let mut lexer = Lexer::new(input).with_strict(true);
+            assert_eq!(lexer.next_token(), Ok(Some(Token::Number(expected.into()))));
+        }
+    }
+
+    #[test]
+    fn test_lexer_strict_strings_and_whitespace() {
+        let err = Lexer::new("\"a\tb\"")
+            .with_strict(true)
+            .next_token()
+            .unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::ControlCharacter);
+        assert_eq!(err.offset, 2);
+
+        let mut lexer = Lexer::new("\u{a0}1").with_strict(true);
+        assert_eq!(
+            lexer.next_token(),
+            Ok(Some(Token::Unknown("\u{a0}".to_string())))
+        );
+        assert_eq!(
+            Lexer::new("\u{a0}1").next_token(),
+            Ok(Some(Token::Number(1.into())))
+        );
     }
 }
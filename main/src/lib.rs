@@ -1,13 +1,76 @@
+pub mod borrowed;
+pub mod diff;
+pub mod error;
+pub mod events;
 pub mod json_value;
 pub mod lexer;
+mod macros;
+pub mod map;
+pub mod merge_patch;
+pub mod number;
 pub mod parser;
-pub use macros::{JsonDeserializable, JsonSerializable};
-// mod macros;
+pub mod patch;
+pub mod path;
+pub mod pointer;
+mod reader;
+mod regex;
+pub mod serializer;
+mod unicode;
+pub mod writer;
+pub use ::macros::{JsonDeserializable, JsonSerializable};
+#[doc(hidden)]
+pub use macros::__private;
 
+// Lets code generated by the derives refer to `::yi_json` paths from inside
+// this crate as well.
+extern crate self as yi_json;
+
+pub use borrowed::BorrowedJsonValue;
+pub use diff::json_diff;
+pub use error::{
+    ParseError, ParseErrorKind, PatchError, PatchErrorKind, PathError, PathErrorKind, PointerError,
+    WriteError,
+};
 pub use json_value::JsonValue;
+pub use map::JsonMap;
+pub use merge_patch::merge_patch_diff;
+pub use number::JsonNumber;
+pub use patch::JsonPatch;
+pub use path::JsonPath;
+pub use pointer::JsonPointer;
+pub use serializer::PrettyPrinter;
+pub use writer::JsonWriter;
 
+/// Converts a value into JSON, usually through
+/// `#[derive(JsonSerializable)]`.
+///
+/// Internally tagged enums add the tag to the variant's content. A newtype
+/// variant whose content is not an object is written as
+/// `{"<tag>": "Variant", "value": content}` instead, and deriving rejects
+/// variants holding a primitive or an `Option`, which never serialize to an
+/// object:
+///
+/// ```compile_fail
+/// use yi_json::JsonSerializable;
+///
+/// #[derive(JsonSerializable)]
+/// #[json(tag = "type")]
+/// enum Event {
+///     Count(u32),
+/// }
+/// ```
 pub trait JsonSerializable {
     fn to_json(&self) -> JsonValue;
+
+    /// Writes the value through a [`JsonWriter`]. The default goes through
+    /// [`to_json`](JsonSerializable::to_json); implementations can override
+    /// it to stream without building the intermediate tree.
+    fn write_json<W: std::io::Write>(&self, writer: &mut JsonWriter<W>) -> Result<(), WriteError>
+    where
+        Self: Sized,
+    {
+        writer.value(&self.to_json())
+    }
 }
 pub trait JsonDeserializable {
     fn from_json(value: &JsonValue) -> Option<Self>
@@ -19,6 +82,10 @@ impl JsonSerializable for String {
     fn to_json(&self) -> JsonValue {
         JsonValue::String(self.clone())
     }
+
+    fn write_json<W: std::io::Write>(&self, writer: &mut JsonWriter<W>) -> Result<(), WriteError> {
+        writer.string(self)
+    }
 }
 
 impl JsonDeserializable for String {
@@ -36,7 +103,11 @@ impl JsonDeserializable for String {
 
 impl JsonSerializable for u32 {
     fn to_json(&self) -> JsonValue {
-        JsonValue::Number(self.clone() as f64)
+        JsonValue::Number((*self).into())
+    }
+
+    fn write_json<W: std::io::Write>(&self, writer: &mut JsonWriter<W>) -> Result<(), WriteError> {
+        writer.number(*self)
     }
 }
 
@@ -45,17 +116,20 @@ impl JsonDeserializable for u32 {
     where
         Self: Sized,
     {
-        if let JsonValue::Number(n) = value {
-            Some(n.clone() as u32)
-        } else {
-            None
+        match value {
+            JsonValue::Number(n) => n.as_u64().and_then(|n| u32::try_from(n).ok()),
+            _ => None,
         }
     }
 }
 
 impl JsonSerializable for bool {
     fn to_json(&self) -> JsonValue {
-        JsonValue::Bool(self.clone())
+        JsonValue::Bool(*self)
+    }
+
+    fn write_json<W: std::io::Write>(&self, writer: &mut JsonWriter<W>) -> Result<(), WriteError> {
+        writer.bool(*self)
     }
 }
 
@@ -65,13 +139,87 @@ impl JsonDeserializable for bool {
         Self: Sized,
     {
         if let JsonValue::Bool(b) = value {
-            Some(b.clone())
+            Some(*b)
         } else {
             None
         }
     }
 }
 
+impl JsonSerializable for str {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.to_string())
+    }
+}
+
+impl<T: JsonSerializable> JsonSerializable for [T] {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.iter().map(T::to_json).collect())
+    }
+}
+
+impl<T: JsonSerializable> JsonSerializable for Vec<T> {
+    fn to_json(&self) -> JsonValue {
+        self.as_slice().to_json()
+    }
+
+    fn write_json<W: std::io::Write>(&self, writer: &mut JsonWriter<W>) -> Result<(), WriteError> {
+        writer.begin_array()?;
+        for item in self {
+            item.write_json(writer)?;
+        }
+        writer.end_array()
+    }
+}
+
+impl<T: JsonDeserializable> JsonDeserializable for Vec<T> {
+    fn from_json(value: &JsonValue) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        value.as_array()?.iter().map(T::from_json).collect()
+    }
+}
+
+impl<T: JsonSerializable> JsonSerializable for Option<T> {
+    fn to_json(&self) -> JsonValue {
+        match self {
+            Some(value) => value.to_json(),
+            None => JsonValue::Null,
+        }
+    }
+
+    fn write_json<W: std::io::Write>(&self, writer: &mut JsonWriter<W>) -> Result<(), WriteError> {
+        match self {
+            Some(value) => value.write_json(writer),
+            None => writer.null(),
+        }
+    }
+}
+
+/// `null` becomes `None`. Derived impls also use `None` for a missing
+/// member of an `Option` field.
+impl<T: JsonDeserializable> JsonDeserializable for Option<T> {
+    fn from_json(value: &JsonValue) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        match value {
+            JsonValue::Null => Some(None),
+            value => T::from_json(value).map(Some),
+        }
+    }
+}
+
+/// References serialize like the value behind them. Unsized values such as
+/// `str` and slices are written through [`JsonSerializable::to_json`].
+impl<T: JsonSerializable + ?Sized> JsonSerializable for &T {
+    fn to_json(&self) -> JsonValue {
+        (**self).to_json()
+    }
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
 
@@ -90,22 +238,22 @@ mod tests {
             is_student: true,
         };
 
-        let expected_json = JsonValue::Object(vec![
-            ("name".to_string(), JsonValue::String("John".to_string())),
-            ("age".to_string(), JsonValue::Number(25.0)),
-            ("is_student".to_string(), JsonValue::Bool(true)),
-        ]);
+        let expected_json = json!({
+            "name": "John",
+            "age": 25,
+            "is_student": true,
+        });
 
         assert_eq!(person.to_json(), expected_json);
     }
 
     #[test]
     fn test_from_json() {
-        let json_value = JsonValue::Object(vec![
-            ("name".to_string(), JsonValue::String("Jane".to_string())),
-            ("age".to_string(), JsonValue::Number(30.0)),
-            ("is_student".to_string(), JsonValue::Bool(false)),
-        ]);
+        let json_value = json!({
+            "name": "Jane",
+            "age": 30,
+            "is_student": false,
+        });
 
         let expected_person = Person {
             name: "Jane".to_string(),
@@ -116,5 +264,398 @@ mod tests {
         assert_eq!(Person::from_json(&json_value), Some(expected_person));
     }
 
-    // Add more tests for different scenarios as needed
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    struct UserId(u32);
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    struct Point(u32, u32, String);
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    struct Marker;
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    struct Page<T> {
+        items: Vec<T>,
+        total: u32,
+    }
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    enum Either<L, R>
+    where
+        L: Clone,
+    {
+        Left(L),
+        Right(R),
+    }
+
+    #[derive(JsonSerializable)]
+    struct Label<'a> {
+        text: &'a str,
+        parts: &'a [u32],
+    }
+
+    trait Resource {
+        type Id;
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Users;
+
+    impl Resource for Users {
+        type Id = u32;
+    }
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    #[json(bound = "R::Id: JsonSerializable + JsonDeserializable")]
+    struct Reference<R: Resource> {
+        id: R::Id,
+    }
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    #[json(rename_all = "camelCase")]
+    struct Account {
+        user_name: String,
+        #[json(rename = "mail", alias = "email")]
+        email_address: String,
+        is_admin: bool,
+    }
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    #[json(tag = "kind", rename_all = "snake_case")]
+    enum Notice {
+        #[json(rename_all = "kebab-case")]
+        SignedUp { user_name: String },
+        #[json(rename = "bye", alias = "logout")]
+        LoggedOut,
+    }
+
+    fn default_port() -> u32 {
+        8080
+    }
+
+    fn is_zero(n: &u32) -> bool {
+        *n == 0
+    }
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    struct Server {
+        host: String,
+        #[json(default = "default_port")]
+        port: u32,
+        #[json(default)]
+        tags: Vec<String>,
+        nickname: Option<String>,
+        #[json(skip_serializing_if = "Option::is_none")]
+        owner: Option<String>,
+        #[json(skip)]
+        connections: u32,
+    }
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    #[json(default)]
+    struct Limits {
+        requests: u32,
+        #[json(skip_serializing_if = "is_zero")]
+        burst: u32,
+    }
+
+    impl Default for Limits {
+        fn default() -> Self {
+            Limits {
+                requests: 100,
+                burst: 10,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    struct Optional {
+        a: Option<u32>,
+    }
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    enum Shape {
+        Empty,
+        Circle(u32),
+        Line(u32, u32),
+        Rect { width: u32, height: u32 },
+    }
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    #[json(tag = "type")]
+    enum Event {
+        Ping,
+        Login { user: String, admin: bool },
+        Wrapped(Person),
+    }
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    #[json(tag = "t", content = "c")]
+    enum Message {
+        Quit,
+        Move(u32, u32),
+        Text(String),
+        Color { r: u32 },
+    }
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    #[json(untagged)]
+    enum Loose {
+        Flag(bool),
+        Pair(u32, u32),
+        Named { name: String },
+        Nothing,
+    }
+
+    /// Checks both serializations against `expected` and that it
+    /// deserializes back into `value`.
+    fn assert_round_trip<T>(value: T, expected: JsonValue)
+    where
+        T: JsonSerializable + JsonDeserializable + PartialEq + std::fmt::Debug,
+    {
+        assert_eq!(value.to_json(), expected);
+        let mut writer = JsonWriter::new(Vec::new());
+        writer.serialize(&value).unwrap();
+        let written = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert_eq!(written, expected.to_string());
+        assert_eq!(T::from_json(&expected), Some(value));
+    }
+
+    #[test]
+    fn test_tuple_and_unit_structs() {
+        assert_round_trip(UserId(7), json!(7));
+        assert_round_trip(Point(1, 2, "a".to_string()), json!([1, 2, "a"]));
+        assert_round_trip(Marker, json!(null));
+
+        assert_eq!(UserId::from_json(&json!([7])), None);
+        assert_eq!(Point::from_json(&json!([1, 2])), None);
+        assert_eq!(Point::from_json(&json!([1, 2, "a", 3])), None);
+        assert_eq!(Marker::from_json(&json!({})), None);
+    }
+
+    #[test]
+    fn test_generic_types() {
+        let page = Page {
+            items: vec![UserId(1), UserId(2)],
+            total: 5,
+        };
+        assert_round_trip(page, json!({"items": [1, 2], "total": 5}));
+        assert_round_trip(
+            Page::<Either<u32, String>> {
+                items: vec![Either::Left(1), Either::Right("a".to_string())],
+                total: 2,
+            },
+            json!({"items": [{"Left": 1}, {"Right": "a"}], "total": 2}),
+        );
+        assert_eq!(
+            Page::<u32>::from_json(&json!({"items": [1, "2"], "total": 2})),
+            None
+        );
+
+        let label = Label {
+            text: "point",
+            parts: &[1, 2],
+        };
+        assert_eq!(label.to_json(), json!({"text": "point", "parts": [1, 2]}));
+
+        assert_round_trip(Reference::<Users> { id: 3 }, json!({"id": 3}));
+    }
+
+    #[test]
+    fn test_renaming() {
+        let account = Account {
+            user_name: "jane".to_string(),
+            email_address: "jane@example.com".to_string(),
+            is_admin: false,
+        };
+        assert_round_trip(
+            account,
+            json!({"userName": "jane", "mail": "jane@example.com", "isAdmin": false}),
+        );
+        assert_eq!(
+            Account::from_json(
+                &json!({"userName": "jo", "email": "jo@example.com", "isAdmin": true})
+            ),
+            Some(Account {
+                user_name: "jo".to_string(),
+                email_address: "jo@example.com".to_string(),
+                is_admin: true,
+            })
+        );
+        assert_eq!(
+            Account::from_json(&json!({"user_name": "jo", "mail": "", "is_admin": true})),
+            None
+        );
+
+        assert_round_trip(
+            Notice::SignedUp {
+                user_name: "jane".to_string(),
+            },
+            json!({"kind": "signed_up", "user-name": "jane"}),
+        );
+        assert_round_trip(Notice::LoggedOut, json!({"kind": "bye"}));
+        assert_eq!(
+            Notice::from_json(&json!({"kind": "logout"})),
+            Some(Notice::LoggedOut)
+        );
+    }
+
+    #[test]
+    fn test_defaults_and_skipping() {
+        let server = Server {
+            host: "localhost".to_string(),
+            port: 80,
+            tags: vec!["web".to_string()],
+            nickname: None,
+            owner: None,
+            connections: 0,
+        };
+        assert_round_trip(
+            server,
+            json!({"host": "localhost", "port": 80, "tags": ["web"], "nickname": null}),
+        );
+
+        let server = Server {
+            host: "localhost".to_string(),
+            port: 80,
+            tags: Vec::new(),
+            nickname: Some("box".to_string()),
+            owner: Some("ops".to_string()),
+            connections: 3,
+        };
+        assert_eq!(
+            server.to_json(),
+            json!({"host": "localhost", "port": 80, "tags": [], "nickname": "box", "owner": "ops"})
+        );
+
+        assert_eq!(
+            Server::from_json(&json!({"host": "localhost", "connections": 3})),
+            Some(Server {
+                host: "localhost".to_string(),
+                port: 8080,
+                tags: Vec::new(),
+                nickname: None,
+                owner: None,
+                connections: 0,
+            })
+        );
+        assert_eq!(Server::from_json(&json!({"port": 80})), None);
+        assert_eq!(
+            Server::from_json(&json!({"host": "localhost", "nickname": 1})),
+            None
+        );
+
+        assert_eq!(
+            Limits::from_json(&json!({"burst": 5})),
+            Some(Limits {
+                requests: 100,
+                burst: 5,
+            })
+        );
+        assert_eq!(
+            Limits {
+                requests: 1,
+                burst: 0,
+            }
+            .to_json(),
+            json!({"requests": 1})
+        );
+        assert_eq!(Limits::from_json(&json!(42)), None);
+        assert_eq!(Limits::from_json(&json!([1])), None);
+        assert_eq!(Optional::from_json(&json!({})), Some(Optional { a: None }));
+        assert_eq!(Optional::from_json(&json!("str")), None);
+        assert_eq!(Optional::from_json(&json!([1, 2])), None);
+    }
+
+    #[test]
+    fn test_externally_tagged_enum() {
+        assert_round_trip(Shape::Empty, json!("Empty"));
+        assert_round_trip(Shape::Circle(2), json!({"Circle": 2}));
+        assert_round_trip(Shape::Line(1, 2), json!({"Line": [1, 2]}));
+        assert_round_trip(
+            Shape::Rect {
+                width: 3,
+                height: 4,
+            },
+            json!({"Rect": {"width": 3, "height": 4}}),
+        );
+
+        assert_eq!(Shape::from_json(&json!("Circle")), None);
+        assert_eq!(Shape::from_json(&json!({"Line": [1]})), None);
+        assert_eq!(
+            Shape::from_json(&json!({"Circle": 1, "Line": [1, 2]})),
+            None
+        );
+    }
+
+    #[test]
+    fn test_internally_tagged_enum() {
+        assert_round_trip(Event::Ping, json!({"type": "Ping"}));
+        assert_round_trip(
+            Event::Login {
+                user: "ann".to_string(),
+                admin: false,
+            },
+            json!({"type": "Login", "user": "ann", "admin": false}),
+        );
+        assert_round_trip(
+            Event::Wrapped(Person {
+                name: "John".to_string(),
+                age: 25,
+                is_student: true,
+            }),
+            json!({"type": "Wrapped", "name": "John", "age": 25, "is_student": true}),
+        );
+
+        assert_eq!(Event::from_json(&json!({"type": "Pong"})), None);
+        assert_eq!(Event::from_json(&json!({"user": "ann"})), None);
+    }
+
+    #[derive(Debug, PartialEq, JsonSerializable, JsonDeserializable)]
+    #[json(tag = "type")]
+    enum Boxed {
+        Id(UserId),
+        At(Point),
+    }
+
+    #[test]
+    fn test_internally_tagged_newtype_of_non_object() {
+        assert_round_trip(Boxed::Id(UserId(1)), json!({"type": "Id", "value": 1}));
+        assert_round_trip(
+            Boxed::At(Point(1, 2, "x".to_string())),
+            json!({"type": "At", "value": [1, 2, "x"]}),
+        );
+        assert_eq!(Boxed::from_json(&json!({"type": "Id"})), None);
+    }
+
+    #[test]
+    fn test_adjacently_tagged_enum() {
+        assert_round_trip(Message::Quit, json!({"t": "Quit"}));
+        assert_round_trip(Message::Move(1, 2), json!({"t": "Move", "c": [1, 2]}));
+        assert_round_trip(
+            Message::Text("hi".to_string()),
+            json!({"t": "Text", "c": "hi"}),
+        );
+        assert_round_trip(
+            Message::Color { r: 9 },
+            json!({"t": "Color", "c": {"r": 9}}),
+        );
+
+        assert_eq!(Message::from_json(&json!({"t": "Text"})), None);
+    }
+
+    #[test]
+    fn test_untagged_enum() {
+        assert_round_trip(Loose::Flag(true), json!(true));
+        assert_round_trip(Loose::Pair(1, 2), json!([1, 2]));
+        assert_round_trip(
+            Loose::Named {
+                name: "x".to_string(),
+            },
+            json!({"name": "x"}),
+        );
+        assert_round_trip(Loose::Nothing, json!(null));
+
+        assert_eq!(Loose::from_json(&json!("x")), None);
+    }
 }
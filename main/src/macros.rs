@@ -1,59 +1,298 @@
+/// Builds a [`JsonValue`](crate::JsonValue) from JSON-like syntax.
+///
+/// Values can be `null`, `true`, `false`, arrays, objects and any Rust
+/// expression whose type implements
+/// [`JsonSerializable`](crate::JsonSerializable) or `Into<JsonValue>`.
+/// Object keys are string literals or expressions that convert into a
+/// `String`; a key made of more than one token has to be parenthesized.
+///
+/// ```
+/// use yi_json::json;
+///
+/// let name = "John";
+/// let value = json!({
+///     "name": name,
+///     "grades": [90, 85],
+///     "info": { "city": "NY", "zip": null },
+/// });
+/// assert_eq!(value["grades"][1], json!(85));
+/// ```
+#[macro_export]
+macro_rules! json {
+    // Arrays are built element by element: `@array [elements so far]
+    // remaining tokens`.
+    (@array [$($elems:expr,)*]) => {
+        ::std::vec![$($elems,)*]
+    };
+    (@array [$($elems:expr),*]) => {
+        ::std::vec![$($elems),*]
+    };
+    (@array [$($elems:expr,)*] null $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!(null)] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] true $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!(true)] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] false $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!(false)] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] [$($array:tt)*] $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!([$($array)*])] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] {$($object:tt)*} $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!({$($object)*})] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!($next),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] $last:expr) => {
+        $crate::json!(@array [$($elems,)* $crate::json!($last)])
+    };
+    (@array [$($elems:expr),*] , $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)*] $($rest)*)
+    };
 
-// macro_rules! JsonSerializable {
-//     ($struct_name: ident {$(field_name:ident : $field_type:ty),*}) => {
-//          impl JsonDeserializable for $struct_name {
-//             fn from_json(&value: JsonValue) -> Option<Self> {
-//                 $(
-//                     let #field_name = value.get(#field_name)?;
-//                     let #field_name = <#field_type>::from_json(#field_name)?;
-//                 )*;
-
-//                 Some($(#field_name,)*)
-//             }
-//          }
-//     };
-// }
-
-// #[proc_macro_derive(JsonDeserializable)]
-// pub fn deserializable_derive(input: TokenStream) -> TokenStream {
-//     let input = syn::parse_macro_input!(input as DeriveInput);
-//     let name = &input.ident;
-
-//     let expanded = match &input.data {
-//         Data::Struct(data) => match &data.fields {
-//             Fields::Named(fields) => {
-//                 let field_deserialization = fields.named.iter().map(|field| {
-//                     let field_name = &field.ident;
-//                     let field_type = &field.ty;
-//                     quote! {
-//                             let #field_name = value.get(stringify!(#field_name))?;
-//                             let #field_name = <#field_type>::from_json(#field_name)?;
-//                     }
-//                 });
-
-//                 quote! {
-//                     impl JsonDeserializable for #name {
-//                         fn from_json(value: &JsonValue) -> Option<Self>
-//                         where Self: Sized,
-//                         {
-//                             if let JsonValue::Object(obj) = value {
-
-//                                 #field_deserialization
-
-//                                 Some(Self { #(#field_name,)*})
-//                             } else {
-//                                 None
-//                             }
-//                         }
-//                     }
-//                 }
-//             },
-//             _ => {
-//                 quote! {compile_error!("Deserializable trait only supports structs with named fields");}
-//             }
-//         },
-//         D => quote! { compile_error!("Deserializaable trait only supports structs");},
-//     };
-
-//     expanded.into()
-// }
+    // Objects are built member by member: `@object map (key tokens so far)
+    // (remaining tokens) (copy of the remaining tokens)`. Once the value is
+    // known, the key is put in brackets: `@object map [key] (value) rest`.
+    (@object $object:ident () () ()) => {};
+    (@object $object:ident [$($key:tt)+] ($value:expr) , $($rest:tt)*) => {
+        let _ = $object.insert(::std::convert::Into::into($($key)+), $value);
+        $crate::json!(@object $object () ($($rest)*) ($($rest)*));
+    };
+    (@object $object:ident [$($key:tt)+] ($value:expr)) => {
+        let _ = $object.insert(::std::convert::Into::into($($key)+), $value);
+    };
+    (@object $object:ident ($($key:tt)+) (: null $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object [$($key)+] ($crate::json!(null)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: true $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object [$($key)+] ($crate::json!(true)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: false $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object [$($key)+] ($crate::json!(false)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: [$($array:tt)*] $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object [$($key)+] ($crate::json!([$($array)*])) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: {$($map:tt)*} $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object [$($key)+] ($crate::json!({$($map)*})) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: $value:expr , $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object [$($key)+] ($crate::json!($value)) , $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: $value:expr) $copy:tt) => {
+        $crate::json!(@object $object [$($key)+] ($crate::json!($value)));
+    };
+    (@object $object:ident ($($key:tt)+) (:) $copy:tt) => {
+        ::std::compile_error!("missing value after the last key in json!");
+    };
+    (@object $object:ident ($($key:tt)+) () $copy:tt) => {
+        ::std::compile_error!("missing colon and value after the last key in json!");
+    };
+    (@object $object:ident () (: $($rest:tt)*) ($colon:tt $($copy:tt)*)) => {
+        ::std::compile_error!(::std::concat!("missing key before `", ::std::stringify!($colon), "` in json!"));
+    };
+    (@object $object:ident ($($key:tt)*) (, $($rest:tt)*) ($comma:tt $($copy:tt)*)) => {
+        ::std::compile_error!(::std::concat!("unexpected `", ::std::stringify!($comma), "` after a key in json!"));
+    };
+    (@object $object:ident () (($key:expr) : $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object ($key) (: $($rest)*) (: $($rest)*));
+    };
+    (@object $object:ident ($($key:tt)*) ($tt:tt $($rest:tt)*) $copy:tt) => {
+        $crate::json!(@object $object ($($key)* $tt) ($($rest)*) ($($rest)*));
+    };
+
+    (null) => {
+        $crate::JsonValue::Null
+    };
+    (true) => {
+        $crate::JsonValue::Bool(true)
+    };
+    (false) => {
+        $crate::JsonValue::Bool(false)
+    };
+    ([]) => {
+        $crate::JsonValue::Array(::std::vec::Vec::new())
+    };
+    ([ $($tt:tt)+ ]) => {
+        $crate::JsonValue::Array($crate::json!(@array [] $($tt)+))
+    };
+    ({}) => {
+        $crate::JsonValue::Object($crate::JsonMap::new())
+    };
+    ({ $($tt:tt)+ }) => {{
+        let mut object = $crate::JsonMap::new();
+        $crate::json!(@object object () ($($tt)+) ($($tt)+));
+        $crate::JsonValue::Object(object)
+    }};
+    ($other:expr) => {{
+        #[allow(unused_imports)]
+        use $crate::__private::{ViaInto as _, ViaSerializable as _};
+        (&&$crate::__private::Wrap(::std::cell::Cell::new(::std::option::Option::Some($other)))).to_json_value()
+    }};
+}
+
+/// Asserts that two [`JsonValue`](crate::JsonValue)s are equal, like
+/// `assert_eq!`, but on failure prints the differences found by
+/// [`json_diff`](crate::diff::json_diff) rather than both values.
+#[macro_export]
+macro_rules! assert_json_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                let diff = $crate::diff::json_diff(left, right);
+                if !diff.is_empty() {
+                    panic!("assertion `left == right` failed:\n{}", diff.colored());
+                }
+            }
+        }
+    };
+}
+
+/// Support for `json!`, which has to pick `JsonSerializable` or
+/// `Into<JsonValue>` depending on what the interpolated type implements.
+/// Method resolution tries `ViaSerializable`, implemented for `&Wrap<T>`,
+/// before `ViaInto`, implemented for `Wrap<T>`, because it needs one less
+/// dereference of the `&&Wrap<T>` receiver.
+#[doc(hidden)]
+pub mod __private {
+    use std::cell::Cell;
+
+    use crate::{JsonSerializable, JsonValue};
+
+    pub struct Wrap<T>(pub Cell<Option<T>>);
+
+    impl<T> Wrap<T> {
+        fn take(&self) -> T {
+            self.0.take().expect("json! converts each value once")
+        }
+    }
+
+    pub trait ViaSerializable {
+        fn to_json_value(&self) -> JsonValue;
+    }
+
+    impl<T: JsonSerializable> ViaSerializable for &Wrap<T> {
+        fn to_json_value(&self) -> JsonValue {
+            self.take().to_json()
+        }
+    }
+
+    pub trait ViaInto {
+        fn to_json_value(&self) -> JsonValue;
+    }
+
+    impl<T: Into<JsonValue>> ViaInto for Wrap<T> {
+        fn to_json_value(&self) -> JsonValue {
+            self.take().into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::JsonParser;
+    use crate::{JsonMap, JsonSerializable, JsonValue};
+
+    #[derive(crate::JsonSerializable)]
+    struct Point {
+        x: u32,
+        y: u32,
+    }
+
+    /// Only convertible through `Into<JsonValue>`.
+    struct Celsius(f64);
+
+    impl From<Celsius> for JsonValue {
+        fn from(c: Celsius) -> Self {
+            JsonValue::Number(c.0.into())
+        }
+    }
+
+    fn parse(input: &str) -> JsonValue {
+        JsonParser::new(input).parse().unwrap()
+    }
+
+    mod without_prelude {
+        #![no_implicit_prelude]
+
+        /// A `vec!` of its own, which `json!` must not pick up.
+        #[allow(unused_macros)]
+        macro_rules! vec {
+            ($($tt:tt)*) => {
+                ::std::compile_error!("json! used a `vec!` in scope")
+            };
+        }
+
+        pub(super) fn build() -> crate::JsonValue {
+            let name = "John";
+            crate::json!({"name": name, "grades": [90, 85], "tags": [true,], ("a"): {}})
+        }
+    }
+
+    #[test]
+    fn test_json_without_prelude() {
+        assert_eq!(
+            without_prelude::build(),
+            parse(r#"{"name": "John", "grades": [90, 85], "tags": [true], "a": {}}"#)
+        );
+    }
+
+    #[test]
+    fn test_json_literals() {
+        assert_eq!(json!(null), JsonValue::Null);
+        assert_eq!(json!(true), JsonValue::Bool(true));
+        assert_eq!(json!([]), JsonValue::Array(Vec::new()));
+        assert_eq!(json!({}), JsonValue::Object(JsonMap::new()));
+        assert_eq!(json!("a"), JsonValue::String("a".to_string()));
+        assert_eq!(json!(-1.5), JsonValue::Number((-1.5).into()));
+        assert_eq!(
+            json!([null, true, false, [], {}, [1, [2]], {"a": {"b": null}},]),
+            parse(r#"[null, true, false, [], {}, [1, [2]], {"a": {"b": null}}]"#)
+        );
+    }
+
+    #[test]
+    fn test_json_nested_object() {
+        let name = "John".to_string();
+        let value = json!({
+            "name": name,
+            "grades": [90, 85.5],
+            "info": { "city": "NY", "tags": [] },
+            "ok": 1 + 1 == 2,
+            "none": null,
+        });
+
+        assert_eq!(
+            value,
+            parse(
+                r#"{"name": "John", "grades": [90, 85.5], "info": {"city": "NY", "tags": []}, "ok": true, "none": null}"#
+            )
+        );
+        assert_eq!(
+            value.to_string(),
+            r#"{"name":"John","grades":[90,85.5],"info":{"city":"NY","tags":[]},"ok":true,"none":null}"#
+        );
+    }
+
+    #[test]
+    fn test_json_interpolation() {
+        let key = "dynamic";
+        let tags = vec!["a", "b"];
+        let value = json!({
+            "point": Point { x: 1, y: 2 },
+            "temperature": Celsius(21.5),
+            "tags": tags,
+            "maybe": None::<u32>,
+            (key): [Point { x: 3, y: 4 }.to_json(), Some("x")],
+        });
+
+        assert_eq!(
+            value,
+            parse(
+                r#"{"point": {"x": 1, "y": 2}, "temperature": 21.5, "tags": ["a", "b"], "maybe": null, "dynamic": [{"x": 3, "y": 4}, "x"]}"#
+            )
+        );
+    }
+}
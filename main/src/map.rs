@@ -0,0 +1,426 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::{slice, vec};
+
+use crate::json_value::JsonValue;
+
+/// Objects with more members than this get a hash index; smaller ones are
+/// searched linearly, which is faster at that size.
+const INDEX_THRESHOLD: usize = 8;
+
+/// The members of a JSON object, in insertion order, with hashed lookup by
+/// key.
+///
+/// Keys are unique: inserting an existing key replaces its value but keeps
+/// its position, so for input with duplicate keys the last value wins.
+/// Two maps are equal if they have the same keys with equal values,
+/// regardless of order, so `==` cannot tell whether the order was kept;
+/// compare [`keys`](JsonMap::keys) for that.
+#[derive(Clone, Default)]
+pub struct JsonMap {
+    entries: Vec<(String, JsonValue)>,
+    /// Position of each key in `entries`, once there are more than
+    /// `INDEX_THRESHOLD` of them.
+    index: Option<HashMap<String, usize>>,
+}
+
+impl JsonMap {
+    pub fn new() -> JsonMap {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> JsonMap {
+        JsonMap {
+            entries: Vec::with_capacity(capacity),
+            index: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn position(&self, key: &str) -> Option<usize> {
+        match &self.index {
+            Some(index) => index.get(key).copied(),
+            None => self.entries.iter().position(|(name, _)| name == key),
+        }
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.position(key).is_some()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.position(key).map(|i| &self.entries[i].1)
+    }
+
+    /// Returns the stored key along with the value.
+    pub fn get_key_value(&self, key: &str) -> Option<(&String, &JsonValue)> {
+        self.position(key).map(|i| {
+            let (name, value) = &self.entries[i];
+            (name, value)
+        })
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut JsonValue> {
+        self.position(key).map(|i| &mut self.entries[i].1)
+    }
+
+    /// Inserts a member and returns the value previously stored under `key`.
+    /// A new key is added at the end; an existing one keeps its position.
+    pub fn insert(&mut self, key: String, value: JsonValue) -> Option<JsonValue> {
+        if let Some(i) = self.position(&key) {
+            return Some(std::mem::replace(&mut self.entries[i].1, value));
+        }
+        match &mut self.index {
+            Some(index) => {
+                index.insert(key.clone(), self.entries.len());
+            }
+            None if self.entries.len() >= INDEX_THRESHOLD => {
+                let index = self
+                    .entries
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (name, _))| (name.clone(), i))
+                    .chain(std::iter::once((key.clone(), self.entries.len())))
+                    .collect();
+                self.index = Some(index);
+            }
+            None => {}
+        }
+        self.entries.push((key, value));
+        None
+    }
+
+    /// Returns the member for `key`, for in-place insertion or update.
+    pub fn entry(&mut self, key: impl Into<String>) -> Entry<'_> {
+        let key = key.into();
+        match self.position(&key) {
+            Some(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            None => Entry::Vacant(VacantEntry { map: self, key }),
+        }
+    }
+
+    /// Removes a member and returns its value. The members after it move up
+    /// so that the order of the others is unchanged, which takes time
+    /// linear in their number.
+    pub fn remove(&mut self, key: &str) -> Option<JsonValue> {
+        let i = self.position(key)?;
+        let (_, value) = self.entries.remove(i);
+        if let Some(index) = &mut self.index {
+            index.remove(key);
+            for (name, _) in &self.entries[i..] {
+                *index.get_mut(name.as_str()).expect("index is in sync") -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.entries.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut(self.entries.iter_mut())
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &String> + ExactSizeIterator {
+        self.entries.iter().map(|(name, _)| name)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &JsonValue> + ExactSizeIterator {
+        self.entries.iter().map(|(_, value)| value)
+    }
+}
+
+/// Compares the members regardless of their order; see [`JsonMap`].
+impl PartialEq for JsonMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl fmt::Debug for JsonMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl From<Vec<(String, JsonValue)>> for JsonMap {
+    fn from(members: Vec<(String, JsonValue)>) -> Self {
+        members.into_iter().collect()
+    }
+}
+
+impl FromIterator<(String, JsonValue)> for JsonMap {
+    fn from_iter<I: IntoIterator<Item = (String, JsonValue)>>(iter: I) -> Self {
+        let mut map = JsonMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl Extend<(String, JsonValue)> for JsonMap {
+    fn extend<I: IntoIterator<Item = (String, JsonValue)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+/// A member of a [`JsonMap`] that may or may not exist, from
+/// [`JsonMap::entry`].
+pub enum Entry<'a> {
+    Occupied(OccupiedEntry<'a>),
+    Vacant(VacantEntry<'a>),
+}
+
+pub struct OccupiedEntry<'a> {
+    map: &'a mut JsonMap,
+    index: usize,
+}
+
+pub struct VacantEntry<'a> {
+    map: &'a mut JsonMap,
+    key: String,
+}
+
+impl<'a> Entry<'a> {
+    pub fn key(&self) -> &str {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: JsonValue) -> &'a mut JsonValue {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> JsonValue) -> &'a mut JsonValue {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Inserts `null` if the member does not exist.
+    pub fn or_null(self) -> &'a mut JsonValue {
+        self.or_insert(JsonValue::Null)
+    }
+
+    pub fn and_modify(mut self, f: impl FnOnce(&mut JsonValue)) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a> OccupiedEntry<'a> {
+    pub fn key(&self) -> &str {
+        &self.map.entries[self.index].0
+    }
+
+    pub fn get(&self) -> &JsonValue {
+        &self.map.entries[self.index].1
+    }
+
+    pub fn get_mut(&mut self) -> &mut JsonValue {
+        &mut self.map.entries[self.index].1
+    }
+
+    pub fn into_mut(self) -> &'a mut JsonValue {
+        &mut self.map.entries[self.index].1
+    }
+
+    /// Replaces the value and returns the old one.
+    pub fn insert(&mut self, value: JsonValue) -> JsonValue {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes the member, like [`JsonMap::remove`].
+    pub fn remove(self) -> JsonValue {
+        let key = self.map.entries[self.index].0.clone();
+        self.map.remove(&key).expect("entry is occupied")
+    }
+}
+
+impl<'a> VacantEntry<'a> {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Adds the member at the end of the map.
+    pub fn insert(self, value: JsonValue) -> &'a mut JsonValue {
+        let index = self.map.len();
+        self.map.insert(self.key, value);
+        &mut self.map.entries[index].1
+    }
+}
+
+pub struct Iter<'a>(slice::Iter<'a, (String, JsonValue)>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a String, &'a JsonValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, value)| (key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(key, value)| (key, value))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+pub struct IterMut<'a>(slice::IterMut<'a, (String, JsonValue)>);
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (&'a String, &'a mut JsonValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, value)| (&*key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for IterMut<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(key, value)| (&*key, value))
+    }
+}
+
+impl ExactSizeIterator for IterMut<'_> {}
+
+impl<'a> IntoIterator for &'a JsonMap {
+    type Item = (&'a String, &'a JsonValue);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut JsonMap {
+    type Item = (&'a String, &'a mut JsonValue);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> IterMut<'a> {
+        self.iter_mut()
+    }
+}
+
+impl IntoIterator for JsonMap {
+    type Item = (String, JsonValue);
+    type IntoIter = vec::IntoIter<(String, JsonValue)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(n: usize) -> JsonValue {
+        JsonValue::Number(n.into())
+    }
+
+    #[test]
+    fn test_insert_get_and_order() {
+        for size in [3, 50] {
+            let mut map = JsonMap::new();
+            for i in (0..size).rev() {
+                assert_eq!(map.insert(format!("k{}", i), number(i)), None);
+            }
+            assert_eq!(
+                map.insert("k1".to_string(), JsonValue::Null),
+                Some(number(1))
+            );
+
+            assert_eq!(map.len(), size);
+            assert_eq!(map.get("k0"), Some(&number(0)));
+            assert_eq!(map.get("k1"), Some(&JsonValue::Null));
+            assert_eq!(map.get("missing"), None);
+            let keys: Vec<_> = map.keys().cloned().collect();
+            let expected: Vec<_> = (0..size).rev().map(|i| format!("k{}", i)).collect();
+            assert_eq!(keys, expected);
+        }
+    }
+
+    #[test]
+    fn test_remove_keeps_order_and_index() {
+        let mut map: JsonMap = (0..20).map(|i| (format!("k{}", i), number(i))).collect();
+
+        assert_eq!(map.remove("k3"), Some(number(3)));
+        assert_eq!(map.remove("k3"), None);
+        assert_eq!(map.len(), 19);
+        for i in (0..20).filter(|&i| i != 3) {
+            assert_eq!(map.get(&format!("k{}", i)), Some(&number(i)));
+        }
+        assert_eq!(map.keys().nth(3).map(String::as_str), Some("k4"));
+        map.insert("k3".to_string(), JsonValue::Null);
+        assert_eq!(map.keys().last().map(String::as_str), Some("k3"));
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut map = JsonMap::new();
+
+        *map.entry("count").or_insert(number(0)) = number(1);
+        map.entry("count")
+            .and_modify(|count| *count = number(2))
+            .or_insert(number(0));
+        map.entry("list")
+            .or_insert_with(|| JsonValue::Array(Vec::new()));
+        assert_eq!(map.entry("list").key(), "list");
+        assert_eq!(map.get("count"), Some(&number(2)));
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["count", "list"]);
+
+        match map.entry("count") {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.insert(number(3)), number(2));
+                assert_eq!(entry.remove(), number(3));
+            }
+            Entry::Vacant(_) => panic!("expected an occupied entry"),
+        }
+        assert!(!map.contains_key("count"));
+        assert_eq!(*map.entry("none").or_null(), JsonValue::Null);
+    }
+
+    #[test]
+    fn test_equality_ignores_order() {
+        let a = JsonMap::from(vec![
+            ("a".to_string(), number(1)),
+            ("b".to_string(), number(2)),
+        ]);
+        let b = JsonMap::from(vec![
+            ("b".to_string(), number(2)),
+            ("a".to_string(), number(1)),
+        ]);
+        let c = JsonMap::from(vec![("a".to_string(), number(1))]);
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+}
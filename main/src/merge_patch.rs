@@ -0,0 +1,158 @@
+use crate::json_value::JsonValue;
+use crate::map::JsonMap;
+
+impl JsonValue {
+    /// Applies a JSON Merge Patch (RFC 7386).
+    ///
+    /// An object patch changes the members it names: `null` removes a
+    /// member, and any other value is merged into the member recursively,
+    /// so nested objects only change where the patch says. If `self` is not
+    /// an object it is replaced by an empty one first. Any other patch
+    /// replaces `self` entirely.
+    pub fn merge_patch(&mut self, patch: &JsonValue) {
+        let JsonValue::Object(members) = patch else {
+            *self = patch.clone();
+            return;
+        };
+        if !self.is_object() {
+            *self = JsonValue::new_obj();
+        }
+        let JsonValue::Object(obj) = self else {
+            unreachable!()
+        };
+        for (name, value) in members {
+            match value {
+                JsonValue::Null => {
+                    obj.remove(name);
+                }
+                value => obj.entry(name.as_str()).or_null().merge_patch(value),
+            }
+        }
+    }
+}
+
+/// Computes the merge patch that turns `original` into `target` when
+/// applied with [`JsonValue::merge_patch`].
+///
+/// Only changed members appear in the patch, and removed ones as `null`.
+/// Merge patches cannot set a member to `null`, so members of `target`
+/// that are `null` end up removed instead.
+pub fn merge_patch_diff(original: &JsonValue, target: &JsonValue) -> JsonValue {
+    let (JsonValue::Object(original), JsonValue::Object(target)) = (original, target) else {
+        return target.clone();
+    };
+    let mut patch = JsonMap::new();
+    for name in original.keys().filter(|name| !target.contains_key(name)) {
+        patch.insert(name.clone(), JsonValue::Null);
+    }
+    for (name, value) in target {
+        match original.get(name) {
+            Some(old) if old == value => {}
+            Some(old) => {
+                patch.insert(name.clone(), merge_patch_diff(old, value));
+            }
+            None => {
+                patch.insert(name.clone(), value.clone());
+            }
+        }
+    }
+    JsonValue::Object(patch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_rfc_examples() {
+        let cases = [
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": "b"}),
+                json!({"b": "c"}),
+                json!({"a": "b", "b": "c"}),
+            ),
+            (json!({"a": "b"}), json!({"a": null}), json!({})),
+            (
+                json!({"a": "b", "b": "c"}),
+                json!({"a": null}),
+                json!({"b": "c"}),
+            ),
+            (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+            (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+            (
+                json!({"a": {"b": "c"}}),
+                json!({"a": {"b": "d", "c": null}}),
+                json!({"a": {"b": "d"}}),
+            ),
+            (
+                json!({"a": [{"b": "c"}]}),
+                json!({"a": [1]}),
+                json!({"a": [1]}),
+            ),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+            (json!({"a": "foo"}), json!(null), json!(null)),
+            (json!({"a": "foo"}), json!("bar"), json!("bar")),
+            (
+                json!({"e": null}),
+                json!({"a": 1}),
+                json!({"e": null, "a": 1}),
+            ),
+            (
+                json!([1, 2]),
+                json!({"a": "b", "c": null}),
+                json!({"a": "b"}),
+            ),
+            (
+                json!({}),
+                json!({"a": {"bb": {"ccc": null}}}),
+                json!({"a": {"bb": {}}}),
+            ),
+        ];
+
+        for (original, patch, expected) in cases {
+            let mut value = original.clone();
+            value.merge_patch(&patch);
+            assert_eq!(value, expected, "{} patched with {}", original, patch);
+        }
+    }
+
+    #[test]
+    fn test_merge_patch_diff() {
+        let original = json!({
+            "title": "Goodbye!",
+            "author": {"givenName": "John", "familyName": "Doe"},
+            "tags": ["example", "sample"],
+            "content": "This will be unchanged",
+        });
+        let target = json!({
+            "title": "Hello!",
+            "author": {"givenName": "John"},
+            "tags": ["example"],
+            "content": "This will be unchanged",
+            "phoneNumber": "+01-123-456-7890",
+        });
+
+        let patch = merge_patch_diff(&original, &target);
+        assert_eq!(
+            patch,
+            json!({
+                "title": "Hello!",
+                "phoneNumber": "+01-123-456-7890",
+                "author": {"familyName": null},
+                "tags": ["example"],
+            })
+        );
+        let mut value = original.clone();
+        value.merge_patch(&patch);
+        assert_eq!(value, target);
+
+        assert_eq!(merge_patch_diff(&original, &original), json!({}));
+        assert_eq!(
+            merge_patch_diff(&json!([1]), &json!({"a": 1})),
+            json!({"a": 1})
+        );
+    }
+}
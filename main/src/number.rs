@@ -0,0 +1,534 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{ParseError, ParseErrorKind};
+use crate::lexer::{Lexer, Token};
+use crate::serializer;
+
+/// A JSON number, stored as `u64`, `i64` or `f64`, or as its original text.
+///
+/// By default the lexer picks the narrowest representation that holds the
+/// number exactly: integers without fraction or exponent become `u64` when
+/// they are non-negative and `i64` when they are negative, and everything
+/// else, including `-0` and integers outside the 64-bit ranges, becomes
+/// `f64`. With [`Lexer::with_arbitrary_precision`] numbers instead keep the
+/// exact text they were written with, which is available from
+/// [`as_str`](Self::as_str) and written back unchanged.
+///
+/// Numbers compare by their mathematical value, independent of the
+/// representation: `1`, `1.0` and `1e0` are equal. Comparisons between
+/// integers and floats are exact, so `9007199254740993` (2^53 + 1) is not
+/// equal to the nearest `f64`, `9007199254740992.0`. A number kept as text
+/// equals an `f64` if it has the value of the float's shortest decimal
+/// representation, so `0.1` equals `0.1f64`. `0.0` and `-0.0` are equal,
+/// and NaN is not equal to anything. Ordering follows the same rules.
+#[derive(Debug, Clone)]
+pub struct JsonNumber(N);
+
+#[derive(Debug, Clone)]
+enum N {
+    /// Always non-negative.
+    PosInt(u64),
+    /// Always negative.
+    NegInt(i64),
+    Float(f64),
+    /// Text that is valid under the RFC 8259 number grammar.
+    Text(Box<str>),
+}
+
+impl JsonNumber {
+    /// Parses the text of a JSON number. `text` must consist of an optional
+    /// `-`, digits, and optionally a fraction and an exponent.
+    pub(crate) fn parse(text: &str, is_integer: bool) -> Option<JsonNumber> {
+        if is_integer {
+            if text.starts_with('-') {
+                match text.parse::<i64>() {
+                    Ok(n) if n < 0 => return Some(JsonNumber(N::NegInt(n))),
+                    _ => {}
+                }
+            } else if let Ok(n) = text.parse::<u64>() {
+                return Some(JsonNumber(N::PosInt(n)));
+            }
+        }
+        match text.parse::<f64>() {
+            Ok(n) if n.is_finite() => Some(JsonNumber(N::Float(n))),
+            _ => None,
+        }
+    }
+
+    /// Keeps `text`, which must be valid under the RFC 8259 number grammar,
+    /// as the number's representation.
+    pub(crate) fn from_text(text: &str) -> JsonNumber {
+        JsonNumber(N::Text(text.into()))
+    }
+
+    /// Returns the exact text of the number if it was parsed with
+    /// [`Lexer::with_arbitrary_precision`] or created with
+    /// [`str::parse`].
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.0 {
+            N::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    pub fn is_u64(&self) -> bool {
+        self.as_u64().is_some()
+    }
+
+    /// Returns `true` if the number is stored as `f64`, i.e. it had a
+    /// fraction or exponent or did not fit into 64 bits and was not kept as
+    /// text.
+    pub fn is_f64(&self) -> bool {
+        matches!(self.0, N::Float(_))
+    }
+
+    /// Returns the number as `i64` if it is an integer in range. Floats
+    /// without a fractional part count as integers.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_i128().and_then(|n| i64::try_from(n).ok())
+    }
+
+    /// Returns the number as `u64` if it is a non-negative integer in range.
+    /// Floats without a fractional part count as integers.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_i128().and_then(|n| u64::try_from(n).ok())
+    }
+
+    /// Returns the number as `i128` if it is an integer in range, like
+    /// [`as_i64`](Self::as_i64). Useful for numbers kept as text.
+    pub fn as_i128(&self) -> Option<i128> {
+        match &self.0 {
+            N::PosInt(n) => Some((*n).into()),
+            N::NegInt(n) => Some((*n).into()),
+            N::Float(f) => float_to_i128(*f),
+            N::Text(text) => Decimal::parse(text).to_i128(),
+        }
+    }
+
+    /// Returns the number as `u128` if it is a non-negative integer in
+    /// range, like [`as_u64`](Self::as_u64).
+    pub fn as_u128(&self) -> Option<u128> {
+        match &self.0 {
+            N::Text(text) => Decimal::parse(text).to_u128(),
+            _ => self.as_i128().and_then(|n| u128::try_from(n).ok()),
+        }
+    }
+
+    /// Returns the number as `f64`, rounding to the nearest representable
+    /// value. Numbers kept as text that are too large for `f64` become
+    /// infinite; see [`as_finite_f64`](Self::as_finite_f64).
+    pub fn as_f64(&self) -> f64 {
+        match &self.0 {
+            N::PosInt(n) => *n as f64,
+            N::NegInt(n) => *n as f64,
+            N::Float(f) => *f,
+            N::Text(text) => text.parse().unwrap_or(f64::NAN),
+        }
+    }
+
+    /// Like [`as_f64`](Self::as_f64), but returns `None` instead of an
+    /// infinity or NaN.
+    pub fn as_finite_f64(&self) -> Option<f64> {
+        Some(self.as_f64()).filter(|f| f.is_finite())
+    }
+
+    fn to_decimal(&self) -> Option<Decimal> {
+        match &self.0 {
+            N::PosInt(n) => Some(Decimal::parse(&n.to_string())),
+            N::NegInt(n) => Some(Decimal::parse(&n.to_string())),
+            N::Float(f) if f.is_finite() => Some(Decimal::parse(&format!("{:e}", f))),
+            N::Float(_) => None,
+            N::Text(text) => Some(Decimal::parse(text)),
+        }
+    }
+}
+
+/// A finite number as `(-1)^negative * digits * 10^exponent`, where
+/// `digits` has neither leading nor trailing zeros. Zero has no digits and
+/// is never negative.
+#[derive(Debug, PartialEq, Eq)]
+struct Decimal {
+    negative: bool,
+    digits: String,
+    exponent: i64,
+}
+
+impl Decimal {
+    /// Decomposes a valid JSON number, or the `{:e}` output for a finite
+    /// `f64`.
+    fn parse(text: &str) -> Decimal {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (mantissa, exponent) = match text.find(['e', 'E']) {
+            Some(i) => {
+                let exponent = text[i + 1..].trim_start_matches('+');
+                // Exponents this large make the value overflow or vanish in
+                // any type; saturating keeps comparisons correct.
+                let saturated = if exponent.starts_with('-') {
+                    -(i64::MAX / 4)
+                } else {
+                    i64::MAX / 4
+                };
+                (&text[..i], exponent.parse().unwrap_or(saturated))
+            }
+            None => (text, 0),
+        };
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = format!("{}{}", int, frac);
+        let digits = digits.trim_start_matches('0');
+        let trimmed = digits.trim_end_matches('0');
+        let exponent = exponent - frac.len() as i64 + (digits.len() - trimmed.len()) as i64;
+
+        if trimmed.is_empty() {
+            return Decimal {
+                negative: false,
+                digits: String::new(),
+                exponent: 0,
+            };
+        }
+        Decimal {
+            negative,
+            digits: trimmed.to_string(),
+            exponent,
+        }
+    }
+
+    fn magnitude(&self) -> Option<u128> {
+        if self.digits.is_empty() {
+            return Some(0);
+        }
+        // Trailing zeros are stripped, so a negative exponent means a
+        // fractional part.
+        if self.exponent < 0 || self.exponent > 39 {
+            return None;
+        }
+        let mut n: u128 = self.digits.parse().ok()?;
+        for _ in 0..self.exponent {
+            n = n.checked_mul(10)?;
+        }
+        Some(n)
+    }
+
+    fn cmp_value(&self, other: &Decimal) -> Ordering {
+        let sign = |d: &Decimal| match (d.negative, d.digits.is_empty()) {
+            (true, _) => -1,
+            (false, true) => 0,
+            (false, false) => 1,
+        };
+        let by_sign = sign(self).cmp(&sign(other));
+        if by_sign != Ordering::Equal || self.digits.is_empty() {
+            return by_sign;
+        }
+        // With neither leading nor trailing zeros, the position of the
+        // leading digit decides first, and then the digits compare like
+        // strings.
+        let magnitude = |d: &Decimal| d.digits.len() as i64 + d.exponent;
+        let by_magnitude = magnitude(self)
+            .cmp(&magnitude(other))
+            .then_with(|| self.digits.cmp(&other.digits));
+        match self.negative {
+            true => by_magnitude.reverse(),
+            false => by_magnitude,
+        }
+    }
+
+    fn to_u128(&self) -> Option<u128> {
+        match self.negative {
+            true => None,
+            false => self.magnitude(),
+        }
+    }
+
+    fn to_i128(&self) -> Option<i128> {
+        let magnitude = self.magnitude()?;
+        if self.negative {
+            0i128.checked_sub_unsigned(magnitude)
+        } else {
+            i128::try_from(magnitude).ok()
+        }
+    }
+}
+
+/// Converts `f` to an integer if it has no fractional part and fits.
+fn float_to_i128(f: f64) -> Option<i128> {
+    // 2^127 is exactly representable; anything below it fits into i128.
+    const LIMIT: f64 = 170141183460469231731687303715884105728.0;
+    if f.fract() == 0.0 && (-LIMIT..LIMIT).contains(&f) {
+        Some(f as i128)
+    } else {
+        None
+    }
+}
+
+impl PartialEq for JsonNumber {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (N::Float(a), N::Float(b)) => a == b,
+            (N::Text(_), _) | (_, N::Text(_)) => {
+                self.to_decimal().is_some() && self.to_decimal() == other.to_decimal()
+            }
+            // A float that is not an integer has no `i128` and so never
+            // equals an integer.
+            _ => self.as_i128() == other.as_i128(),
+        }
+    }
+}
+
+impl PartialOrd for JsonNumber {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (&self.0, &other.0) {
+            (N::Float(a), N::Float(b)) => a.partial_cmp(b),
+            (N::PosInt(_) | N::NegInt(_), N::PosInt(_) | N::NegInt(_)) => {
+                self.as_i128().partial_cmp(&other.as_i128())
+            }
+            _ => match (self.to_decimal(), other.to_decimal()) {
+                (Some(a), Some(b)) => Some(a.cmp_value(&b)),
+                // NaN or an infinity.
+                _ => self.as_f64().partial_cmp(&other.as_f64()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for JsonNumber {
+    /// Writes the number as JSON. Integers are written exactly, floats so
+    /// that parsing them back yields the same `f64`; NaN and infinities have
+    /// no JSON representation and are written as `null`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            N::PosInt(n) => write!(f, "{}", n),
+            N::NegInt(n) => write!(f, "{}", n),
+            N::Float(n) => serializer::write_float(f, *n),
+            N::Text(text) => f.write_str(text),
+        }
+    }
+}
+
+impl FromStr for JsonNumber {
+    type Err = ParseError;
+
+    /// Parses a number written according to RFC 8259 and keeps its exact
+    /// text, as with [`Lexer::with_arbitrary_precision`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lexer = Lexer::new(s)
+            .with_strict(true)
+            .with_arbitrary_precision(true);
+        let token = lexer.next_token()?;
+        let span = lexer.token_span();
+        match (token, lexer.next_token()?) {
+            (Some(Token::Number(n)), None) if span.len() == s.len() => Ok(n),
+            _ => Err(ParseError::new(ParseErrorKind::InvalidNumber, 0, 1, 1)),
+        }
+    }
+}
+
+macro_rules! from_unsigned {
+    ($($t:ty),*) => {$(
+        impl From<$t> for JsonNumber {
+            fn from(n: $t) -> Self {
+                JsonNumber(N::PosInt(n as u64))
+            }
+        }
+    )*};
+}
+
+macro_rules! from_signed {
+    ($($t:ty),*) => {$(
+        impl From<$t> for JsonNumber {
+            fn from(n: $t) -> Self {
+                if n < 0 {
+                    JsonNumber(N::NegInt(n as i64))
+                } else {
+                    JsonNumber(N::PosInt(n as u64))
+                }
+            }
+        }
+    )*};
+}
+
+from_unsigned!(u8, u16, u32, u64, usize);
+from_signed!(i8, i16, i32, i64, isize);
+
+/// Values outside the 64-bit range keep their exact digits as text.
+impl From<i128> for JsonNumber {
+    fn from(n: i128) -> Self {
+        match (u64::try_from(n), i64::try_from(n)) {
+            (Ok(n), _) => JsonNumber(N::PosInt(n)),
+            (_, Ok(n)) => JsonNumber(N::NegInt(n)),
+            _ => JsonNumber::from_text(&n.to_string()),
+        }
+    }
+}
+
+/// Values outside the 64-bit range keep their exact digits as text.
+impl From<u128> for JsonNumber {
+    fn from(n: u128) -> Self {
+        match u64::try_from(n) {
+            Ok(n) => JsonNumber(N::PosInt(n)),
+            Err(_) => JsonNumber::from_text(&n.to_string()),
+        }
+    }
+}
+
+impl From<f32> for JsonNumber {
+    fn from(n: f32) -> Self {
+        JsonNumber(N::Float(n.into()))
+    }
+}
+
+impl From<f64> for JsonNumber {
+    fn from(n: f64) -> Self {
+        JsonNumber(N::Float(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> JsonNumber {
+        let is_integer = !text.contains(['.', 'e', 'E']);
+        JsonNumber::parse(text, is_integer).unwrap()
+    }
+
+    #[test]
+    fn test_narrowest_representation() {
+        assert_eq!(parse("18446744073709551615").as_u64(), Some(u64::MAX));
+        assert_eq!(parse("-9223372036854775808").as_i64(), Some(i64::MIN));
+        assert_eq!(parse("9007199254740993").as_i64(), Some(9007199254740993));
+        assert!(!parse("9007199254740993").is_f64());
+        assert!(parse("18446744073709551616").is_f64());
+        assert!(parse("-0").is_f64());
+        assert!(parse("-0").as_f64().is_sign_negative());
+        assert!(parse("1.0").is_f64());
+        assert_eq!(parse("-1").as_u64(), None);
+        assert_eq!(parse("1.5").as_i64(), None);
+        assert_eq!(parse("2e3").as_u64(), Some(2000));
+        assert_eq!(parse("12").as_f64(), 12.0);
+    }
+
+    #[test]
+    fn test_equality_across_representations() {
+        assert_eq!(JsonNumber::from(1), JsonNumber::from(1.0));
+        assert_eq!(JsonNumber::from(-3i64), JsonNumber::from(-3.0));
+        assert_eq!(JsonNumber::from(u64::MAX), parse("18446744073709551615"));
+        assert_eq!(JsonNumber::from(0), JsonNumber::from(-0.0));
+        assert_ne!(JsonNumber::from(1), JsonNumber::from(1.5));
+        assert_ne!(
+            JsonNumber::from(9007199254740993u64),
+            JsonNumber::from(9007199254740992.0)
+        );
+        assert_ne!(JsonNumber::from(f64::NAN), JsonNumber::from(f64::NAN));
+    }
+
+    #[test]
+    fn test_ordering_across_representations() {
+        assert!(JsonNumber::from(-1) < JsonNumber::from(0u64));
+        assert!(JsonNumber::from(1) < JsonNumber::from(1.5));
+        assert!(JsonNumber::from(9007199254740992.0) < JsonNumber::from(9007199254740993u64));
+        assert!(JsonNumber::from_text("1e400") > JsonNumber::from(f64::MAX));
+        assert!(JsonNumber::from_text("-0.125") < JsonNumber::from_text("-0.12"));
+        assert!(JsonNumber::from_text("0.12") < parse("0.123"));
+        assert!(JsonNumber::from_text("-2e-400") < JsonNumber::from(0));
+        assert_eq!(
+            parse("100").partial_cmp(&JsonNumber::from_text("1e2")),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(JsonNumber::from(f64::NAN).partial_cmp(&parse("1")), None);
+    }
+
+    #[test]
+    fn test_text_numbers() {
+        let n: JsonNumber = "0.1000000000000000055511151231257827".parse().unwrap();
+        assert_eq!(n.as_str(), Some("0.1000000000000000055511151231257827"));
+        assert_eq!(n.to_string(), "0.1000000000000000055511151231257827");
+        assert_eq!(n.as_f64(), 0.1);
+        assert_eq!(n.as_i64(), None);
+        assert_ne!(n, JsonNumber::from(0.1));
+
+        let big: JsonNumber = "1234567890123456789012345678901234567890".parse().unwrap();
+        assert_eq!(big.as_u64(), None);
+        assert_eq!(big.as_i128(), None);
+        assert!(big.as_f64() > 1.2e39);
+        let huge: JsonNumber = "1e400".parse().unwrap();
+        assert_eq!(huge.as_finite_f64(), None);
+        assert_eq!(huge.to_string(), "1e400");
+
+        let n: JsonNumber = "-1.50E+2".parse().unwrap();
+        assert_eq!(n.as_i64(), Some(-150));
+        assert_eq!(n.as_u64(), None);
+        assert_eq!(n, JsonNumber::from(-150));
+        assert_eq!(n, JsonNumber::from(-150.0));
+        assert_eq!(
+            "170141183460469231731687303715884105727"
+                .parse::<JsonNumber>()
+                .unwrap()
+                .as_i128(),
+            Some(i128::MAX)
+        );
+        assert_eq!(
+            "3400000000000000000000000000000000000000e-2"
+                .parse::<JsonNumber>()
+                .unwrap()
+                .as_u128(),
+            Some(34 * 10u128.pow(36))
+        );
+        assert_eq!("0.1".parse::<JsonNumber>(), Ok(JsonNumber::from(0.1)));
+        assert_eq!("-0.0".parse::<JsonNumber>(), Ok(JsonNumber::from(0)));
+        assert_eq!(
+            "0e99999999999999999999"
+                .parse::<JsonNumber>()
+                .unwrap()
+                .as_u64(),
+            Some(0)
+        );
+
+        for invalid in ["", "01", "+1", "1.", " 1", "1 ", "1 2", "\"1\"", "-"] {
+            assert!(invalid.parse::<JsonNumber>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_from_128_bit_integers() {
+        assert!(JsonNumber::from(5u128).is_u64());
+        assert!(JsonNumber::from(-5i128).is_i64());
+        assert_eq!(JsonNumber::from(-5i128), JsonNumber::from(-5i64));
+
+        let big = JsonNumber::from(u128::MAX);
+        assert_eq!(big.to_string(), "340282366920938463463374607431768211455");
+        assert_eq!(big.as_u128(), Some(u128::MAX));
+        let small = JsonNumber::from(i128::MIN);
+        assert_eq!(
+            small.to_string(),
+            "-170141183460469231731687303715884105728"
+        );
+        assert_eq!(small.as_i128(), Some(i128::MIN));
+        assert_ne!(
+            JsonNumber::from(u64::MAX as u128 + 1),
+            JsonNumber::from(u64::MAX as u128 + 2)
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            JsonNumber::from(u64::MAX).to_string(),
+            "18446744073709551615"
+        );
+        assert_eq!(
+            JsonNumber::from(i64::MIN).to_string(),
+            "-9223372036854775808"
+        );
+        assert_eq!(JsonNumber::from(0.5).to_string(), "0.5");
+        assert_eq!(JsonNumber::from(1e300).to_string(), "1e300");
+        assert_eq!(JsonNumber::from(f64::INFINITY).to_string(), "null");
+    }
+}
@@ -1,116 +1,318 @@
+use std::borrow::Cow;
+
+use crate::borrowed::BorrowedJsonValue;
+use crate::error::{ParseError, ParseErrorKind};
 use crate::json_value::JsonValue;
-use crate::lexer::{Lexer, Token};
+use crate::lexer::{Lexer, Span, Token};
+use crate::map::JsonMap;
+use crate::number::JsonNumber;
+
+/// Maximum nesting of arrays and objects accepted by default.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
 
 pub struct JsonParser<'a> {
     lexer: Lexer<'a>,
     current_token: Option<Token>,
+    current_span: Span,
+    /// Contents of `current_token` when it is a string borrowed from the
+    /// input.
+    current_borrowed: Option<&'a str>,
+    depth: usize,
+    max_depth: usize,
 }
 
 impl<'a> JsonParser<'a> {
-    pub fn new(input: &str) -> JsonParser {
+    pub fn new(input: &str) -> JsonParser<'_> {
         Self::from_lexer(Lexer::new(input))
     }
 
-    pub fn from_lexer(mut lexer: Lexer) -> JsonParser {
-        let current_token = lexer.next_token();
+    /// Parses JSON read incrementally from `reader`; see
+    /// [`Lexer::from_reader`]. Wrapping the reader in a `BufReader` is not
+    /// necessary.
+    pub fn from_reader<R: std::io::Read + 'a>(reader: R) -> JsonParser<'a> {
+        Self::from_lexer(Lexer::from_reader(reader))
+    }
+
+    pub fn from_lexer(lexer: Lexer<'_>) -> JsonParser<'_> {
         JsonParser {
             lexer,
-            current_token,
+            current_token: None,
+            current_span: Span::default(),
+            current_borrowed: None,
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
         }
     }
 
-    fn consume(&mut self) -> Option<Token> {
-        let token = std::mem::replace(&mut self.current_token, None);
-        self.current_token = self.lexer.next_token();
-        token
+    /// Sets how deeply arrays and objects may be nested before parsing
+    /// fails with [`ParseErrorKind::RecursionLimitExceeded`].
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// See [`Lexer::with_strict`].
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.lexer = self.lexer.with_strict(strict);
+        self
+    }
+
+    /// See [`Lexer::with_lossy_surrogates`].
+    pub fn with_lossy_surrogates(mut self, lossy_surrogates: bool) -> Self {
+        self.lexer = self.lexer.with_lossy_surrogates(lossy_surrogates);
+        self
+    }
+
+    /// See [`Lexer::with_arbitrary_precision`].
+    pub fn with_arbitrary_precision(mut self, arbitrary_precision: bool) -> Self {
+        self.lexer = self.lexer.with_arbitrary_precision(arbitrary_precision);
+        self
+    }
+
+    fn bump(&mut self) -> Result<(), ParseError> {
+        self.current_token = self.lexer.next_token()?;
+        self.current_span = self.lexer.token_span();
+        self.current_borrowed = self.lexer.take_borrowed();
+        Ok(())
     }
 
     fn peek(&self) -> Option<&Token> {
         self.current_token.as_ref()
     }
-    pub fn parse(&mut self) -> Option<JsonValue> {
-        self.parse_value()
+
+    fn error_at(&self, kind: ParseErrorKind, span: Span) -> ParseError {
+        ParseError::new(kind, span.start, span.line, span.column)
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        let kind = match self.current_token {
+            Some(_) => kind,
+            None => ParseErrorKind::UnexpectedEof,
+        };
+        self.error_at(kind, self.current_span)
+    }
+
+    /// Parses a single JSON value spanning the whole input.
+    pub fn parse(&mut self) -> Result<JsonValue, ParseError> {
+        self.parse_root()
     }
 
-    fn parse_value(&mut self) -> Option<JsonValue> {
-        match self.consume() {
-            Some(Token::LBrace) => self.parse_object(),
-            Some(Token::LBracket) => self.parse_array(),
-            Some(Token::Str(s)) => Some(JsonValue::String(s)),
-            Some(Token::Number(n)) => Some(JsonValue::Number(n)),
-            Some(Token::Bool(b)) => Some(JsonValue::Bool(b)),
-            Some(Token::Null) => Some(JsonValue::Null),
-            _ => None,
+    /// Like [`parse`](Self::parse), but strings and keys without escape
+    /// sequences borrow from the input instead of being copied.
+    pub fn parse_borrowed(&mut self) -> Result<BorrowedJsonValue<'a>, ParseError> {
+        self.lexer.set_borrowing(true);
+        let value = self.parse_root();
+        self.lexer.set_borrowing(false);
+        value
+    }
+
+    fn parse_root<V: Tree<'a>>(&mut self) -> Result<V, ParseError> {
+        self.bump()?;
+        let value = self.parse_value()?;
+        if self.current_token.is_some() {
+            return Err(self.error(ParseErrorKind::TrailingCharacters));
         }
+        Ok(value)
     }
 
-    fn parse_object(&mut self) -> Option<JsonValue> {
-        let mut result = Vec::new();
+    fn take_str<V: Tree<'a>>(&mut self, owned: String) -> V::Str {
+        V::str(owned, self.current_borrowed.take())
+    }
 
-        while let Some(token) = self.peek() {
-            match token {
-                Token::RBrace => break,
-                _ => {
-                    if !result.is_empty() {
-                        match token {
-                            Token::Comma => {
-                                self.consume();
-                            }
-                            _ => panic!("Expected comma after object element"),
-                        }
-                    }
-                    let id = match self.parse_value() {
-                        Some(JsonValue::String(s)) => s,
-                        _ => panic!("Invalid object element id"),
-                    };
-                    if self.consume() != Some(Token::Colon) {
-                        panic!("Expected colo after element id");
-                    }
+    fn parse_value<V: Tree<'a>>(&mut self) -> Result<V, ParseError> {
+        let value = match self.current_token.take() {
+            Some(Token::LBrace) => return self.parse_nested(Self::parse_object),
+            Some(Token::LBracket) => return self.parse_nested(Self::parse_array),
+            Some(Token::Str(s)) => V::string(self.take_str::<V>(s)),
+            Some(Token::Number(n)) => V::number(n),
+            Some(Token::Bool(b)) => V::bool(b),
+            Some(Token::Null) => V::null(),
+            token => {
+                self.current_token = token;
+                return Err(self.error(ParseErrorKind::UnexpectedToken));
+            }
+        };
+        self.bump()?;
+        Ok(value)
+    }
+
+    fn parse_nested<V: Tree<'a>>(
+        &mut self,
+        parse: fn(&mut Self) -> Result<V, ParseError>,
+    ) -> Result<V, ParseError> {
+        if self.depth >= self.max_depth {
+            return Err(self.error_at(ParseErrorKind::RecursionLimitExceeded, self.current_span));
+        }
+        self.depth += 1;
+        self.bump()?;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
 
-                    let value = self
-                        .parse_value()
-                        .unwrap_or_else(|| panic!("Unexpected end before closing object"));
-                    result.push((id, value));
+    fn parse_object<V: Tree<'a>>(&mut self) -> Result<V, ParseError> {
+        let mut result = V::Members::default();
+
+        if self.peek() == Some(&Token::RBrace) {
+            self.bump()?;
+            return Ok(V::object(result));
+        }
+
+        loop {
+            let id = match self.current_token.take() {
+                Some(Token::Str(s)) => self.take_str::<V>(s),
+                token => {
+                    self.current_token = token;
+                    return Err(self.error(ParseErrorKind::ExpectedKey));
                 }
+            };
+            self.bump()?;
+
+            if self.peek() != Some(&Token::Colon) {
+                return Err(self.error(ParseErrorKind::ExpectedColon));
+            }
+            self.bump()?;
+
+            let value = self.parse_value()?;
+            V::push_member(&mut result, id, value);
+
+            match self.peek() {
+                Some(Token::Comma) => self.bump()?,
+                Some(Token::RBrace) => break,
+                _ => return Err(self.error(ParseErrorKind::ExpectedComma)),
             }
         }
-        self.consume();
-        Some(JsonValue::Object(result))
+        self.bump()?;
+
+        Ok(V::object(result))
     }
 
-    fn parse_array(&mut self) -> Option<JsonValue> {
+    fn parse_array<V: Tree<'a>>(&mut self) -> Result<V, ParseError> {
         let mut result = Vec::new();
 
-        while let Some(token) = self.peek() {
-            match token {
-                Token::RBracket => break,
-                _ => {
-                    if !result.is_empty() {
-                        match token {
-                            Token::Comma => {
-                                self.consume();
-                            }
-                            _ => panic!("Expected comma after array element"),
-                        }
-                    }
+        if self.peek() == Some(&Token::RBracket) {
+            self.bump()?;
+            return Ok(V::array(result));
+        }
 
-                    if let Some(value) = self.parse_value() {
-                        result.push(value);
-                    } else {
-                        panic!("Unexpected end before closing array");
-                    }
-                }
+        loop {
+            result.push(self.parse_value()?);
+
+            match self.peek() {
+                Some(Token::Comma) => self.bump()?,
+                Some(Token::RBracket) => break,
+                _ => return Err(self.error(ParseErrorKind::ExpectedComma)),
             }
         }
-        self.consume();
+        self.bump()?;
+
+        Ok(V::array(result))
+    }
+}
+
+/// Lets the same parsing code build both [`JsonValue`] and
+/// [`BorrowedJsonValue`] trees.
+trait Tree<'a>: Sized {
+    type Str;
+
+    /// Makes a string from the lexed token, or from the input slice when the
+    /// lexer could borrow it.
+    fn str(owned: String, borrowed: Option<&'a str>) -> Self::Str;
+    type Members: Default;
+
+    fn push_member(members: &mut Self::Members, key: Self::Str, value: Self);
+    fn object(members: Self::Members) -> Self;
+    fn array(elements: Vec<Self>) -> Self;
+    fn string(s: Self::Str) -> Self;
+    fn number(n: JsonNumber) -> Self;
+    fn bool(b: bool) -> Self;
+    fn null() -> Self;
+}
+
+impl<'a> Tree<'a> for JsonValue {
+    type Str = String;
+    type Members = JsonMap;
+
+    fn str(owned: String, borrowed: Option<&'a str>) -> String {
+        match borrowed {
+            Some(s) => s.to_string(),
+            None => owned,
+        }
+    }
+
+    fn push_member(members: &mut JsonMap, key: String, value: JsonValue) {
+        members.insert(key, value);
+    }
 
-        Some(JsonValue::Array(result))
+    fn object(members: JsonMap) -> Self {
+        JsonValue::Object(members)
+    }
+
+    fn array(elements: Vec<JsonValue>) -> Self {
+        JsonValue::Array(elements)
+    }
+
+    fn string(s: String) -> Self {
+        JsonValue::String(s)
+    }
+
+    fn number(n: JsonNumber) -> Self {
+        JsonValue::Number(n)
+    }
+
+    fn bool(b: bool) -> Self {
+        JsonValue::Bool(b)
+    }
+
+    fn null() -> Self {
+        JsonValue::Null
+    }
+}
+
+impl<'a> Tree<'a> for BorrowedJsonValue<'a> {
+    type Str = Cow<'a, str>;
+    type Members = Vec<(Cow<'a, str>, BorrowedJsonValue<'a>)>;
+
+    fn str(owned: String, borrowed: Option<&'a str>) -> Cow<'a, str> {
+        match borrowed {
+            Some(s) => Cow::Borrowed(s),
+            None => Cow::Owned(owned),
+        }
+    }
+
+    fn push_member(members: &mut Self::Members, key: Cow<'a, str>, value: Self) {
+        members.push((key, value));
+    }
+
+    fn object(members: Self::Members) -> Self {
+        BorrowedJsonValue::Object(members)
+    }
+
+    fn array(elements: Vec<BorrowedJsonValue<'a>>) -> Self {
+        BorrowedJsonValue::Array(elements)
+    }
+
+    fn string(s: Cow<'a, str>) -> Self {
+        BorrowedJsonValue::String(s)
+    }
+
+    fn number(n: JsonNumber) -> Self {
+        BorrowedJsonValue::Number(n)
+    }
+
+    fn bool(b: bool) -> Self {
+        BorrowedJsonValue::Bool(b)
+    }
+
+    fn null() -> Self {
+        BorrowedJsonValue::Null
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::json;
 
     #[test]
     fn test_parse_simple_object() {
@@ -126,14 +328,11 @@ mod tests {
 
         assert_eq!(
             json_value,
-            JsonValue::Object(vec![
-                (
-                    "name".to_string(),
-                    JsonValue::String("John Doe".to_string())
-                ),
-                ("age".to_string(), JsonValue::Number(30.0)),
-                ("is_student".to_string(), JsonValue::Bool(false)),
-            ])
+            json!({
+                "name": "John Doe",
+                "age": 30,
+                "is_student": false,
+            })
         );
     }
 
@@ -146,11 +345,11 @@ mod tests {
         assert_eq!(
             json_value,
             JsonValue::Array(vec![
-                JsonValue::Number(1.0),
-                JsonValue::Number(2.0),
-                JsonValue::Number(3.0),
-                JsonValue::Number(4.0),
-                JsonValue::Number(5.0),
+                JsonValue::Number(1.into()),
+                JsonValue::Number(2.into()),
+                JsonValue::Number(3.into()),
+                JsonValue::Number(4.into()),
+                JsonValue::Number(5.into()),
             ])
         );
     }
@@ -174,32 +373,16 @@ mod tests {
 
         assert_eq!(
             json_value,
-            JsonValue::Object(vec![
-                (
-                    "name".to_string(),
-                    JsonValue::String("John Doe".to_string())
-                ),
-                ("age".to_string(), JsonValue::Number(30.0)),
-                ("is_student".to_string(), JsonValue::Bool(false)),
-                (
-                    "grades".to_string(),
-                    JsonValue::Array(vec![
-                        JsonValue::Number(90.0),
-                        JsonValue::Number(85.0),
-                        JsonValue::Number(92.0)
-                    ])
-                ),
-                (
-                    "info".to_string(),
-                    JsonValue::Object(vec![
-                        (
-                            "city".to_string(),
-                            JsonValue::String("New York".to_string())
-                        ),
-                        ("country".to_string(), JsonValue::String("USA".to_string())),
-                    ])
-                ),
-            ])
+            json!({
+                "name": "John Doe",
+                "age": 30,
+                "is_student": false,
+                "grades": [90, 85, 92],
+                "info": {
+                    "city": "New York",
+                    "country": "USA",
+                },
+            })
         );
     }
 
@@ -217,11 +400,11 @@ mod tests {
 
         assert_eq!(
             json_value,
-            JsonValue::Object(vec![
-                ("is_true".to_string(), JsonValue::Bool(true)),
-                ("is_false".to_string(), JsonValue::Bool(false)),
-                ("is_null".to_string(), JsonValue::Null),
-            ])
+            json!({
+                "is_true": true,
+                "is_false": false,
+                "is_null": null,
+            })
         );
     }
 
@@ -254,48 +437,199 @@ mod tests {
 
         assert_eq!(
             json_value,
-            JsonValue::Object(vec![
-                (
-                    "name".to_string(),
-                    JsonValue::String("John Doe".to_string())
-                ),
-                ("age".to_string(), JsonValue::Number(30.0)),
-                ("is_student".to_string(), JsonValue::Bool(false)),
-                (
-                    "grades".to_string(),
-                    JsonValue::Array(vec![
-                        JsonValue::Number(90.0),
-                        JsonValue::Number(85.0),
-                        JsonValue::Number(92.0)
-                    ])
-                ),
-                (
-                    "info".to_string(),
-                    JsonValue::Object(vec![
-                        (
-                            "city".to_string(),
-                            JsonValue::String("New York".to_string())
-                        ),
-                        ("country".to_string(), JsonValue::String("USA".to_string())),
-                    ])
-                ),
-                (
-                    "courses".to_string(),
-                    JsonValue::Array(vec![
-                        JsonValue::Object(vec![
-                            ("title".to_string(), JsonValue::String("Math".to_string())),
-                            ("credits".to_string(), JsonValue::Number(3.0)),
-                        ]),
-                        JsonValue::Object(vec![
-                            (
-                                "title".to_string(),
-                                JsonValue::String("History".to_string())
-                            ),
-                            ("credits".to_string(), JsonValue::Number(4.0)),
-                        ]),
-                    ])
-                ),
-            ])
+            json!({
+                "name": "John Doe",
+                "age": 30,
+                "is_student": false,
+                "grades": [90, 85, 92],
+                "info": {
+                    "city": "New York",
+                    "country": "USA",
+                },
+                "courses": [
+                    { "title": "Math", "credits": 3 },
+                    { "title": "History", "credits": 4 },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_exact_integers() {
+        let value = JsonParser::new(
+            r#"{"id": 1152921504606846977, "min": -9223372036854775808, "x": 1.5}"#,
+        )
+        .parse()
+        .unwrap();
+
+        assert_eq!(value.get("id").unwrap().as_u64(), Some(1152921504606846977));
+        assert_eq!(value.get("min").unwrap().as_i64(), Some(i64::MIN));
+        assert_eq!(value.get("x").unwrap().as_i64(), None);
+        assert_eq!(value.get("x").unwrap().as_f64(), Some(1.5));
+        assert_eq!(
+            value.to_string(),
+            r#"{"id":1152921504606846977,"min":-9223372036854775808,"x":1.5}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_arbitrary_precision_round_trip() {
+        let input = r#"[0.1000000000000000055511151231257827,1234567890123456789012345678901234567890,-0,1E+5,2.50e-3,1e400]"#;
+        let value = JsonParser::new(input)
+            .with_arbitrary_precision(true)
+            .parse()
+            .unwrap();
+
+        assert_eq!(value.to_string(), input);
+        assert_eq!(
+            value.get_arr(1),
+            Some(&JsonValue::Number(
+                "1234567890123456789012345678901234567890".parse().unwrap()
+            ))
+        );
+
+        // Spellings only accepted in lenient mode are converted as usual.
+        let value = JsonParser::new("[+1, 01, 1.]")
+            .with_arbitrary_precision(true)
+            .parse()
+            .unwrap();
+        assert_eq!(value.to_string(), "[1,1,1]");
+    }
+
+    #[test]
+    fn test_parse_duplicate_keys() {
+        let value = JsonParser::new(r#"{"a": 1, "b": 2, "a": 3}"#)
+            .parse()
+            .unwrap();
+
+        assert_eq!(value.get("a"), Some(&JsonValue::Number(3.into())));
+        assert_eq!(value.to_string(), r#"{"a":3,"b":2}"#);
+    }
+
+    #[test]
+    fn test_parse_keeps_member_order() {
+        let input =
+            r#"{"z":1,"y":{"k":true,"b":null,"a":[]},"x":2,"j":3,"i":4,"h":5,"g":6,"f":7,"e":8}"#;
+        let value = JsonParser::new(input).parse().unwrap();
+
+        let keys: Vec<_> = value.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["z", "y", "x", "j", "i", "h", "g", "f", "e"]);
+        let nested: Vec<_> = value["y"].as_object().unwrap().keys().collect();
+        assert_eq!(nested, ["k", "b", "a"]);
+        assert_eq!(value.to_string(), input);
+
+        // Equality ignores member order, so only the output shows it.
+        let reordered = JsonParser::new(r#"{"b":2,"a":1}"#).parse().unwrap();
+        assert_eq!(
+            reordered,
+            JsonParser::new(r#"{"a":1,"b":2}"#).parse().unwrap()
+        );
+        assert_eq!(reordered.to_string(), r#"{"b":2,"a":1}"#);
+    }
+
+    fn parse_err(input: &str) -> ParseError {
+        JsonParser::new(input).parse().unwrap_err()
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            ("", ParseErrorKind::UnexpectedEof),
+            ("[1, 2", ParseErrorKind::UnexpectedEof),
+            ("[1 2]", ParseErrorKind::ExpectedComma),
+            ("[1,]", ParseErrorKind::UnexpectedToken),
+            ("{\"a\" 1}", ParseErrorKind::ExpectedColon),
+            ("{1: 2}", ParseErrorKind::ExpectedKey),
+            ("{\"a\": 1,}", ParseErrorKind::ExpectedKey),
+            ("{\"a\": 1 \"b\": 2}", ParseErrorKind::ExpectedComma),
+            ("[\"abc]", ParseErrorKind::UnclosedString),
+            ("[-]", ParseErrorKind::InvalidNumber),
+            ("nul", ParseErrorKind::UnexpectedToken),
+            ("{} {}", ParseErrorKind::TrailingCharacters),
+            ("1 x", ParseErrorKind::TrailingCharacters),
+        ];
+
+        for (input, kind) in cases {
+            assert_eq!(parse_err(input).kind, kind, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let err = parse_err("{\n  \"a\": 1,\n  \"b\" 2\n}");
+
+        assert_eq!(err.kind, ParseErrorKind::ExpectedColon);
+        assert_eq!((err.offset, err.line, err.column), (18, 3, 7));
+    }
+
+    #[test]
+    fn test_parse_recursion_limit() {
+        let deep = "[".repeat(100_000);
+        assert_eq!(
+            parse_err(&deep).kind,
+            ParseErrorKind::RecursionLimitExceeded
+        );
+
+        let mut parser = JsonParser::new("[[[]]]").with_max_depth(2);
+        assert_eq!(
+            parser.parse().unwrap_err().kind,
+            ParseErrorKind::RecursionLimitExceeded
+        );
+    }
+
+    #[test]
+    fn test_parse_from_reader() {
+        let json_str = r#"{"name": "Zoë", "tags": ["\ud83d\ude00", "a\u00e9"], "n": -1.5e3}"#;
+        let value = JsonParser::from_reader(json_str.as_bytes()).parse();
+
+        assert_eq!(value, JsonParser::new(json_str).parse());
+        assert!(value.is_ok());
+    }
+
+    #[test]
+    fn test_parse_from_reader_invalid_utf8() {
+        let err = JsonParser::from_reader(&b"[\"ok\",\n \"\xff\"]"[..])
+            .parse()
+            .unwrap_err();
+
+        assert_eq!(err.kind, ParseErrorKind::InvalidUtf8);
+        assert_eq!((err.offset, err.line, err.column), (9, 2, 3));
+        assert!(!err.is_io());
+    }
+
+    struct FailingReader<'a>(&'a [u8]);
+
+    impl std::io::Read for FailingReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionReset,
+                    "connection reset",
+                ));
+            }
+            let n = self.0.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_parse_from_reader_io_error() {
+        let err = JsonParser::from_reader(FailingReader(b"[1, \"ab"))
+            .parse()
+            .unwrap_err();
+
+        assert!(err.is_io());
+        assert_eq!(err.kind, ParseErrorKind::Io);
+        assert_eq!(err.offset, 7);
+        assert_eq!(
+            err.io_error().map(|e| e.kind()),
+            Some(std::io::ErrorKind::ConnectionReset)
+        );
+        assert_eq!(
+            err.to_string(),
+            "I/O error: connection reset at line 1 column 8"
         );
     }
 }
@@ -0,0 +1,519 @@
+use crate::error::{PatchError, PatchErrorKind, PointerError};
+use crate::json_value::JsonValue;
+use crate::map::JsonMap;
+use crate::pointer::JsonPointer;
+use crate::{JsonDeserializable, JsonSerializable};
+
+/// A JSON Patch (RFC 6902): a list of operations that change a document.
+///
+/// Patches are read from and written as their JSON form, an array of
+/// operation objects like `{"op": "add", "path": "/a", "value": 1}`, and can
+/// be computed between two documents with [`diff`](Self::diff).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct JsonPatch {
+    operations: Vec<PatchOperation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOperation {
+    Add {
+        path: JsonPointer,
+        value: JsonValue,
+    },
+    Remove {
+        path: JsonPointer,
+    },
+    Replace {
+        path: JsonPointer,
+        value: JsonValue,
+    },
+    Move {
+        from: JsonPointer,
+        path: JsonPointer,
+    },
+    Copy {
+        from: JsonPointer,
+        path: JsonPointer,
+    },
+    Test {
+        path: JsonPointer,
+        value: JsonValue,
+    },
+}
+
+impl JsonPatch {
+    pub fn new() -> JsonPatch {
+        Self::default()
+    }
+
+    pub fn operations(&self) -> &[PatchOperation] {
+        &self.operations
+    }
+
+    pub fn push(&mut self, operation: PatchOperation) {
+        self.operations.push(operation);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Reads a patch from its JSON form. Members an operation does not use
+    /// are ignored.
+    pub fn from_value(value: &JsonValue) -> Result<JsonPatch, PatchError> {
+        let ops = value
+            .as_array()
+            .ok_or(PatchError::new(PatchErrorKind::NotAnArray, 0))?;
+        let operations = ops
+            .iter()
+            .enumerate()
+            .map(|(index, op)| {
+                PatchOperation::from_value(op).map_err(|kind| PatchError::new(kind, index))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(JsonPatch { operations })
+    }
+
+    /// Applies the operations in order. The patch is atomic: if an
+    /// operation fails, `value` is restored to what it was before and the
+    /// error is returned.
+    pub fn apply(&self, value: &mut JsonValue) -> Result<(), PatchError> {
+        let original = value.clone();
+        for (index, operation) in self.operations.iter().enumerate() {
+            if let Err(kind) = operation.apply(value) {
+                *value = original;
+                return Err(PatchError::new(kind, index));
+            }
+        }
+        Ok(())
+    }
+
+    /// Computes a patch that turns `from` into `to`.
+    ///
+    /// Objects and arrays are compared member by member and element by
+    /// element, so only the parts that differ are replaced. An array that
+    /// grows or shrinks in one place gets `add` or `remove` operations there
+    /// rather than changes to every element after it.
+    pub fn diff(from: &JsonValue, to: &JsonValue) -> JsonPatch {
+        let mut patch = JsonPatch::new();
+        diff_into(from, to, &mut JsonPointer::root(), &mut patch.operations);
+        patch
+    }
+}
+
+fn diff_into(
+    from: &JsonValue,
+    to: &JsonValue,
+    path: &mut JsonPointer,
+    ops: &mut Vec<PatchOperation>,
+) {
+    if from == to {
+        return;
+    }
+    match (from, to) {
+        (JsonValue::Object(from), JsonValue::Object(to)) => {
+            for key in from.keys().filter(|key| !to.contains_key(key)) {
+                path.push(key.as_str());
+                ops.push(PatchOperation::Remove { path: path.clone() });
+                path.pop();
+            }
+            for (key, value) in to {
+                path.push(key.as_str());
+                match from.get(key) {
+                    Some(old) => diff_into(old, value, path, ops),
+                    None => ops.push(PatchOperation::Add {
+                        path: path.clone(),
+                        value: value.clone(),
+                    }),
+                }
+                path.pop();
+            }
+        }
+        (JsonValue::Array(from), JsonValue::Array(to)) => {
+            // Elements equal at both ends are left alone; the ones in
+            // between are diffed pairwise, and the surplus added or removed.
+            let prefix = from.iter().zip(to).take_while(|(a, b)| a == b).count();
+            let suffix = from[prefix..]
+                .iter()
+                .rev()
+                .zip(to[prefix..].iter().rev())
+                .take_while(|(a, b)| a == b)
+                .count();
+            let from_middle = &from[prefix..from.len() - suffix];
+            let to_middle = &to[prefix..to.len() - suffix];
+            let common = from_middle.len().min(to_middle.len());
+
+            for (i, (old, new)) in from_middle.iter().zip(to_middle).enumerate() {
+                path.push_index(prefix + i);
+                diff_into(old, new, path, ops);
+                path.pop();
+            }
+            for (i, value) in to_middle.iter().enumerate().skip(common) {
+                path.push_index(prefix + i);
+                ops.push(PatchOperation::Add {
+                    path: path.clone(),
+                    value: value.clone(),
+                });
+                path.pop();
+            }
+            path.push_index(prefix + common);
+            for _ in common..from_middle.len() {
+                ops.push(PatchOperation::Remove { path: path.clone() });
+            }
+            path.pop();
+        }
+        _ => ops.push(PatchOperation::Replace {
+            path: path.clone(),
+            value: to.clone(),
+        }),
+    }
+}
+
+impl PatchOperation {
+    fn from_value(value: &JsonValue) -> Result<PatchOperation, PatchErrorKind> {
+        let invalid = PatchErrorKind::InvalidOperation;
+        let pointer = |name: &str| {
+            let text = value.get(name).and_then(JsonValue::as_str).ok_or(invalid)?;
+            JsonPointer::parse(text).map_err(PatchErrorKind::Pointer)
+        };
+        let member = |name: &str| value.get(name).cloned().ok_or(invalid);
+
+        let op = value.get("op").and_then(JsonValue::as_str).ok_or(invalid)?;
+        let path = pointer("path")?;
+        Ok(match op {
+            "add" => PatchOperation::Add {
+                path,
+                value: member("value")?,
+            },
+            "remove" => PatchOperation::Remove { path },
+            "replace" => PatchOperation::Replace {
+                path,
+                value: member("value")?,
+            },
+            "move" => PatchOperation::Move {
+                from: pointer("from")?,
+                path,
+            },
+            "copy" => PatchOperation::Copy {
+                from: pointer("from")?,
+                path,
+            },
+            "test" => PatchOperation::Test {
+                path,
+                value: member("value")?,
+            },
+            _ => return Err(invalid),
+        })
+    }
+
+    fn apply(&self, target: &mut JsonValue) -> Result<(), PatchErrorKind> {
+        let not_found = PatchErrorKind::Pointer(PointerError::NotFound);
+        match self {
+            PatchOperation::Add { path, value } => {
+                path.insert(target, value.clone())?;
+            }
+            PatchOperation::Remove { path } => {
+                path.remove(target)?;
+            }
+            PatchOperation::Replace { path, value } => {
+                *path.get_mut(target).ok_or(not_found)? = value.clone();
+            }
+            PatchOperation::Move { from, path } => {
+                if from == path {
+                    path.get(target).ok_or(not_found)?;
+                    return Ok(());
+                }
+                if path.tokens().starts_with(from.tokens()) {
+                    return Err(PatchErrorKind::MoveIntoChild);
+                }
+                let value = from.remove(target)?;
+                path.insert(target, value)?;
+            }
+            PatchOperation::Copy { from, path } => {
+                let value = from.get(target).ok_or(not_found)?.clone();
+                path.insert(target, value)?;
+            }
+            PatchOperation::Test { path, value } => {
+                if path.get(target).ok_or(not_found)? != value {
+                    return Err(PatchErrorKind::TestFailed);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn to_value(&self) -> JsonValue {
+        let (op, from, path, value) = match self {
+            PatchOperation::Add { path, value } => ("add", None, path, Some(value)),
+            PatchOperation::Remove { path } => ("remove", None, path, None),
+            PatchOperation::Replace { path, value } => ("replace", None, path, Some(value)),
+            PatchOperation::Move { from, path } => ("move", Some(from), path, None),
+            PatchOperation::Copy { from, path } => ("copy", Some(from), path, None),
+            PatchOperation::Test { path, value } => ("test", None, path, Some(value)),
+        };
+        let mut obj = JsonMap::with_capacity(3);
+        obj.insert("op".to_string(), op.into());
+        if let Some(from) = from {
+            obj.insert("from".to_string(), from.to_string().into());
+        }
+        obj.insert("path".to_string(), path.to_string().into());
+        if let Some(value) = value {
+            obj.insert("value".to_string(), value.clone());
+        }
+        JsonValue::Object(obj)
+    }
+}
+
+impl JsonSerializable for JsonPatch {
+    fn to_json(&self) -> JsonValue {
+        self.operations
+            .iter()
+            .map(PatchOperation::to_value)
+            .collect()
+    }
+}
+
+impl JsonDeserializable for JsonPatch {
+    fn from_json(value: &JsonValue) -> Option<Self> {
+        Self::from_value(value).ok()
+    }
+}
+
+impl FromIterator<PatchOperation> for JsonPatch {
+    fn from_iter<I: IntoIterator<Item = PatchOperation>>(iter: I) -> Self {
+        JsonPatch {
+            operations: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    fn patch(value: JsonValue) -> JsonPatch {
+        JsonPatch::from_value(&value).unwrap()
+    }
+
+    fn applied(mut doc: JsonValue, ops: JsonValue) -> Result<JsonValue, PatchError> {
+        patch(ops).apply(&mut doc).map(|_| doc)
+    }
+
+    #[test]
+    fn test_rfc_examples() {
+        assert_eq!(
+            applied(
+                json!({"foo": "bar"}),
+                json!([{"op": "add", "path": "/baz", "value": "qux"}])
+            ),
+            Ok(json!({"baz": "qux", "foo": "bar"}))
+        );
+        assert_eq!(
+            applied(
+                json!({"foo": ["bar", "baz"]}),
+                json!([{"op": "add", "path": "/foo/1", "value": "qux"}])
+            ),
+            Ok(json!({"foo": ["bar", "qux", "baz"]}))
+        );
+        assert_eq!(
+            applied(
+                json!({"baz": "qux", "foo": "bar"}),
+                json!([{"op": "remove", "path": "/baz"}])
+            ),
+            Ok(json!({"foo": "bar"}))
+        );
+        assert_eq!(
+            applied(
+                json!({"baz": "qux", "foo": "bar"}),
+                json!([{"op": "replace", "path": "/baz", "value": "boo"}])
+            ),
+            Ok(json!({"baz": "boo", "foo": "bar"}))
+        );
+        assert_eq!(
+            applied(
+                json!({"foo": {"bar": "baz", "waldo": "fred"}, "qux": {"corge": "grault"}}),
+                json!([{"op": "move", "from": "/foo/waldo", "path": "/qux/thud"}])
+            ),
+            Ok(json!({"foo": {"bar": "baz"}, "qux": {"corge": "grault", "thud": "fred"}}))
+        );
+        assert_eq!(
+            applied(
+                json!({"foo": ["all", "grass", "cows", "eat"]}),
+                json!([{"op": "move", "from": "/foo/1", "path": "/foo/3"}])
+            ),
+            Ok(json!({"foo": ["all", "cows", "eat", "grass"]}))
+        );
+        assert_eq!(
+            applied(
+                json!({"baz": "qux", "foo": ["a", 2, "c"]}),
+                json!([
+                    {"op": "test", "path": "/baz", "value": "qux"},
+                    {"op": "test", "path": "/foo/1", "value": 2},
+                ])
+            ),
+            Ok(json!({"baz": "qux", "foo": ["a", 2, "c"]}))
+        );
+        assert_eq!(
+            applied(
+                json!({"foo": "bar"}),
+                json!([{"op": "add", "path": "/child", "value": {"grandchild": {}}}])
+            ),
+            Ok(json!({"foo": "bar", "child": {"grandchild": {}}}))
+        );
+        assert_eq!(
+            applied(
+                json!({"foo": ["bar"]}),
+                json!([{"op": "add", "path": "/foo/-", "value": ["abc", "def"]}])
+            ),
+            Ok(json!({"foo": ["bar", ["abc", "def"]]}))
+        );
+        assert_eq!(
+            applied(
+                json!({"/": 9, "~1": 10}),
+                json!([{"op": "test", "path": "/~01", "value": 10}])
+            ),
+            Ok(json!({"/": 9, "~1": 10}))
+        );
+        assert_eq!(
+            applied(
+                json!({"a": 1}),
+                json!([{"op": "copy", "from": "/a", "path": "/b"}])
+            ),
+            Ok(json!({"a": 1, "b": 1}))
+        );
+    }
+
+    #[test]
+    fn test_apply_errors_roll_back() {
+        let doc = json!({"baz": "qux", "foo": ["a", 2, "c"]});
+        let failures = [
+            (
+                json!({"op": "test", "path": "/baz", "value": "bar"}),
+                PatchErrorKind::TestFailed,
+            ),
+            (
+                json!({"op": "add", "path": "/baz/bat", "value": "qux"}),
+                PatchErrorKind::Pointer(PointerError::NotAContainer),
+            ),
+            (
+                json!({"op": "replace", "path": "/missing", "value": 1}),
+                PatchErrorKind::Pointer(PointerError::NotFound),
+            ),
+            (
+                json!({"op": "move", "from": "/foo", "path": "/foo/0"}),
+                PatchErrorKind::MoveIntoChild,
+            ),
+            (
+                json!({"op": "remove", "path": "/foo/3"}),
+                PatchErrorKind::Pointer(PointerError::IndexOutOfBounds),
+            ),
+        ];
+
+        for (failing, kind) in failures {
+            let mut value = doc.clone();
+            let ops = json!([{"op": "add", "path": "/new", "value": 1}, {"op": "remove", "path": "/foo/0"}]);
+            let mut ops = patch(ops);
+            ops.push(PatchOperation::from_value(&failing).unwrap());
+
+            assert_eq!(ops.apply(&mut value), Err(PatchError::new(kind, 2)));
+            assert_eq!(value, doc);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |value: JsonValue| JsonPatch::from_value(&value).unwrap_err();
+
+        assert_eq!(
+            error(json!({})),
+            PatchError::new(PatchErrorKind::NotAnArray, 0)
+        );
+        assert_eq!(
+            error(json!([{"op": "remove", "path": ""}, {"op": "jump", "path": ""}])),
+            PatchError::new(PatchErrorKind::InvalidOperation, 1)
+        );
+        assert_eq!(
+            error(json!([{"op": "add", "path": "/a"}])).kind,
+            PatchErrorKind::InvalidOperation
+        );
+        assert_eq!(
+            error(json!([{"op": "copy", "path": "/a"}])).kind,
+            PatchErrorKind::InvalidOperation
+        );
+        assert_eq!(
+            error(json!([{"op": "remove", "path": "a"}])).kind,
+            PatchErrorKind::Pointer(PointerError::MissingSlash)
+        );
+        assert_eq!(
+            error(json!([{"op": "add", "path": "/a", "value": 1}, {"op": "remove"}])).to_string(),
+            "invalid operation in operation 1"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let ops = json!([
+            {"op": "add", "path": "/a~1b", "value": null},
+            {"op": "remove", "path": "/a"},
+            {"op": "replace", "path": "", "value": [1]},
+            {"op": "move", "from": "/a", "path": "/b"},
+            {"op": "copy", "from": "/a", "path": "/b"},
+            {"op": "test", "path": "/c", "value": {"d": true}},
+        ]);
+
+        assert_eq!(patch(ops.clone()).to_json(), ops);
+        assert_eq!(
+            JsonPatch::from_json(&ops).map(|p| p.operations().len()),
+            Some(6)
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let cases = [
+            (json!(1), json!(1), json!([])),
+            (
+                json!(1),
+                json!("a"),
+                json!([{"op": "replace", "path": "", "value": "a"}]),
+            ),
+            (
+                json!({"a": 1, "b": {"c": 2, "d": 3}}),
+                json!({"b": {"c": 2, "d": 4}, "e": 5}),
+                json!([
+                    {"op": "remove", "path": "/a"},
+                    {"op": "replace", "path": "/b/d", "value": 4},
+                    {"op": "add", "path": "/e", "value": 5},
+                ]),
+            ),
+            (
+                json!([1, 2, 3, 4]),
+                json!([0, 1, 2, 3, 4]),
+                json!([{"op": "add", "path": "/0", "value": 0}]),
+            ),
+            (
+                json!([1, 2, 3, 4]),
+                json!([1, 4]),
+                json!([{"op": "remove", "path": "/1"}, {"op": "remove", "path": "/1"}]),
+            ),
+            (
+                json!([1, {"a": 1}, 3]),
+                json!([1, {"a": 2}, 3, 4]),
+                json!([
+                    {"op": "replace", "path": "/1/a", "value": 2},
+                    {"op": "add", "path": "/3", "value": 4},
+                ]),
+            ),
+        ];
+
+        for (from, to, expected) in cases {
+            let diff = JsonPatch::diff(&from, &to);
+            assert_eq!(diff.to_json(), expected, "{} -> {}", from, to);
+
+            let mut value = from.clone();
+            diff.apply(&mut value).unwrap();
+            assert_eq!(value, to);
+        }
+    }
+}
@@ -0,0 +1,1320 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{PathError, PathErrorKind};
+use crate::json_value::JsonValue;
+use crate::number::JsonNumber;
+use crate::pointer::JsonPointer;
+use crate::regex::Regex;
+
+/// The largest index or slice bound JSONPath allows, 2^53 - 1.
+const MAX_INT: i64 = (1 << 53) - 1;
+/// How deeply parentheses, filters and function calls may nest, so that
+/// parsing a hostile query cannot overflow the stack.
+const MAX_NESTING: usize = 128;
+
+/// A compiled JSONPath query (RFC 9535), like
+/// `$.courses[?match(@.title, 'M.*')].credits`.
+///
+/// Parse a query once and run it against any number of documents with
+/// [`select`](Self::select), which returns each selected value along with
+/// its [`NormalizedPath`], or [`select_values`](Self::select_values).
+///
+/// All of RFC 9535 is supported: child and descendant segments, name,
+/// wildcard, index, slice and filter selectors, comparisons and the
+/// `length`, `count`, `match`, `search` and `value` functions. Object
+/// members are visited in document order.
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    query: Query,
+}
+
+/// The location of a selected value, written like `$['courses'][1]`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct NormalizedPath {
+    elements: Vec<PathElement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathElement {
+    Name(String),
+    Index(usize),
+}
+
+/// A value selected by a [`JsonPath`] and where it was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryNode<'v> {
+    pub path: NormalizedPath,
+    pub value: &'v JsonValue,
+}
+
+impl JsonPath {
+    pub fn parse(text: &str) -> Result<JsonPath, PathError> {
+        let mut parser = Parser {
+            input: text,
+            pos: 0,
+            depth: 0,
+        };
+        let query = parser.query()?;
+        if parser.pos < text.len() {
+            return Err(parser.unexpected());
+        }
+        Ok(JsonPath { query })
+    }
+
+    pub fn select<'v>(&self, value: &'v JsonValue) -> Vec<QueryNode<'v>> {
+        let start = Located {
+            steps: Vec::new(),
+            value,
+        };
+        self.query
+            .select(start, value)
+            .into_iter()
+            .map(|node| QueryNode {
+                path: NormalizedPath {
+                    elements: node.steps.into_iter().map(PathElement::from).collect(),
+                },
+                value: node.value,
+            })
+            .collect()
+    }
+
+    /// Like [`select`](Self::select), without building the paths.
+    pub fn select_values<'v>(&self, value: &'v JsonValue) -> Vec<&'v JsonValue> {
+        self.query.select(value, value)
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = PathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl NormalizedPath {
+    pub fn elements(&self) -> &[PathElement] {
+        &self.elements
+    }
+
+    /// Converts to a [`JsonPointer`] to the same value, for example to
+    /// change it with [`JsonPointer::get_mut`].
+    pub fn to_pointer(&self) -> JsonPointer {
+        self.elements
+            .iter()
+            .map(|element| match element {
+                PathElement::Name(name) => name.clone(),
+                PathElement::Index(index) => index.to_string(),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for NormalizedPath {
+    /// Writes the path in the normalized form RFC 9535 defines, with names
+    /// in single quotes.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("$")?;
+        for element in &self.elements {
+            match element {
+                PathElement::Index(index) => write!(f, "[{}]", index)?,
+                PathElement::Name(name) => {
+                    f.write_str("['")?;
+                    for c in name.chars() {
+                        match c {
+                            '\'' => f.write_str("\\'")?,
+                            '\\' => f.write_str("\\\\")?,
+                            '\u{8}' => f.write_str("\\b")?,
+                            '\u{c}' => f.write_str("\\f")?,
+                            '\n' => f.write_str("\\n")?,
+                            '\r' => f.write_str("\\r")?,
+                            '\t' => f.write_str("\\t")?,
+                            c if c < ' ' => write!(f, "\\u{:04x}", c as u32)?,
+                            c => fmt::Write::write_char(f, c)?,
+                        }
+                    }
+                    f.write_str("']")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Query {
+    /// Whether the query starts at `@`, the value a filter is testing,
+    /// rather than at `$`.
+    relative: bool,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Child(Vec<Selector>),
+    Descendant(Vec<Selector>),
+}
+
+#[derive(Debug, Clone)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    },
+    Filter(Expr),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, CompareOp, Operand),
+    /// A query, true if it selects anything.
+    Exists(Query),
+    /// A function returning a logical value.
+    Test(FunctionCall),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A comparison side or function argument.
+#[derive(Debug, Clone)]
+enum Operand {
+    Literal(JsonValue),
+    Query(Query),
+    Function(FunctionCall),
+}
+
+#[derive(Debug, Clone)]
+struct FunctionCall {
+    function: Function,
+    args: Vec<Operand>,
+    /// The pattern of `match` and `search`, compiled up front when it is a
+    /// literal.
+    regex: Option<Regex>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Length,
+    Count,
+    Match,
+    Search,
+    Value,
+}
+
+/// The types of the function extension type system.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    /// A JSON value or nothing.
+    Value,
+    Logical,
+    /// A list of nodes selected by a query.
+    Nodes,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        Some(match name {
+            "length" => Function::Length,
+            "count" => Function::Count,
+            "match" => Function::Match,
+            "search" => Function::Search,
+            "value" => Function::Value,
+            _ => return None,
+        })
+    }
+
+    fn params(self) -> &'static [Type] {
+        match self {
+            Function::Length => &[Type::Value],
+            Function::Count | Function::Value => &[Type::Nodes],
+            Function::Match | Function::Search => &[Type::Value, Type::Value],
+        }
+    }
+
+    fn result(self) -> Type {
+        match self {
+            Function::Length | Function::Count | Function::Value => Type::Value,
+            Function::Match | Function::Search => Type::Logical,
+        }
+    }
+}
+
+/// One step from a value to a member or element.
+#[derive(Debug, Clone, Copy)]
+enum Step<'v> {
+    Name(&'v str),
+    Index(usize),
+}
+
+impl From<Step<'_>> for PathElement {
+    fn from(step: Step<'_>) -> Self {
+        match step {
+            Step::Name(name) => PathElement::Name(name.to_string()),
+            Step::Index(index) => PathElement::Index(index),
+        }
+    }
+}
+
+/// A node during selection. Query results track their location, while
+/// queries inside filters only need the values.
+trait Node<'v>: Sized {
+    fn value(&self) -> &'v JsonValue;
+    fn child(&self, step: Step<'v>, value: &'v JsonValue) -> Self;
+}
+
+impl<'v> Node<'v> for &'v JsonValue {
+    fn value(&self) -> &'v JsonValue {
+        self
+    }
+
+    fn child(&self, _step: Step<'v>, value: &'v JsonValue) -> Self {
+        value
+    }
+}
+
+struct Located<'v> {
+    steps: Vec<Step<'v>>,
+    value: &'v JsonValue,
+}
+
+impl<'v> Node<'v> for Located<'v> {
+    fn value(&self) -> &'v JsonValue {
+        self.value
+    }
+
+    fn child(&self, step: Step<'v>, value: &'v JsonValue) -> Self {
+        let mut steps = Vec::with_capacity(self.steps.len() + 1);
+        steps.extend_from_slice(&self.steps);
+        steps.push(step);
+        Located { steps, value }
+    }
+}
+
+fn for_each_child<'v, N: Node<'v>>(node: &N, mut f: impl FnMut(N)) {
+    match node.value() {
+        JsonValue::Object(obj) => {
+            for (name, value) in obj {
+                f(node.child(Step::Name(name), value));
+            }
+        }
+        JsonValue::Array(arr) => {
+            for (index, value) in arr.iter().enumerate() {
+                f(node.child(Step::Index(index), value));
+            }
+        }
+        _ => {}
+    }
+}
+
+impl Query {
+    fn select<'v, N: Node<'v>>(&self, start: N, root: &'v JsonValue) -> Vec<N> {
+        let mut nodes = vec![start];
+        for segment in &self.segments {
+            let mut selected = Vec::new();
+            for node in &nodes {
+                segment.select(node, root, &mut selected);
+            }
+            nodes = selected;
+        }
+        nodes
+    }
+
+    /// Whether the query selects at most one node whatever the document,
+    /// which lets it be compared or passed as a value.
+    fn is_singular(&self) -> bool {
+        self.segments.iter().all(|segment| match segment {
+            Segment::Child(selectors) => matches!(
+                selectors.as_slice(),
+                [Selector::Name(_) | Selector::Index(_)]
+            ),
+            Segment::Descendant(_) => false,
+        })
+    }
+
+    fn start<'v>(&self, current: &'v JsonValue, root: &'v JsonValue) -> &'v JsonValue {
+        match self.relative {
+            true => current,
+            false => root,
+        }
+    }
+}
+
+impl Segment {
+    fn select<'v, N: Node<'v>>(&self, node: &N, root: &'v JsonValue, out: &mut Vec<N>) {
+        match self {
+            Segment::Child(selectors) => {
+                for selector in selectors {
+                    selector.select(node, root, out);
+                }
+            }
+            // The node itself, then its descendants in document order.
+            Segment::Descendant(selectors) => {
+                for selector in selectors {
+                    selector.select(node, root, out);
+                }
+                for_each_child(node, |child| self.select(&child, root, out));
+            }
+        }
+    }
+}
+
+impl Selector {
+    fn select<'v, N: Node<'v>>(&self, node: &N, root: &'v JsonValue, out: &mut Vec<N>) {
+        match (self, node.value()) {
+            (Selector::Name(name), JsonValue::Object(obj)) => {
+                if let Some((name, value)) = obj.get_key_value(name) {
+                    out.push(node.child(Step::Name(name), value));
+                }
+            }
+            (Selector::Wildcard, _) => for_each_child(node, |child| out.push(child)),
+            (Selector::Index(index), JsonValue::Array(arr)) => {
+                let index = match *index < 0 {
+                    true => arr.len().checked_sub(index.unsigned_abs() as usize),
+                    false => Some(*index as usize),
+                };
+                if let Some((index, value)) = index.and_then(|i| Some((i, arr.get(i)?))) {
+                    out.push(node.child(Step::Index(index), value));
+                }
+            }
+            (Selector::Slice { start, end, step }, JsonValue::Array(arr)) => {
+                for index in slice_indices(arr.len(), *start, *end, step.unwrap_or(1)) {
+                    out.push(node.child(Step::Index(index), &arr[index]));
+                }
+            }
+            (Selector::Filter(expr), _) => for_each_child(node, |child| {
+                if expr.test(child.value(), root) {
+                    out.push(child);
+                }
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// Returns the indices an array slice selects, in order, following RFC
+/// 9535 section 2.3.4.2.2.
+fn slice_indices(
+    len: usize,
+    start: Option<i64>,
+    end: Option<i64>,
+    step: i64,
+) -> impl Iterator<Item = usize> {
+    let len = len as i64;
+    let normalize = |i: i64| if i < 0 { len + i } else { i };
+    let (mut i, bound) = match step {
+        0 => (0, 0),
+        1.. => (
+            normalize(start.unwrap_or(0)).clamp(0, len),
+            normalize(end.unwrap_or(len)).clamp(0, len),
+        ),
+        _ => (
+            normalize(start.unwrap_or(len - 1)).clamp(-1, len - 1),
+            end.map_or(-1, |end| normalize(end).clamp(-1, len - 1)),
+        ),
+    };
+    std::iter::from_fn(move || {
+        let more = match step {
+            0 => false,
+            1.. => i < bound,
+            _ => bound < i,
+        };
+        if !more {
+            return None;
+        }
+        let index = i as usize;
+        i += step;
+        Some(index)
+    })
+}
+
+impl Expr {
+    fn test(&self, current: &JsonValue, root: &JsonValue) -> bool {
+        match self {
+            Expr::Or(exprs) => exprs.iter().any(|expr| expr.test(current, root)),
+            Expr::And(exprs) => exprs.iter().all(|expr| expr.test(current, root)),
+            Expr::Not(expr) => !expr.test(current, root),
+            Expr::Compare(lhs, op, rhs) => {
+                let lhs = lhs.value(current, root);
+                let rhs = rhs.value(current, root);
+                compare(lhs.as_deref(), *op, rhs.as_deref())
+            }
+            Expr::Exists(query) => !query.select(query.start(current, root), root).is_empty(),
+            Expr::Test(call) => call.test(current, root),
+        }
+    }
+}
+
+/// Compares two values, either of which may be nothing: an empty query
+/// result or a function result that is not a value.
+fn compare(lhs: Option<&JsonValue>, op: CompareOp, rhs: Option<&JsonValue>) -> bool {
+    let less = |a: Option<&JsonValue>, b: Option<&JsonValue>| match (a, b) {
+        (Some(JsonValue::Number(a)), Some(JsonValue::Number(b))) => a < b,
+        (Some(JsonValue::String(a)), Some(JsonValue::String(b))) => a < b,
+        _ => false,
+    };
+    match op {
+        CompareOp::Eq => lhs == rhs,
+        CompareOp::Ne => lhs != rhs,
+        CompareOp::Lt => less(lhs, rhs),
+        CompareOp::Le => less(lhs, rhs) || lhs == rhs,
+        CompareOp::Gt => less(rhs, lhs),
+        CompareOp::Ge => less(rhs, lhs) || lhs == rhs,
+    }
+}
+
+impl Operand {
+    /// Evaluates an operand of value type: a literal, a singular query or a
+    /// function returning a value.
+    fn value<'a>(
+        &'a self,
+        current: &'a JsonValue,
+        root: &'a JsonValue,
+    ) -> Option<Cow<'a, JsonValue>> {
+        match self {
+            Operand::Literal(value) => Some(Cow::Borrowed(value)),
+            Operand::Query(query) => query
+                .select(query.start(current, root), root)
+                .first()
+                .map(|value| Cow::Borrowed(*value)),
+            Operand::Function(call) => call.value(current, root),
+        }
+    }
+
+    /// Evaluates an operand of nodes type, which is always a query.
+    fn nodes<'a>(&'a self, current: &'a JsonValue, root: &'a JsonValue) -> Vec<&'a JsonValue> {
+        match self {
+            Operand::Query(query) => query.select(query.start(current, root), root),
+            _ => unreachable!("only queries have nodes type"),
+        }
+    }
+}
+
+impl FunctionCall {
+    fn value<'a>(
+        &'a self,
+        current: &'a JsonValue,
+        root: &'a JsonValue,
+    ) -> Option<Cow<'a, JsonValue>> {
+        match self.function {
+            Function::Length => {
+                let len = match self.args[0].value(current, root)?.as_ref() {
+                    JsonValue::String(s) => s.chars().count(),
+                    JsonValue::Array(arr) => arr.len(),
+                    JsonValue::Object(obj) => obj.len(),
+                    _ => return None,
+                };
+                Some(Cow::Owned(len.into()))
+            }
+            Function::Count => Some(Cow::Owned(self.args[0].nodes(current, root).len().into())),
+            Function::Value => match self.args[0].nodes(current, root).as_slice() {
+                [value] => Some(Cow::Borrowed(*value)),
+                _ => None,
+            },
+            Function::Match | Function::Search => unreachable!("not a value function"),
+        }
+    }
+
+    fn test(&self, current: &JsonValue, root: &JsonValue) -> bool {
+        let subject = self.args[0].value(current, root);
+        let pattern = self.args[1].value(current, root);
+        let (Some(JsonValue::String(subject)), Some(JsonValue::String(pattern))) =
+            (subject.as_deref(), pattern.as_deref())
+        else {
+            return false;
+        };
+        let compiled;
+        let regex = match &self.regex {
+            Some(regex) => regex,
+            None => match Regex::new(pattern) {
+                Some(regex) => {
+                    compiled = regex;
+                    &compiled
+                }
+                None => return false,
+            },
+        };
+        match self.function {
+            Function::Match => regex.is_match(subject),
+            Function::Search => regex.is_match_within(subject),
+            _ => unreachable!("not a logical function"),
+        }
+    }
+}
+
+struct Parser<'q> {
+    input: &'q str,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.input[self.pos..].chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += c.len_utf8();
+        }
+        found
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        let found = self.input[self.pos..].starts_with(s);
+        if found {
+            self.pos += s.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), PathError> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => Err(self.unexpected()),
+        }
+    }
+
+    fn error(&self, kind: PathErrorKind, offset: usize) -> PathError {
+        PathError::new(kind, offset)
+    }
+
+    fn unexpected(&self) -> PathError {
+        match self.peek() {
+            Some(_) => self.error(PathErrorKind::UnexpectedCharacter, self.pos),
+            None => self.error(PathErrorKind::UnexpectedEof, self.pos),
+        }
+    }
+
+    /// Runs `parse` one nesting level deeper, failing past [`MAX_NESTING`].
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, PathError>,
+    ) -> Result<T, PathError> {
+        if self.depth >= MAX_NESTING {
+            return Err(self.error(PathErrorKind::NestingTooDeep, self.pos));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn query(&mut self) -> Result<Query, PathError> {
+        let relative = match self.peek() {
+            Some('$') => false,
+            Some('@') => true,
+            _ => return Err(self.unexpected()),
+        };
+        self.pos += 1;
+        let mut segments = Vec::new();
+        loop {
+            let before = self.pos;
+            self.skip_whitespace();
+            match self.peek() {
+                Some('[') => segments.push(Segment::Child(self.bracketed()?)),
+                Some('.') if self.peek_second() == Some('.') => {
+                    self.pos += 2;
+                    let selectors = match self.peek() {
+                        Some('[') => self.bracketed()?,
+                        _ => vec![self.shorthand()?],
+                    };
+                    segments.push(Segment::Descendant(selectors));
+                }
+                Some('.') => {
+                    self.pos += 1;
+                    segments.push(Segment::Child(vec![self.shorthand()?]));
+                }
+                _ => {
+                    self.pos = before;
+                    return Ok(Query { relative, segments });
+                }
+            }
+        }
+    }
+
+    /// Parses `*` or a member name after `.` or `..`.
+    fn shorthand(&mut self) -> Result<Selector, PathError> {
+        if self.eat('*') {
+            return Ok(Selector::Wildcard);
+        }
+        let start = self.pos;
+        let is_first = |c: char| c.is_ascii_alphabetic() || c == '_' || !c.is_ascii();
+        if !self.peek().is_some_and(is_first) {
+            return Err(self.unexpected());
+        }
+        while self
+            .peek()
+            .is_some_and(|c| is_first(c) || c.is_ascii_digit())
+        {
+            self.bump();
+        }
+        Ok(Selector::Name(self.input[start..self.pos].to_string()))
+    }
+
+    fn bracketed(&mut self) -> Result<Vec<Selector>, PathError> {
+        self.expect('[')?;
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.selector()?);
+            self.skip_whitespace();
+            if self.eat(']') {
+                return Ok(selectors);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector, PathError> {
+        match self.peek() {
+            Some('\'' | '"') => Ok(Selector::Name(self.string()?)),
+            Some('*') => {
+                self.pos += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.pos += 1;
+                self.skip_whitespace();
+                Ok(Selector::Filter(self.nested(Self::or_expr)?))
+            }
+            _ => {
+                let start = self.int()?;
+                self.skip_whitespace();
+                if !self.eat(':') {
+                    return match start {
+                        Some(index) => Ok(Selector::Index(index)),
+                        None => Err(self.unexpected()),
+                    };
+                }
+                self.skip_whitespace();
+                let end = self.int()?;
+                self.skip_whitespace();
+                let step = match self.eat(':') {
+                    true => {
+                        self.skip_whitespace();
+                        self.int()?
+                    }
+                    false => None,
+                };
+                Ok(Selector::Slice { start, end, step })
+            }
+        }
+    }
+
+    /// Parses an integer without leading zeros in the exactly representable
+    /// range, if there is one.
+    fn int(&mut self) -> Result<Option<i64>, PathError> {
+        let start = self.pos;
+        let negative = self.eat('-');
+        let digits_start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits = &self.input[digits_start..self.pos];
+        if digits.is_empty() {
+            return match negative {
+                true => Err(self.unexpected()),
+                false => Ok(None),
+            };
+        }
+        let leading_zero = digits.starts_with('0') && (digits.len() > 1 || negative);
+        match self.input[start..self.pos].parse::<i64>() {
+            Ok(n) if !leading_zero && (-MAX_INT..=MAX_INT).contains(&n) => Ok(Some(n)),
+            _ => Err(self.error(PathErrorKind::InvalidNumber, start)),
+        }
+    }
+
+    /// Parses a string literal in single or double quotes.
+    fn string(&mut self) -> Result<String, PathError> {
+        let quote = self.bump().ok_or_else(|| self.unexpected())?;
+        let mut out = String::new();
+        loop {
+            let start = self.pos;
+            match self.bump() {
+                None => return Err(self.unexpected()),
+                Some(c) if c == quote => return Ok(out),
+                Some('\\') => {
+                    let c = match self.bump() {
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some(c @ ('/' | '\\')) => c,
+                        Some(c) if c == quote => c,
+                        Some('u') => self.unicode_escape(start)?,
+                        _ => return Err(self.error(PathErrorKind::InvalidEscape, start)),
+                    };
+                    out.push(c);
+                }
+                Some(c) if c < ' ' => {
+                    return Err(self.error(PathErrorKind::UnexpectedCharacter, start))
+                }
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    /// Parses the hex digits of a `\u` escape, and a second escape for the
+    /// low half of a surrogate pair.
+    fn unicode_escape(&mut self, start: usize) -> Result<char, PathError> {
+        let invalid = self.error(PathErrorKind::InvalidEscape, start);
+        let high = self.hex4().ok_or(invalid)?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high.into()).ok_or(invalid);
+        }
+        if !self.eat_str("\\u") {
+            return Err(invalid);
+        }
+        let low = self.hex4().ok_or(invalid)?;
+        char::decode_utf16([high, low])
+            .next()
+            .and_then(Result::ok)
+            .ok_or(invalid)
+    }
+
+    fn hex4(&mut self) -> Option<u16> {
+        let digits = self.input.get(self.pos..self.pos + 4)?;
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let n = u16::from_str_radix(digits, 16).ok()?;
+        self.pos += 4;
+        Some(n)
+    }
+
+    fn or_expr(&mut self) -> Result<Expr, PathError> {
+        let mut exprs = vec![self.and_expr()?];
+        loop {
+            let before = self.pos;
+            self.skip_whitespace();
+            if !self.eat_str("||") {
+                self.pos = before;
+                break;
+            }
+            self.skip_whitespace();
+            exprs.push(self.and_expr()?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.pop().unwrap(),
+            _ => Expr::Or(exprs),
+        })
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, PathError> {
+        let mut exprs = vec![self.basic_expr()?];
+        loop {
+            let before = self.pos;
+            self.skip_whitespace();
+            if !self.eat_str("&&") {
+                self.pos = before;
+                break;
+            }
+            self.skip_whitespace();
+            exprs.push(self.basic_expr()?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.pop().unwrap(),
+            _ => Expr::And(exprs),
+        })
+    }
+
+    fn basic_expr(&mut self) -> Result<Expr, PathError> {
+        // `!=` is a comparison, which cannot start an expression.
+        if self.peek() == Some('!') {
+            self.pos += 1;
+            self.skip_whitespace();
+            let expr = match self.peek() {
+                Some('(') => self.paren_expr()?,
+                _ => {
+                    let start = self.pos;
+                    let operand = self.operand()?;
+                    self.test_expr(operand, start)?
+                }
+            };
+            return Ok(Expr::Not(Box::new(expr)));
+        }
+        if self.peek() == Some('(') {
+            return self.paren_expr();
+        }
+
+        let start = self.pos;
+        let lhs = self.operand()?;
+        let before = self.pos;
+        self.skip_whitespace();
+        let Some(op) = self.compare_op() else {
+            self.pos = before;
+            return self.test_expr(lhs, start);
+        };
+        self.skip_whitespace();
+        let rhs_start = self.pos;
+        let rhs = self.operand()?;
+        self.check_comparable(&lhs, start)?;
+        self.check_comparable(&rhs, rhs_start)?;
+        Ok(Expr::Compare(lhs, op, rhs))
+    }
+
+    fn paren_expr(&mut self) -> Result<Expr, PathError> {
+        self.expect('(')?;
+        self.skip_whitespace();
+        let expr = self.nested(Self::or_expr)?;
+        self.skip_whitespace();
+        self.expect(')')?;
+        Ok(expr)
+    }
+
+    fn compare_op(&mut self) -> Option<CompareOp> {
+        let ops = [
+            ("==", CompareOp::Eq),
+            ("!=", CompareOp::Ne),
+            ("<=", CompareOp::Le),
+            (">=", CompareOp::Ge),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+        ];
+        ops.into_iter()
+            .find(|(text, _)| self.eat_str(text))
+            .map(|(_, op)| op)
+    }
+
+    /// Turns an operand that is not compared into a test: a query, or a
+    /// function returning a logical value.
+    fn test_expr(&mut self, operand: Operand, start: usize) -> Result<Expr, PathError> {
+        match operand {
+            Operand::Query(query) => Ok(Expr::Exists(query)),
+            Operand::Function(call) if call.function.result() == Type::Logical => {
+                Ok(Expr::Test(call))
+            }
+            Operand::Function(_) => Err(self.error(PathErrorKind::InvalidFunctionCall, start)),
+            // A literal on its own is missing its comparison.
+            Operand::Literal(_) => {
+                self.skip_whitespace();
+                Err(self.unexpected())
+            }
+        }
+    }
+
+    fn check_comparable(&self, operand: &Operand, start: usize) -> Result<(), PathError> {
+        match operand {
+            Operand::Query(query) if !query.is_singular() => {
+                Err(self.error(PathErrorKind::NonSingularQuery, start))
+            }
+            Operand::Function(call) if call.function.result() != Type::Value => {
+                Err(self.error(PathErrorKind::InvalidFunctionCall, start))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, PathError> {
+        match self.peek() {
+            Some('$' | '@') => Ok(Operand::Query(self.query()?)),
+            Some('\'' | '"') => Ok(Operand::Literal(JsonValue::String(self.string()?))),
+            Some('-' | '0'..='9') => self.number().map(Operand::Literal),
+            Some('a'..='z') => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+                {
+                    self.pos += 1;
+                }
+                let name = &self.input[start..self.pos];
+                if self.peek() == Some('(') {
+                    return self.function(name, start).map(Operand::Function);
+                }
+                match name {
+                    "true" => Ok(Operand::Literal(JsonValue::Bool(true))),
+                    "false" => Ok(Operand::Literal(JsonValue::Bool(false))),
+                    "null" => Ok(Operand::Literal(JsonValue::Null)),
+                    _ => Err(self.unexpected()),
+                }
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn number(&mut self) -> Result<JsonValue, PathError> {
+        let start = self.pos;
+        self.eat('-');
+        let mut seen_exponent = false;
+        while let Some(c) = self.peek() {
+            match c {
+                '0'..='9' | '.' => {}
+                'e' | 'E' => seen_exponent = true,
+                '+' | '-' if seen_exponent => {}
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        match self.input[start..self.pos].parse::<JsonNumber>() {
+            Ok(n) => Ok(JsonValue::Number(n)),
+            Err(_) => Err(self.error(PathErrorKind::InvalidNumber, start)),
+        }
+    }
+
+    /// Parses the arguments of a function call and checks them against its
+    /// parameters.
+    fn function(&mut self, name: &str, start: usize) -> Result<FunctionCall, PathError> {
+        let function = Function::from_name(name)
+            .ok_or_else(|| self.error(PathErrorKind::UnknownFunction, start))?;
+        self.expect('(')?;
+        self.skip_whitespace();
+        let mut args = Vec::new();
+        if !self.eat(')') {
+            loop {
+                let arg_start = self.pos;
+                let arg = self.nested(Self::operand)?;
+                let fits = match function.params().get(args.len()) {
+                    Some(Type::Nodes) => matches!(arg, Operand::Query(_)),
+                    Some(_) => match &arg {
+                        Operand::Literal(_) => true,
+                        Operand::Query(query) => query.is_singular(),
+                        Operand::Function(call) => call.function.result() == Type::Value,
+                    },
+                    None => false,
+                };
+                if !fits {
+                    return Err(self.error(PathErrorKind::InvalidFunctionCall, arg_start));
+                }
+                args.push(arg);
+                self.skip_whitespace();
+                if self.eat(')') {
+                    break;
+                }
+                self.expect(',')?;
+                self.skip_whitespace();
+            }
+        }
+        if args.len() != function.params().len() {
+            return Err(self.error(PathErrorKind::InvalidFunctionCall, start));
+        }
+        let regex = match args.get(1) {
+            Some(Operand::Literal(JsonValue::String(pattern))) => Regex::new(pattern),
+            _ => None,
+        };
+        Ok(FunctionCall {
+            function,
+            args,
+            regex,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    fn courses() -> JsonValue {
+        json!({
+            "name": "John",
+            "courses": [
+                {"title": "Math", "credits": 5, "tags": ["core"]},
+                {"title": "History", "credits": 3},
+                {"title": "Music", "credits": 2, "tags": []},
+            ],
+        })
+    }
+
+    fn select(query: &str, value: &JsonValue) -> Vec<JsonValue> {
+        JsonPath::parse(query)
+            .unwrap()
+            .select_values(value)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    fn paths(query: &str, value: &JsonValue) -> Vec<String> {
+        JsonPath::parse(query)
+            .unwrap()
+            .select(value)
+            .iter()
+            .map(|node| node.path.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_child_and_descendant_segments() {
+        let doc = courses();
+
+        assert_eq!(select("$.name", &doc), [json!("John")]);
+        assert_eq!(select("$['name']", &doc), [json!("John")]);
+        assert_eq!(select("$.courses[1].title", &doc), [json!("History")]);
+        assert_eq!(select("$.courses[-1].credits", &doc), [json!(2)]);
+        assert_eq!(select("$.courses[3]", &doc), Vec::<JsonValue>::new());
+        assert_eq!(select("$..credits", &doc), [json!(5), json!(3), json!(2)]);
+        assert_eq!(select("$..tags[*]", &doc), [json!("core")]);
+        assert_eq!(select("$.courses[0].*", &doc).len(), 3);
+        assert_eq!(JsonPath::parse("$").unwrap().select_values(&doc), [&doc]);
+        assert_eq!(
+            paths("$..title", &doc),
+            [
+                "$['courses'][0]['title']",
+                "$['courses'][1]['title']",
+                "$['courses'][2]['title']"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_slices_and_unions() {
+        let doc = json!([0, 1, 2, 3, 4, 5]);
+
+        assert_eq!(select("$[1:3]", &doc), [json!(1), json!(2)]);
+        assert_eq!(select("$[:2]", &doc), [json!(0), json!(1)]);
+        assert_eq!(select("$[-2:]", &doc), [json!(4), json!(5)]);
+        assert_eq!(select("$[::2]", &doc), [json!(0), json!(2), json!(4)]);
+        assert_eq!(select("$[::-2]", &doc), [json!(5), json!(3), json!(1)]);
+        assert_eq!(select("$[4:1:-1]", &doc), [json!(4), json!(3), json!(2)]);
+        assert_eq!(select("$[1:3:0]", &doc), Vec::<JsonValue>::new());
+        assert_eq!(select("$[10:]", &doc), Vec::<JsonValue>::new());
+        assert_eq!(
+            select("$[0, 0, -1, 1:2]", &doc),
+            [json!(0), json!(0), json!(5), json!(1)]
+        );
+        assert_eq!(paths("$[ 5 , 'x' ]", &doc), ["$[5]"]);
+    }
+
+    #[test]
+    fn test_filters() {
+        let doc = courses();
+
+        assert_eq!(
+            select("$.courses[?match(@.title, 'M.*')].credits", &doc),
+            [json!(5), json!(2)]
+        );
+        assert_eq!(
+            select("$.courses[?@.credits > 2].title", &doc),
+            [json!("Math"), json!("History")]
+        );
+        assert_eq!(
+            select("$.courses[?@.credits <= 2].title", &doc),
+            [json!("Music")]
+        );
+        assert_eq!(
+            select("$.courses[?@.tags].title", &doc),
+            [json!("Math"), json!("Music")]
+        );
+        assert_eq!(
+            select("$.courses[?!@.tags].title", &doc),
+            [json!("History")]
+        );
+        assert_eq!(
+            select(
+                "$.courses[?@.title == 'Math' || (@.credits < 3 && @.tags)].title",
+                &doc
+            ),
+            [json!("Math"), json!("Music")]
+        );
+        assert_eq!(
+            select("$.courses[?@.missing == null].title", &doc),
+            Vec::<JsonValue>::new()
+        );
+        assert_eq!(
+            select("$.courses[?@.missing == @.other].credits", &doc),
+            [json!(5), json!(3), json!(2)]
+        );
+        assert_eq!(
+            select("$.courses[?@.title < 'I'].title", &doc),
+            [json!("History")]
+        );
+        assert_eq!(
+            select("$.courses[?length(@.tags) == 0].title", &doc),
+            [json!("Music")]
+        );
+        assert_eq!(
+            select("$.courses[?@.credits == $.courses[1].credits].title", &doc),
+            [json!("History")]
+        );
+        assert_eq!(select("$[?@ == 'John']", &doc), [json!("John")]);
+    }
+
+    #[test]
+    fn test_functions() {
+        let doc = courses();
+
+        assert_eq!(
+            select("$.courses[?length(@.title) == 5].title", &doc),
+            [json!("Music")]
+        );
+        assert_eq!(
+            select("$.courses[?count(@.*) == 2].title", &doc),
+            [json!("History")]
+        );
+        assert_eq!(
+            select("$.courses[?match(@.title, 'M.*')].title", &doc),
+            [json!("Math"), json!("Music")]
+        );
+        assert_eq!(
+            select("$.courses[?match(@.title, 'M')].title", &doc),
+            Vec::<JsonValue>::new()
+        );
+        assert_eq!(
+            select("$.courses[?search(@.title, 's')].title", &doc),
+            [json!("History"), json!("Music")]
+        );
+        assert_eq!(
+            select("$.courses[?value(@..tags[0]) == 'core'].title", &doc),
+            [json!("Math")]
+        );
+        assert_eq!(
+            select("$.courses[?match(@.title, '(')].title", &doc),
+            Vec::<JsonValue>::new()
+        );
+        assert_eq!(
+            select("$.courses[?length(@.credits) == 1]", &doc),
+            Vec::<JsonValue>::new()
+        );
+    }
+
+    #[test]
+    fn test_regex_functions_on_hostile_input() {
+        let long = "a".repeat(100_000);
+        let doc = json!([long, "a".repeat(28) + "c"]);
+
+        assert_eq!(select("$[?match(@, 'a.*')]", &doc).len(), 2);
+        assert_eq!(select("$[?search(@, 'c')]", &doc).len(), 1);
+        assert!(select("$[?match(@, '(a|a)*b')]", &doc).is_empty());
+        assert!(select("$[?search(@, '(a|a)*b')]", &doc).is_empty());
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let parens = |n: usize| format!("$[?{}@{}]", "(".repeat(n), ")".repeat(n));
+        let filters = |n: usize| format!("${}{}", "[?@".repeat(n), "]".repeat(n));
+        let calls = |n: usize| format!("$[?{}@{} == 1]", "length(".repeat(n), ")".repeat(n));
+        let doc = json!([[1]]);
+
+        assert_eq!(select(&parens(100), &doc).len(), 1);
+        assert_eq!(select(&filters(100), &doc).len(), 0);
+        assert!(JsonPath::parse(&calls(100)).is_ok());
+
+        let err = JsonPath::parse(&parens(20_000)).unwrap_err();
+        assert_eq!(err, PathError::new(PathErrorKind::NestingTooDeep, 131));
+        for query in [filters(5_000), calls(5_000)] {
+            let err = JsonPath::parse(&query).unwrap_err();
+            assert_eq!(err.kind, PathErrorKind::NestingTooDeep);
+        }
+    }
+
+    #[test]
+    fn test_normalized_paths() {
+        let doc = json!({"a'b": {"c\\d": [0, {"\n": 1}]}});
+        let nodes = JsonPath::parse("$..[1].*").unwrap().select(&doc);
+
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].value, &json!(1));
+        assert_eq!(nodes[0].path.to_string(), "$['a\\'b']['c\\\\d'][1]['\\n']");
+        assert_eq!(nodes[0].path.to_pointer().to_string(), "/a'b/c\\d/1/\n");
+        assert_eq!(doc.pointer("/a'b/c\\d/1/\n"), Some(&json!(1)));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |query: &str| JsonPath::parse(query).unwrap_err();
+        let kind_and_offset = |query: &str| {
+            let err = error(query);
+            (err.kind, err.offset)
+        };
+
+        assert_eq!(
+            kind_and_offset("a"),
+            (PathErrorKind::UnexpectedCharacter, 0)
+        );
+        assert_eq!(
+            kind_and_offset(" $"),
+            (PathErrorKind::UnexpectedCharacter, 0)
+        );
+        assert_eq!(
+            kind_and_offset("$ "),
+            (PathErrorKind::UnexpectedCharacter, 1)
+        );
+        assert_eq!(kind_and_offset("$["), (PathErrorKind::UnexpectedEof, 2));
+        assert_eq!(kind_and_offset("$[01]"), (PathErrorKind::InvalidNumber, 2));
+        assert_eq!(
+            kind_and_offset("$[9007199254740992]"),
+            (PathErrorKind::InvalidNumber, 2)
+        );
+        assert_eq!(
+            kind_and_offset("$['\\x']"),
+            (PathErrorKind::InvalidEscape, 3)
+        );
+        assert_eq!(
+            kind_and_offset("$[?foo(@)]"),
+            (PathErrorKind::UnknownFunction, 3)
+        );
+        assert_eq!(
+            kind_and_offset("$[?length(@)]"),
+            (PathErrorKind::InvalidFunctionCall, 3)
+        );
+        assert_eq!(
+            kind_and_offset("$[?count(1) == 1]"),
+            (PathErrorKind::InvalidFunctionCall, 9)
+        );
+        assert_eq!(
+            kind_and_offset("$[?length(@.*) == 1]"),
+            (PathErrorKind::InvalidFunctionCall, 10)
+        );
+        assert_eq!(
+            kind_and_offset("$[?match(@.a) == 1]"),
+            (PathErrorKind::InvalidFunctionCall, 3)
+        );
+        assert_eq!(
+            kind_and_offset("$[?@.* == 1]"),
+            (PathErrorKind::NonSingularQuery, 3)
+        );
+        assert_eq!(
+            kind_and_offset("$[?match(@.a, 'a') == true]"),
+            (PathErrorKind::InvalidFunctionCall, 3)
+        );
+        assert_eq!(
+            kind_and_offset("$[?1]"),
+            (PathErrorKind::UnexpectedCharacter, 4)
+        );
+        assert_eq!(
+            kind_and_offset("$. a"),
+            (PathErrorKind::UnexpectedCharacter, 2)
+        );
+        assert!(JsonPath::parse("$ .a [0]").is_ok());
+        assert_eq!(
+            error("$[").to_string(),
+            "unexpected end of query at offset 2"
+        );
+    }
+}
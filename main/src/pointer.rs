@@ -0,0 +1,371 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::PointerError;
+use crate::json_value::JsonValue;
+
+/// A JSON Pointer (RFC 6901), which identifies a value inside a document by
+/// the object keys and array indices leading to it, like `/courses/1/title`.
+///
+/// The empty pointer refers to the whole document. In the text form `~` and
+/// `/` inside a token are written as `~0` and `~1`; [`tokens`](Self::tokens)
+/// returns them unescaped.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct JsonPointer {
+    tokens: Vec<String>,
+}
+
+impl JsonPointer {
+    /// The pointer to the whole document.
+    pub fn root() -> JsonPointer {
+        Self::default()
+    }
+
+    pub fn parse(s: &str) -> Result<JsonPointer, PointerError> {
+        if s.is_empty() {
+            return Ok(Self::root());
+        }
+        let rest = s.strip_prefix('/').ok_or(PointerError::MissingSlash)?;
+        let tokens = rest.split('/').map(unescape).collect::<Result<_, _>>()?;
+        Ok(JsonPointer { tokens })
+    }
+
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Appends a token, making the pointer refer to a child of the value it
+    /// referred to.
+    pub fn push(&mut self, token: impl Into<String>) {
+        self.tokens.push(token.into());
+    }
+
+    pub fn push_index(&mut self, index: usize) {
+        self.tokens.push(index.to_string());
+    }
+
+    pub fn pop(&mut self) -> Option<String> {
+        self.tokens.pop()
+    }
+
+    /// Returns the pointer to the containing value, or `None` for the root.
+    pub fn parent(&self) -> Option<JsonPointer> {
+        let (_, parent) = self.tokens.split_last()?;
+        Some(JsonPointer {
+            tokens: parent.to_vec(),
+        })
+    }
+
+    pub fn get<'v>(&self, value: &'v JsonValue) -> Option<&'v JsonValue> {
+        self.tokens
+            .iter()
+            .try_fold(value, |value, token| match value {
+                JsonValue::Object(obj) => obj.get(token),
+                JsonValue::Array(arr) => arr.get(parse_index(token).ok()?),
+                _ => None,
+            })
+    }
+
+    pub fn get_mut<'v>(&self, value: &'v mut JsonValue) -> Option<&'v mut JsonValue> {
+        self.tokens
+            .iter()
+            .try_fold(value, |value, token| match value {
+                JsonValue::Object(obj) => obj.get_mut(token),
+                JsonValue::Array(arr) => arr.get_mut(parse_index(token).ok()?),
+                _ => None,
+            })
+    }
+
+    /// Returns the containing value and the last token, for operations that
+    /// act on a member or element.
+    fn parent_mut<'v, 'p>(
+        &'p self,
+        value: &'v mut JsonValue,
+    ) -> Result<(&'v mut JsonValue, &'p str), PointerError> {
+        let (last, parent) = self.tokens.split_last().ok_or(PointerError::Root)?;
+        let parent = JsonPointer {
+            tokens: parent.to_vec(),
+        };
+        let container = parent.get_mut(value).ok_or(PointerError::NotFound)?;
+        Ok((container, last))
+    }
+
+    /// Adds `new` at the location the pointer refers to, with the semantics
+    /// of the JSON Patch `add` operation: an object member is created or
+    /// replaced, and in an array the element is inserted before the one at
+    /// the index, or appended for the index `-` or the array length. The
+    /// root pointer replaces the whole document.
+    ///
+    /// Returns the value that was replaced, if any.
+    pub fn insert(
+        &self,
+        value: &mut JsonValue,
+        new: JsonValue,
+    ) -> Result<Option<JsonValue>, PointerError> {
+        if self.is_root() {
+            return Ok(Some(value.replace(new)));
+        }
+        let (container, last) = self.parent_mut(value)?;
+        match container {
+            JsonValue::Object(obj) => Ok(obj.insert(last.to_string(), new)),
+            JsonValue::Array(arr) => {
+                let index = match last {
+                    "-" => arr.len(),
+                    _ => parse_index(last)?,
+                };
+                if index > arr.len() {
+                    return Err(PointerError::IndexOutOfBounds);
+                }
+                arr.insert(index, new);
+                Ok(None)
+            }
+            _ => Err(PointerError::NotAContainer),
+        }
+    }
+
+    /// Removes the value the pointer refers to from its object or array and
+    /// returns it. Elements after a removed array element move up.
+    pub fn remove(&self, value: &mut JsonValue) -> Result<JsonValue, PointerError> {
+        let (container, last) = self.parent_mut(value)?;
+        match container {
+            JsonValue::Object(obj) => obj.remove(last).ok_or(PointerError::NotFound),
+            JsonValue::Array(arr) => {
+                let index = parse_index(last)?;
+                if index >= arr.len() {
+                    return Err(PointerError::IndexOutOfBounds);
+                }
+                Ok(arr.remove(index))
+            }
+            _ => Err(PointerError::NotAContainer),
+        }
+    }
+}
+
+/// Parses an array index, which RFC 6901 writes without leading zeros.
+fn parse_index(token: &str) -> Result<usize, PointerError> {
+    let valid = !token.is_empty()
+        && token.bytes().all(|b| b.is_ascii_digit())
+        && (token == "0" || !token.starts_with('0'));
+    if !valid {
+        return Err(PointerError::InvalidIndex);
+    }
+    token.parse().map_err(|_| PointerError::IndexOutOfBounds)
+}
+
+fn unescape(token: &str) -> Result<String, PointerError> {
+    if !token.contains('~') {
+        return Ok(token.to_string());
+    }
+    let mut out = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '~' => match chars.next() {
+                Some('0') => out.push('~'),
+                Some('1') => out.push('/'),
+                _ => return Err(PointerError::InvalidEscape),
+            },
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+impl FromStr for JsonPointer {
+    type Err = PointerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for JsonPointer {
+    /// Writes the pointer in its text form, escaping `~` and `/`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.tokens {
+            f.write_str("/")?;
+            for c in token.chars() {
+                match c {
+                    '~' => f.write_str("~0")?,
+                    '/' => f.write_str("~1")?,
+                    c => fmt::Write::write_char(f, c)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for JsonPointer {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        JsonPointer {
+            tokens: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl JsonValue {
+    /// Looks up a value by JSON Pointer, like `/courses/1/title`. Returns
+    /// `None` if the pointer is malformed or nothing is found.
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue> {
+        JsonPointer::parse(pointer).ok()?.get(self)
+    }
+
+    /// Mutable version of [`pointer`](Self::pointer).
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut JsonValue> {
+        JsonPointer::parse(pointer).ok()?.get_mut(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    /// The example document from RFC 6901, section 5.
+    fn rfc_document() -> JsonValue {
+        json!({
+            "foo": ["bar", "baz"],
+            "": 0,
+            "a/b": 1,
+            "c%d": 2,
+            "e^f": 3,
+            "g|h": 4,
+            "i\\j": 5,
+            "k\"l": 6,
+            " ": 7,
+            "m~n": 8,
+        })
+    }
+
+    #[test]
+    fn test_rfc_examples() {
+        let doc = rfc_document();
+        let cases = [
+            ("", doc.clone()),
+            ("/foo", json!(["bar", "baz"])),
+            ("/foo/0", json!("bar")),
+            ("/", json!(0)),
+            ("/a~1b", json!(1)),
+            ("/c%d", json!(2)),
+            ("/e^f", json!(3)),
+            ("/g|h", json!(4)),
+            ("/i\\j", json!(5)),
+            ("/k\"l", json!(6)),
+            ("/ ", json!(7)),
+            ("/m~0n", json!(8)),
+        ];
+
+        for (pointer, expected) in cases {
+            assert_eq!(doc.pointer(pointer), Some(&expected), "{}", pointer);
+            assert_eq!(JsonPointer::parse(pointer).unwrap().to_string(), pointer);
+        }
+    }
+
+    #[test]
+    fn test_lookup_misses() {
+        let doc = json!({"courses": [{"title": "Math"}, {"title": "History"}]});
+
+        assert_eq!(doc.pointer("/courses/1/title"), Some(&json!("History")));
+        for pointer in [
+            "courses",
+            "/missing",
+            "/courses/2",
+            "/courses/01",
+            "/courses/-",
+            "/courses/x",
+            "/courses/0/title/x",
+            "/~2",
+        ] {
+            assert_eq!(doc.pointer(pointer), None, "{}", pointer);
+        }
+    }
+
+    #[test]
+    fn test_parse_and_build() {
+        assert_eq!(JsonPointer::parse("a"), Err(PointerError::MissingSlash));
+        assert_eq!(JsonPointer::parse("/a~"), Err(PointerError::InvalidEscape));
+        assert_eq!(JsonPointer::parse("/~01").unwrap().tokens(), ["~1"]);
+
+        let mut pointer = JsonPointer::root();
+        assert!(pointer.is_root());
+        assert_eq!(pointer.to_string(), "");
+        pointer.push("a/b");
+        pointer.push_index(3);
+        pointer.push("~");
+        assert_eq!(pointer.to_string(), "/a~1b/3/~0");
+        assert_eq!(pointer.to_string().parse(), Ok(pointer.clone()));
+        assert_eq!(pointer.parent().unwrap().to_string(), "/a~1b/3");
+        assert_eq!(pointer.pop().as_deref(), Some("~"));
+        assert_eq!(
+            ["x", "0"].into_iter().collect::<JsonPointer>().to_string(),
+            "/x/0"
+        );
+    }
+
+    #[test]
+    fn test_pointer_mut() {
+        let mut doc = json!({"info": {"tags": ["a"]}});
+
+        *doc.pointer_mut("/info/tags/0").unwrap() = json!("b");
+        assert_eq!(doc, json!({"info": {"tags": ["b"]}}));
+        assert_eq!(doc.pointer_mut("/info/missing"), None);
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut doc = json!({"list": [1, 3], "obj": {}});
+        let insert = |doc: &mut JsonValue, pointer: &str, value| {
+            JsonPointer::parse(pointer).unwrap().insert(doc, value)
+        };
+
+        assert_eq!(insert(&mut doc, "/list/1", json!(2)), Ok(None));
+        assert_eq!(insert(&mut doc, "/list/-", json!(4)), Ok(None));
+        assert_eq!(insert(&mut doc, "/list/4", json!(5)), Ok(None));
+        assert_eq!(insert(&mut doc, "/obj/a", json!(true)), Ok(None));
+        assert_eq!(
+            insert(&mut doc, "/obj/a", json!(false)),
+            Ok(Some(json!(true)))
+        );
+        assert_eq!(doc, json!({"list": [1, 2, 3, 4, 5], "obj": {"a": false}}));
+
+        assert_eq!(
+            insert(&mut doc, "/list/9", json!(0)),
+            Err(PointerError::IndexOutOfBounds)
+        );
+        assert_eq!(
+            insert(&mut doc, "/list/01", json!(0)),
+            Err(PointerError::InvalidIndex)
+        );
+        assert_eq!(
+            insert(&mut doc, "/missing/a", json!(0)),
+            Err(PointerError::NotFound)
+        );
+        assert_eq!(
+            insert(&mut doc, "/obj/a/b", json!(0)),
+            Err(PointerError::NotAContainer)
+        );
+
+        let remove =
+            |doc: &mut JsonValue, pointer: &str| JsonPointer::parse(pointer).unwrap().remove(doc);
+        assert_eq!(remove(&mut doc, "/list/0"), Ok(json!(1)));
+        assert_eq!(remove(&mut doc, "/obj/a"), Ok(json!(false)));
+        assert_eq!(remove(&mut doc, "/obj/a"), Err(PointerError::NotFound));
+        assert_eq!(
+            remove(&mut doc, "/list/4"),
+            Err(PointerError::IndexOutOfBounds)
+        );
+        assert_eq!(remove(&mut doc, ""), Err(PointerError::Root));
+        assert_eq!(doc, json!({"list": [2, 3, 4, 5], "obj": {}}));
+
+        assert_eq!(
+            insert(&mut doc, "", json!(null)),
+            Ok(Some(json!({"list": [2, 3, 4, 5], "obj": {}})))
+        );
+        assert_eq!(doc, JsonValue::Null);
+    }
+}
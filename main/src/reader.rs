@@ -0,0 +1,148 @@
+use std::io;
+
+const BUFFER_SIZE: usize = 8 * 1024;
+
+pub(crate) enum ReadError {
+    Io(io::Error),
+    InvalidUtf8,
+}
+
+/// Decodes chars from an [`io::Read`] through a fixed-size buffer, validating
+/// UTF-8 as it goes.
+pub(crate) struct Utf8Reader<'a> {
+    reader: Box<dyn io::Read + 'a>,
+    buf: Box<[u8]>,
+    pos: usize,
+    end: usize,
+    eof: bool,
+    /// An error hit while peeking, reported by the next `next_char` call.
+    error: Option<ReadError>,
+}
+
+impl<'a> Utf8Reader<'a> {
+    pub(crate) fn new<R: io::Read + 'a>(reader: R) -> Utf8Reader<'a> {
+        Utf8Reader {
+            reader: Box::new(reader),
+            buf: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            pos: 0,
+            end: 0,
+            eof: false,
+            error: None,
+        }
+    }
+
+    /// Makes at least `n` bytes available in the buffer unless the input
+    /// ends first. Returns the number of available bytes.
+    fn fill(&mut self, n: usize) -> io::Result<usize> {
+        if self.end - self.pos >= n || self.eof {
+            return Ok(self.end - self.pos);
+        }
+        self.buf.copy_within(self.pos..self.end, 0);
+        self.end -= self.pos;
+        self.pos = 0;
+        while self.end < n {
+            match self.reader.read(&mut self.buf[self.end..]) {
+                Ok(0) => {
+                    self.eof = true;
+                    break;
+                }
+                Ok(read) => self.end += read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(self.end)
+    }
+
+    pub(crate) fn next_char(&mut self) -> Result<Option<char>, ReadError> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        if self.fill(1).map_err(ReadError::Io)? == 0 {
+            return Ok(None);
+        }
+        let width = match self.buf[self.pos] {
+            b if b < 0x80 => {
+                self.pos += 1;
+                return Ok(Some(b as char));
+            }
+            0xC2..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF4 => 4,
+            _ => return Err(ReadError::InvalidUtf8),
+        };
+        if self.fill(width).map_err(ReadError::Io)? < width {
+            return Err(ReadError::InvalidUtf8);
+        }
+        let bytes = &self.buf[self.pos..self.pos + width];
+        let c = std::str::from_utf8(bytes)
+            .map_err(|_| ReadError::InvalidUtf8)?
+            .chars()
+            .next();
+        self.pos += width;
+        Ok(c)
+    }
+
+    /// Returns up to `n` upcoming bytes without consuming them.
+    pub(crate) fn peek(&mut self, n: usize) -> &[u8] {
+        if let Err(err) = self.fill(n) {
+            self.error = Some(ReadError::Io(err));
+        }
+        let end = self.end.min(self.pos + n);
+        &self.buf[self.pos..end]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hands out at most one byte per `read` call.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((&b, rest)) if !buf.is_empty() => {
+                    buf[0] = b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Result<String, ()> {
+        let mut reader = Utf8Reader::new(Trickle(bytes));
+        let mut out = String::new();
+        loop {
+            match reader.next_char() {
+                Ok(Some(c)) => out.push(c),
+                Ok(None) => return Ok(out),
+                Err(_) => return Err(()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_utf8_reader_decodes_split_sequences() {
+        let text = "aé中😀".repeat(BUFFER_SIZE / 4);
+        assert_eq!(decode(text.as_bytes()), Ok(text));
+    }
+
+    #[test]
+    fn test_utf8_reader_rejects_invalid_utf8() {
+        for bytes in [
+            &b"\xff"[..],
+            b"\x81",
+            b"\xc0\xaf",
+            b"\xed\xa0\x80",
+            b"\xf4\x90\x80\x80",
+            b"\xe0\xff",
+            b"a\xe4\xb8",
+        ] {
+            assert_eq!(decode(bytes), Err(()), "{:?}", bytes);
+        }
+    }
+}
@@ -0,0 +1,637 @@
+use crate::unicode::general_category;
+
+/// A matcher for I-Regexp (RFC 9485), the interoperable regular expression
+/// subset used by the JSONPath `match` and `search` functions.
+///
+/// Patterns compile to a small NFA program that is simulated over the
+/// input one character at a time, following every alternative at once, so
+/// matching takes time linear in the input and uses no recursion.
+///
+/// The `\p{..}` and `\P{..}` escapes take the general categories from the
+/// Unicode Character Database, version 14.0.
+#[derive(Debug, Clone)]
+pub(crate) struct Regex {
+    program: Vec<Inst>,
+}
+
+/// Patterns compiling to more instructions than this are rejected, which
+/// bounds the work per input character.
+const MAX_PROGRAM_LEN: usize = 10_000;
+
+/// How deeply groups may nest in a pattern.
+const MAX_NESTING: usize = 64;
+
+/// The largest bound allowed in a `{m,n}` quantifier.
+const MAX_REPEAT: u32 = 1000;
+
+/// An instruction of the compiled NFA.
+#[derive(Debug, Clone)]
+enum Inst {
+    /// Consumes one character matching the atom.
+    Atom(Atom),
+    /// Continues at both targets.
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Atom(Atom),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Atom {
+    Char(char),
+    /// `.`, which matches anything but line breaks.
+    Any,
+    Category(Category, bool),
+    Set {
+        negated: bool,
+        items: Vec<SetItem>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum SetItem {
+    Range(char, char),
+    Category(Category, bool),
+}
+
+/// A general category named in a `\p{..}` escape: a major class such as
+/// `L`, or one of its subcategories such as `Lu`.
+#[derive(Debug, Clone, Copy)]
+enum Category {
+    Major(u8),
+    Minor([u8; 2]),
+}
+
+/// The subcategories I-Regexp accepts; surrogates (`Cs`) cannot occur in a
+/// string.
+const SUBCATEGORIES: [&str; 29] = [
+    "Lu", "Ll", "Lt", "Lm", "Lo", "Mn", "Mc", "Me", "Nd", "Nl", "No", "Pc", "Pd", "Ps", "Pe", "Pi",
+    "Pf", "Po", "Zs", "Zl", "Zp", "Sm", "Sc", "Sk", "So", "Cc", "Cf", "Co", "Cn",
+];
+
+impl Category {
+    fn from_name(name: &str) -> Option<Category> {
+        match *name.as_bytes() {
+            [major] if b"LMNPZSC".contains(&major) => Some(Category::Major(major)),
+            [major, minor] if SUBCATEGORIES.contains(&name) => {
+                Some(Category::Minor([major, minor]))
+            }
+            _ => None,
+        }
+    }
+
+    fn contains(self, c: char) -> bool {
+        let category = general_category(c);
+        match self {
+            Category::Major(major) => category[0] == major,
+            Category::Minor(minor) => category == minor,
+        }
+    }
+}
+
+impl Node {
+    fn matches_only_empty(&self) -> bool {
+        match self {
+            Node::Empty => true,
+            Node::Atom(_) => false,
+            Node::Concat(nodes) | Node::Alt(nodes) => nodes.iter().all(Node::matches_only_empty),
+            Node::Repeat { node, max, .. } => *max == Some(0) || node.matches_only_empty(),
+        }
+    }
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Char(expected) => c == *expected,
+            Atom::Any => c != '\n' && c != '\r',
+            Atom::Category(category, negated) => category.contains(c) != *negated,
+            Atom::Set { negated, items } => {
+                let found = items.iter().any(|item| match item {
+                    SetItem::Range(low, high) => (*low..=*high).contains(&c),
+                    SetItem::Category(category, negated) => category.contains(c) != *negated,
+                });
+                found != *negated
+            }
+        }
+    }
+}
+
+impl Regex {
+    /// Compiles a pattern, or returns `None` if it is not valid I-Regexp or
+    /// too large.
+    pub(crate) fn new(pattern: &str) -> Option<Regex> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            depth: 0,
+        };
+        let node = parser.alternation()?;
+        if parser.pos != parser.chars.len() {
+            return None;
+        }
+        let mut compiler = Compiler {
+            program: Vec::new(),
+        };
+        compiler.compile(&node)?;
+        compiler.push(Inst::Match)?;
+        Some(Regex {
+            program: compiler.program,
+        })
+    }
+
+    /// Returns whether the whole of `s` matches.
+    pub(crate) fn is_match(&self, s: &str) -> bool {
+        self.run(s, true)
+    }
+
+    /// Returns whether some substring of `s` matches.
+    pub(crate) fn is_match_within(&self, s: &str) -> bool {
+        self.run(s, false)
+    }
+
+    /// Simulates the program over `s`, in a single pass. Unanchored runs
+    /// start a new thread at every position and succeed as soon as any
+    /// thread matches.
+    fn run(&self, s: &str, anchored: bool) -> bool {
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        let mut stack = Vec::new();
+        current.add(&self.program, 0, &mut stack);
+        for c in s.chars() {
+            if current.matched && !anchored {
+                return true;
+            }
+            if current.pcs.is_empty() && anchored {
+                return false;
+            }
+            for &pc in &current.pcs {
+                if let Inst::Atom(atom) = &self.program[pc] {
+                    if atom.matches(c) {
+                        next.add(&self.program, pc + 1, &mut stack);
+                    }
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+            if !anchored {
+                current.add(&self.program, 0, &mut stack);
+            }
+        }
+        current.matched
+    }
+}
+
+/// The set of program positions reached at one point of the input.
+struct Threads {
+    pcs: Vec<usize>,
+    seen: Vec<bool>,
+    matched: bool,
+}
+
+impl Threads {
+    fn new(len: usize) -> Threads {
+        Threads {
+            pcs: Vec::new(),
+            seen: vec![false; len],
+            matched: false,
+        }
+    }
+
+    fn clear(&mut self) {
+        for pc in self.pcs.drain(..) {
+            self.seen[pc] = false;
+        }
+        self.matched = false;
+    }
+
+    /// Adds `pc` and everything reachable from it without consuming input.
+    /// Positions already in the set are skipped, which also stops loops
+    /// around empty repetitions.
+    fn add(&mut self, program: &[Inst], pc: usize, stack: &mut Vec<usize>) {
+        stack.push(pc);
+        while let Some(pc) = stack.pop() {
+            if self.seen[pc] {
+                continue;
+            }
+            self.seen[pc] = true;
+            self.pcs.push(pc);
+            match program[pc] {
+                Inst::Split(first, second) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+                Inst::Jump(target) => stack.push(target),
+                Inst::Match => self.matched = true,
+                Inst::Atom(_) => {}
+            }
+        }
+    }
+}
+
+struct Compiler {
+    program: Vec<Inst>,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> Option<usize> {
+        if self.program.len() >= MAX_PROGRAM_LEN {
+            return None;
+        }
+        self.program.push(inst);
+        Some(self.program.len() - 1)
+    }
+
+    /// Pushes a `Split` that continues at the next instruction, with its
+    /// other target left to [`patch`](Self::patch).
+    fn split(&mut self) -> Option<usize> {
+        self.push(Inst::Split(self.program.len() + 1, 0))
+    }
+
+    /// Points the `Split` or `Jump` at `pc` to the next instruction, as its
+    /// second target for a `Split`.
+    fn patch(&mut self, pc: usize) {
+        let target = self.program.len();
+        match &mut self.program[pc] {
+            Inst::Split(_, second) => *second = target,
+            Inst::Jump(to) => *to = target,
+            _ => unreachable!(),
+        }
+    }
+
+    fn compile(&mut self, node: &Node) -> Option<()> {
+        match node {
+            Node::Empty => {}
+            Node::Atom(atom) => {
+                self.push(Inst::Atom(atom.clone()))?;
+            }
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.compile(node)?;
+                }
+            }
+            Node::Alt(branches) => {
+                let mut jumps = Vec::new();
+                for (i, branch) in branches.iter().enumerate() {
+                    if i + 1 == branches.len() {
+                        self.compile(branch)?;
+                        break;
+                    }
+                    let split = self.split()?;
+                    self.compile(branch)?;
+                    jumps.push(self.push(Inst::Jump(0))?);
+                    self.patch(split);
+                }
+                for jump in jumps {
+                    self.patch(jump);
+                }
+            }
+            // Repeating something that can only match the empty string
+            // adds nothing, and would otherwise loop without emitting any
+            // instruction to count against the limit.
+            Node::Repeat { node, .. } if node.matches_only_empty() => {}
+            Node::Repeat { node, min, max } => {
+                for _ in 0..*min {
+                    self.compile(node)?;
+                }
+                match max {
+                    None => {
+                        let split = self.split()?;
+                        self.compile(node)?;
+                        self.push(Inst::Jump(split))?;
+                        self.patch(split);
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.split()?);
+                            self.compile(node)?;
+                        }
+                        for split in splits {
+                            self.patch(split);
+                        }
+                    }
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /// How many groups enclose the current position.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn alternation(&mut self) -> Option<Node> {
+        let mut branches = vec![self.branch()?];
+        while self.eat('|') {
+            branches.push(self.branch()?);
+        }
+        Some(match branches.len() {
+            1 => branches.pop().unwrap(),
+            _ => Node::Alt(branches),
+        })
+    }
+
+    fn branch(&mut self) -> Option<Node> {
+        let mut pieces = Vec::new();
+        while !matches!(self.peek(), None | Some('|' | ')')) {
+            let atom = self.atom()?;
+            pieces.push(self.quantified(atom)?);
+        }
+        Some(match pieces.len() {
+            0 => Node::Empty,
+            1 => pieces.pop().unwrap(),
+            _ => Node::Concat(pieces),
+        })
+    }
+
+    fn atom(&mut self) -> Option<Node> {
+        let c = self.peek()?;
+        self.pos += 1;
+        let atom = match c {
+            '(' => {
+                self.depth += 1;
+                if self.depth > MAX_NESTING {
+                    return None;
+                }
+                let node = self.alternation()?;
+                self.depth -= 1;
+                return self.eat(')').then_some(node);
+            }
+            '.' => Atom::Any,
+            '[' => self.set()?,
+            '\\' => match self.escape()? {
+                SetItem::Category(category, negated) => Atom::Category(category, negated),
+                SetItem::Range(c, _) => Atom::Char(c),
+            },
+            ')' | '*' | '+' | '?' | ']' | '{' | '|' | '}' => return None,
+            c => Atom::Char(c),
+        };
+        Some(Node::Atom(atom))
+    }
+
+    fn quantified(&mut self, node: Node) -> Option<Node> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.pos += 1;
+                let min = self.number()?;
+                let max = match self.eat(',') {
+                    true if self.peek() == Some('}') => None,
+                    true => Some(self.number()?),
+                    false => Some(min),
+                };
+                if self.peek() != Some('}')
+                    || max.is_some_and(|max| max < min)
+                    || max.unwrap_or(min) > MAX_REPEAT
+                {
+                    return None;
+                }
+                (min, max)
+            }
+            _ => return Some(node),
+        };
+        self.pos += 1;
+        Some(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        })
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits.parse().ok()
+    }
+
+    /// Parses a character class after its `[`.
+    fn set(&mut self) -> Option<Atom> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        loop {
+            let item = match self.peek()? {
+                ']' if !items.is_empty() => break,
+                '[' | ']' => return None,
+                '\\' => {
+                    self.pos += 1;
+                    self.escape()?
+                }
+                c => {
+                    self.pos += 1;
+                    SetItem::Range(c, c)
+                }
+            };
+            let low = match item {
+                SetItem::Range(low, _) => low,
+                category => {
+                    items.push(category);
+                    continue;
+                }
+            };
+            // A `-` before the closing bracket is a literal.
+            let high = match (self.peek(), self.chars.get(self.pos + 1)) {
+                (Some('-'), Some(']')) | (Some('-'), None) => low,
+                (Some('-'), Some(_)) => {
+                    self.pos += 1;
+                    match self.peek()? {
+                        '\\' => {
+                            self.pos += 1;
+                            match self.escape()? {
+                                SetItem::Range(high, _) => high,
+                                SetItem::Category(..) => return None,
+                            }
+                        }
+                        '[' | ']' => return None,
+                        high => {
+                            self.pos += 1;
+                            high
+                        }
+                    }
+                }
+                _ => low,
+            };
+            if high < low {
+                return None;
+            }
+            items.push(SetItem::Range(low, high));
+        }
+        self.pos += 1;
+        Some(Atom::Set { negated, items })
+    }
+
+    /// Parses an escape after its `\`, returning a single character as a
+    /// one-character range.
+    fn escape(&mut self) -> Option<SetItem> {
+        let c = self.peek()?;
+        self.pos += 1;
+        let c = match c {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '(' | ')' | '*' | '+' | '-' | '.' | '?' | '[' | '\\' | ']' | '^' | '{' | '|' | '}' => c,
+            'p' | 'P' => {
+                if !self.eat('{') {
+                    return None;
+                }
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                let category = Category::from_name(&name)?;
+                return self
+                    .eat('}')
+                    .then_some(SetItem::Category(category, c == 'P'));
+            }
+            _ => return None,
+        };
+        Some(SetItem::Range(c, c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(pattern: &str, s: &str) -> bool {
+        Regex::new(pattern).unwrap().is_match(s)
+    }
+
+    #[test]
+    fn test_full_match() {
+        assert!(is_match("M.*", "Math"));
+        assert!(!is_match("M.*", "a Math"));
+        assert!(is_match("a|bc", "bc"));
+        assert!(is_match("(ab)+c?", "abab"));
+        assert!(is_match("[a-c-]{2,3}", "a-c"));
+        assert!(!is_match("[a-c]{2,3}", "abca"));
+        assert!(is_match("[^0-9]*", "abc"));
+        assert!(is_match("\\p{Lu}\\p{Ll}+", "\u{c9}t\u{e9}"));
+        assert!(is_match("\\P{N}", "x"));
+        assert!(is_match("a\\.b\\\\", "a.b\\"));
+        assert!(!is_match(".", "\n"));
+        assert!(is_match("(a*)*b", "aaab"));
+        assert!(is_match("", ""));
+    }
+
+    #[test]
+    fn test_categories() {
+        assert!(is_match("\\p{P}+", "\u{bf}\u{ab}\u{2014}\u{3001}"));
+        assert!(is_match("\\p{Pd}\\p{Pi}\\p{Pf}", "\u{2014}\u{ab}\u{bb}"));
+        assert!(is_match("\\p{Nd}+", "\u{663}\u{967}7"));
+        assert!(!is_match("\\p{Nd}", "\u{216b}"));
+        assert!(!is_match("\\p{Nd}", "\u{bd}"));
+        assert!(is_match("\\p{Nl}\\p{No}", "\u{216b}\u{bd}"));
+        assert!(is_match("\\p{N}{3}", "\u{663}\u{216b}\u{bd}"));
+        assert!(is_match(
+            "\\p{Lu}\\p{Ll}\\p{Lt}\\p{Lo}",
+            "\u{c9}\u{e9}\u{1c5}\u{4e2d}"
+        ));
+        assert!(is_match("e\\p{Mn}", "e\u{301}"));
+        assert!(is_match("\\p{Sc}\\p{Sm}\\p{So}", "\u{20ac}\u{2211}\u{a9}"));
+        assert!(is_match("\\p{Zs}\\p{Zl}", "\u{3000}\u{2028}"));
+        assert!(is_match("\\p{Co}\\p{Cn}", "\u{e000}\u{378}"));
+        assert!(is_match("\\P{L}+", "1\u{bd}"));
+        assert!(is_match("[\\p{Nd}\\p{P}]+", "\u{663}\u{bf}"));
+        assert!(!is_match("[^\\p{L}]", "\u{e9}"));
+    }
+
+    #[test]
+    fn test_match_within() {
+        let regex = Regex::new("b+").unwrap();
+        assert!(regex.is_match_within("abbc"));
+        assert!(!regex.is_match_within("ac"));
+        assert!(Regex::new("").unwrap().is_match_within(""));
+    }
+
+    #[test]
+    fn test_long_subject() {
+        let subject = "a".repeat(100_000);
+        assert!(is_match("a.*", &subject));
+        assert!(is_match("(a|b)*", &subject));
+        assert!(!is_match("a*b", &subject));
+        assert!(Regex::new("b")
+            .unwrap()
+            .is_match_within(&(subject.clone() + "b")));
+        assert!(!Regex::new("ab").unwrap().is_match_within(&subject));
+    }
+
+    #[test]
+    fn test_repeated_empty_groups() {
+        assert!(is_match("((){1000}){1000}", ""));
+        assert!(is_match("(a{0}){1000}b", "b"));
+        assert!(is_match("(()|a*){5}b*", "aab"));
+        assert!(!is_match("(a{0})*", "a"));
+    }
+
+    #[test]
+    fn test_nested_alternation_is_linear() {
+        let subject = "a".repeat(28) + "c";
+        assert!(!is_match("(a|a)*b", &subject));
+        assert!(!is_match("((a|aa)*|(a*)*)*b", &subject));
+        assert!(!Regex::new("(a|a)*b").unwrap().is_match_within(&subject));
+        assert!(is_match("(a|a)*c", &subject));
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        for pattern in [
+            "(a",
+            "a)",
+            "*",
+            "a{2,1}",
+            "[a",
+            "[z-a]",
+            "\\d",
+            "\\p{Xx}",
+            "\\p{Cs}",
+            "\\p{IsBasicLatin}",
+            "a{1",
+        ] {
+            assert!(Regex::new(pattern).is_none(), "{}", pattern);
+        }
+        // Too large or too deeply nested to compile.
+        assert!(Regex::new("(a{1000}){1000}").is_none());
+        assert!(Regex::new("a{1001}").is_none());
+        assert!(Regex::new("((){65535}){65535}").is_none());
+        assert!(Regex::new("(a{0}){4000000000}").is_none());
+        assert!(Regex::new("((){4294967295}){4294967295}").is_none());
+        assert!(Regex::new(&("(".repeat(1000) + &")".repeat(1000))).is_none());
+        assert!(Regex::new(&("(".repeat(64) + &")".repeat(64))).is_some());
+    }
+}
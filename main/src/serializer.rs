@@ -0,0 +1,371 @@
+use std::fmt::{self, Write};
+
+use crate::json_value::JsonValue;
+use crate::number::JsonNumber;
+
+/// The longest line, in bytes, that
+/// [`with_compact_scalar_arrays`](PrettyPrinter::with_compact_scalar_arrays)
+/// puts an array on; longer arrays get one element per line. This also
+/// bounds what [`JsonWriter`](crate::JsonWriter) holds back per array.
+pub(crate) const MAX_COMPACT_ARRAY_WIDTH: usize = 1024;
+
+/// Indentation used for each nesting level by [`PrettyPrinter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs(usize),
+}
+
+/// Line terminator used by [`PrettyPrinter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Newline {
+    Lf,
+    CrLf,
+}
+
+impl Newline {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Newline::Lf => "\n",
+            Newline::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Serializes a [`JsonValue`] as indented, human-readable JSON.
+///
+/// Compact output is available through the `Display` impl of `JsonValue`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrettyPrinter {
+    indent: Indent,
+    newline: Newline,
+    sort_keys: bool,
+    pub(crate) compact_scalar_arrays: bool,
+}
+
+impl Default for PrettyPrinter {
+    fn default() -> Self {
+        PrettyPrinter {
+            indent: Indent::Spaces(2),
+            newline: Newline::Lf,
+            sort_keys: false,
+            compact_scalar_arrays: false,
+        }
+    }
+}
+
+impl PrettyPrinter {
+    pub fn new() -> PrettyPrinter {
+        Self::default()
+    }
+
+    pub fn with_indent(mut self, indent: Indent) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    pub fn with_newline(mut self, newline: Newline) -> Self {
+        self.newline = newline;
+        self
+    }
+
+    /// Writes object members ordered by key instead of insertion order.
+    pub fn with_sort_keys(mut self, sort_keys: bool) -> Self {
+        self.sort_keys = sort_keys;
+        self
+    }
+
+    /// Writes arrays that contain no arrays or objects on a single line, as
+    /// long as it stays within 1024 bytes.
+    pub fn with_compact_scalar_arrays(mut self, compact_scalar_arrays: bool) -> Self {
+        self.compact_scalar_arrays = compact_scalar_arrays;
+        self
+    }
+
+    pub fn to_string(&self, value: &JsonValue) -> String {
+        let mut out = String::new();
+        self.write(&mut out, value)
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W, value: &JsonValue) -> fmt::Result {
+        self.write_value(out, value, 0)
+    }
+
+    pub(crate) fn write_newline<W: Write>(&self, out: &mut W, level: usize) -> fmt::Result {
+        out.write_str(self.newline.as_str())?;
+        let (c, width) = match self.indent {
+            Indent::Spaces(width) => (' ', width),
+            Indent::Tabs(width) => ('\t', width),
+        };
+        for _ in 0..level * width {
+            out.write_char(c)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn write_value<W: Write>(
+        &self,
+        out: &mut W,
+        value: &JsonValue,
+        level: usize,
+    ) -> fmt::Result {
+        match value {
+            JsonValue::Object(obj) if !obj.is_empty() => {
+                let mut members: Vec<_> = obj.iter().collect();
+                if self.sort_keys {
+                    members.sort_by_key(|(key, _)| *key);
+                }
+                out.write_char('{')?;
+                for (i, (key, value)) in members.into_iter().enumerate() {
+                    if i > 0 {
+                        out.write_char(',')?;
+                    }
+                    self.write_newline(out, level + 1)?;
+                    write_str(out, key)?;
+                    out.write_str(": ")?;
+                    self.write_value(out, value, level + 1)?;
+                }
+                self.write_newline(out, level)?;
+                out.write_char('}')
+            }
+            JsonValue::Array(arr) if !arr.is_empty() => {
+                if self.compact_scalar_arrays && arr.iter().all(is_scalar) {
+                    if let Some(line) = compact_scalar_array(arr) {
+                        return out.write_str(&line);
+                    }
+                }
+                out.write_char('[')?;
+                for (i, value) in arr.iter().enumerate() {
+                    if i > 0 {
+                        out.write_char(',')?;
+                    }
+                    self.write_newline(out, level + 1)?;
+                    self.write_value(out, value, level + 1)?;
+                }
+                self.write_newline(out, level)?;
+                out.write_char(']')
+            }
+            _ => write_compact(out, value),
+        }
+    }
+}
+
+/// Renders an array of scalars on one line, or returns `None` once the line
+/// gets longer than [`MAX_COMPACT_ARRAY_WIDTH`].
+fn compact_scalar_array(arr: &[JsonValue]) -> Option<String> {
+    let mut line = String::from("[");
+    for (i, value) in arr.iter().enumerate() {
+        if i > 0 {
+            line.push_str(", ");
+        }
+        write_compact(&mut line, value).expect("writing to a String cannot fail");
+        if line.len() + 1 > MAX_COMPACT_ARRAY_WIDTH {
+            return None;
+        }
+    }
+    line.push(']');
+    Some(line)
+}
+
+fn is_scalar(value: &JsonValue) -> bool {
+    !matches!(value, JsonValue::Object(_) | JsonValue::Array(_))
+}
+
+pub(crate) fn write_compact<W: Write>(out: &mut W, value: &JsonValue) -> fmt::Result {
+    match value {
+        JsonValue::Object(obj) => {
+            out.write_char('{')?;
+            for (i, (key, value)) in obj.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+                write_str(out, key)?;
+                out.write_char(':')?;
+                write_compact(out, value)?;
+            }
+            out.write_char('}')
+        }
+        JsonValue::Array(arr) => {
+            out.write_char('[')?;
+            for (i, value) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+                write_compact(out, value)?;
+            }
+            out.write_char(']')
+        }
+        JsonValue::String(s) => write_str(out, s),
+        JsonValue::Number(n) => write_number(out, n),
+        JsonValue::Bool(b) => write!(out, "{}", b),
+        JsonValue::Null => out.write_str("null"),
+    }
+}
+
+pub(crate) fn write_number<W: Write>(out: &mut W, n: &JsonNumber) -> fmt::Result {
+    write!(out, "{}", n)
+}
+
+/// Writes `n` so that parsing it back yields the same `f64`. JSON has no
+/// representation for NaN or infinities, so those are written as `null`.
+pub(crate) fn write_float<W: Write>(out: &mut W, n: f64) -> fmt::Result {
+    if !n.is_finite() {
+        return out.write_str("null");
+    }
+    let abs = n.abs();
+    if abs != 0.0 && !(1e-5..1e16).contains(&abs) {
+        write!(out, "{:e}", n)
+    } else {
+        write!(out, "{}", n)
+    }
+}
+
+/// Writes `s` as a quoted JSON string, escaping quotes, backslashes and
+/// control characters.
+pub(crate) fn write_str<W: Write>(out: &mut W, s: &str) -> fmt::Result {
+    out.write_char('"')?;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escape = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\u{0008}' => "\\b",
+            '\u{000c}' => "\\f",
+            '\u{0000}'..='\u{001f}' => "",
+            _ => continue,
+        };
+        out.write_str(&s[start..i])?;
+        if escape.is_empty() {
+            write!(out, "\\u{:04x}", c as u32)?;
+        } else {
+            out.write_str(escape)?;
+        }
+        start = i + c.len_utf8();
+    }
+    out.write_str(&s[start..])?;
+    out.write_char('"')
+}
+
+impl fmt::Display for JsonValue {
+    /// Writes the value as compact JSON without any whitespace.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_compact(f, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::JsonParser;
+
+    fn sample() -> JsonValue {
+        JsonParser::new(
+            r#"{"name": "John", "grades": [90, 85.5], "info": {"city": "NY", "tags": []}, "empty": {}, "ok": true, "none": null}"#,
+        )
+        .parse()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_display_compact() {
+        assert_eq!(
+            sample().to_string(),
+            r#"{"name":"John","grades":[90,85.5],"info":{"city":"NY","tags":[]},"empty":{},"ok":true,"none":null}"#
+        );
+    }
+
+    #[test]
+    fn test_string_escapes_round_trip() {
+        let value =
+            JsonValue::String("quote\" back\\ \n\r\t\u{8}\u{c}\u{1}\u{1f} é 😀 \u{7f}".to_string());
+        let text = value.to_string();
+
+        assert_eq!(
+            text,
+            "\"quote\\\" back\\\\ \\n\\r\\t\\b\\f\\u0001\\u001f é 😀 \u{7f}\""
+        );
+        assert_eq!(JsonParser::new(&text).with_strict(true).parse(), Ok(value));
+    }
+
+    #[test]
+    fn test_numbers_round_trip() {
+        for n in [
+            0.0,
+            -0.0,
+            1.0,
+            -12.5,
+            0.1,
+            1e22,
+            1.5e300,
+            2.5e-8,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+        ] {
+            let value = JsonValue::Number(n.into());
+            let text = value.to_string();
+            let parsed = JsonParser::new(&text).with_strict(true).parse();
+            assert_eq!(parsed, Ok(value), "{}", text);
+        }
+        for n in [u64::MAX, 9007199254740993] {
+            let value = JsonValue::Number(n.into());
+            assert_eq!(value.to_string(), n.to_string());
+            assert_eq!(JsonParser::new(&n.to_string()).parse(), Ok(value));
+        }
+        assert_eq!(
+            JsonValue::Number(i64::MIN.into()).to_string(),
+            i64::MIN.to_string()
+        );
+        assert_eq!(JsonValue::Number(f64::NAN.into()).to_string(), "null");
+    }
+
+    #[test]
+    fn test_pretty_default() {
+        let expected = r#"{
+  "name": "John",
+  "grades": [
+    90,
+    85.5
+  ],
+  "info": {
+    "city": "NY",
+    "tags": []
+  },
+  "empty": {},
+  "ok": true,
+  "none": null
+}"#;
+        let text = PrettyPrinter::new().to_string(&sample());
+
+        assert_eq!(text, expected);
+        assert_eq!(JsonParser::new(&text).parse(), Ok(sample()));
+    }
+
+    #[test]
+    fn test_pretty_options() {
+        let printer = PrettyPrinter::new()
+            .with_indent(Indent::Tabs(1))
+            .with_newline(Newline::CrLf)
+            .with_sort_keys(true)
+            .with_compact_scalar_arrays(true);
+        let expected = "{\r\n\t\"empty\": {},\r\n\t\"grades\": [90, 85.5],\r\n\t\"info\": {\r\n\t\t\"city\": \"NY\",\r\n\t\t\"tags\": []\r\n\t},\r\n\t\"name\": \"John\",\r\n\t\"none\": null,\r\n\t\"ok\": true\r\n}";
+
+        assert_eq!(printer.to_string(&sample()), expected);
+
+        let long: JsonValue = (0..1000).collect();
+        let text = PrettyPrinter::new()
+            .with_compact_scalar_arrays(true)
+            .to_string(&long);
+        assert!(text.starts_with("[\n  0,\n  1,"));
+
+        let printer = PrettyPrinter::new().with_indent(Indent::Spaces(4));
+        assert_eq!(
+            printer.to_string(&JsonValue::Array(vec![JsonValue::Null])),
+            "[\n    null\n]"
+        );
+    }
+}
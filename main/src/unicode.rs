@@ -0,0 +1,1110 @@
+// The Unicode general category of every character, for the `\p{..}`
+// escapes of the regex module. Generated from the Unicode Character
+// Database, version 14.0.0.
+
+/// Returns the two-letter general category of `c`, such as `Lu`, or `Cn`
+/// for unassigned code points.
+pub(crate) fn general_category(c: char) -> [u8; 2] {
+    let c = c as u32;
+    let i = CATEGORIES.partition_point(|&(_, end, _)| end < c);
+    match CATEGORIES.get(i) {
+        Some(&(start, _, category)) if start <= c => category,
+        _ => *b"Cn",
+    }
+}
+
+/// Sorted, non-overlapping ranges of assigned code points and their
+/// categories.
+#[rustfmt::skip]
+static CATEGORIES: &[(u32, u32, [u8; 2])] = &[
+    (0x0000, 0x001F, *b"Cc"), (0x0020, 0x0020, *b"Zs"), (0x0021, 0x0023, *b"Po"),
+    (0x0024, 0x0024, *b"Sc"), (0x0025, 0x0027, *b"Po"), (0x0028, 0x0028, *b"Ps"),
+    (0x0029, 0x0029, *b"Pe"), (0x002A, 0x002A, *b"Po"), (0x002B, 0x002B, *b"Sm"),
+    (0x002C, 0x002C, *b"Po"), (0x002D, 0x002D, *b"Pd"), (0x002E, 0x002F, *b"Po"),
+    (0x0030, 0x0039, *b"Nd"), (0x003A, 0x003B, *b"Po"), (0x003C, 0x003E, *b"Sm"),
+    (0x003F, 0x0040, *b"Po"), (0x0041, 0x005A, *b"Lu"), (0x005B, 0x005B, *b"Ps"),
+    (0x005C, 0x005C, *b"Po"), (0x005D, 0x005D, *b"Pe"), (0x005E, 0x005E, *b"Sk"),
+    (0x005F, 0x005F, *b"Pc"), (0x0060, 0x0060, *b"Sk"), (0x0061, 0x007A, *b"Ll"),
+    (0x007B, 0x007B, *b"Ps"), (0x007C, 0x007C, *b"Sm"), (0x007D, 0x007D, *b"Pe"),
+    (0x007E, 0x007E, *b"Sm"), (0x007F, 0x009F, *b"Cc"), (0x00A0, 0x00A0, *b"Zs"),
+    (0x00A1, 0x00A1, *b"Po"), (0x00A2, 0x00A5, *b"Sc"), (0x00A6, 0x00A6, *b"So"),
+    (0x00A7, 0x00A7, *b"Po"), (0x00A8, 0x00A8, *b"Sk"), (0x00A9, 0x00A9, *b"So"),
+    (0x00AA, 0x00AA, *b"Lo"), (0x00AB, 0x00AB, *b"Pi"), (0x00AC, 0x00AC, *b"Sm"),
+    (0x00AD, 0x00AD, *b"Cf"), (0x00AE, 0x00AE, *b"So"), (0x00AF, 0x00AF, *b"Sk"),
+    (0x00B0, 0x00B0, *b"So"), (0x00B1, 0x00B1, *b"Sm"), (0x00B2, 0x00B3, *b"No"),
+    (0x00B4, 0x00B4, *b"Sk"), (0x00B5, 0x00B5, *b"Ll"), (0x00B6, 0x00B7, *b"Po"),
+    (0x00B8, 0x00B8, *b"Sk"), (0x00B9, 0x00B9, *b"No"), (0x00BA, 0x00BA, *b"Lo"),
+    (0x00BB, 0x00BB, *b"Pf"), (0x00BC, 0x00BE, *b"No"), (0x00BF, 0x00BF, *b"Po"),
+    (0x00C0, 0x00D6, *b"Lu"), (0x00D7, 0x00D7, *b"Sm"), (0x00D8, 0x00DE, *b"Lu"),
+    (0x00DF, 0x00F6, *b"Ll"), (0x00F7, 0x00F7, *b"Sm"), (0x00F8, 0x00FF, *b"Ll"),
+    (0x0100, 0x0100, *b"Lu"), (0x0101, 0x0101, *b"Ll"), (0x0102, 0x0102, *b"Lu"),
+    (0x0103, 0x0103, *b"Ll"), (0x0104, 0x0104, *b"Lu"), (0x0105, 0x0105, *b"Ll"),
+    (0x0106, 0x0106, *b"Lu"), (0x0107, 0x0107, *b"Ll"), (0x0108, 0x0108, *b"Lu"),
+    (0x0109, 0x0109, *b"Ll"), (0x010A, 0x010A, *b"Lu"), (0x010B, 0x010B, *b"Ll"),
+    (0x010C, 0x010C, *b"Lu"), (0x010D, 0x010D, *b"Ll"), (0x010E, 0x010E, *b"Lu"),
+    (0x010F, 0x010F, *b"Ll"), (0x0110, 0x0110, *b"Lu"), (0x0111, 0x0111, *b"Ll"),
+    (0x0112, 0x0112, *b"Lu"), (0x0113, 0x0113, *b"Ll"), (0x0114, 0x0114, *b"Lu"),
+    (0x0115, 0x0115, *b"Ll"), (0x0116, 0x0116, *b"Lu"), (0x0117, 0x0117, *b"Ll"),
+    (0x0118, 0x0118, *b"Lu"), (0x0119, 0x0119, *b"Ll"), (0x011A, 0x011A, *b"Lu"),
+    (0x011B, 0x011B, *b"Ll"), (0x011C, 0x011C, *b"Lu"), (0x011D, 0x011D, *b"Ll"),
+    (0x011E, 0x011E, *b"Lu"), (0x011F, 0x011F, *b"Ll"), (0x0120, 0x0120, *b"Lu"),
+    (0x0121, 0x0121, *b"Ll"), (0x0122, 0x0122, *b"Lu"), (0x0123, 0x0123, *b"Ll"),
+    (0x0124, 0x0124, *b"Lu"), (0x0125, 0x0125, *b"Ll"), (0x0126, 0x0126, *b"Lu"),
+    (0x0127, 0x0127, *b"Ll"), (0x0128, 0x0128, *b"Lu"), (0x0129, 0x0129, *b"Ll"),
+    (0x012A, 0x012A, *b"Lu"), (0x012B, 0x012B, *b"Ll"), (0x012C, 0x012C, *b"Lu"),
+    (0x012D, 0x012D, *b"Ll"), (0x012E, 0x012E, *b"Lu"), (0x012F, 0x012F, *b"Ll"),
+    (0x0130, 0x0130, *b"Lu"), (0x0131, 0x0131, *b"Ll"), (0x0132, 0x0132, *b"Lu"),
+    (0x0133, 0x0133, *b"Ll"), (0x0134, 0x0134, *b"Lu"), (0x0135, 0x0135, *b"Ll"),
+    (0x0136, 0x0136, *b"Lu"), (0x0137, 0x0138, *b"Ll"), (0x0139, 0x0139, *b"Lu"),
+    (0x013A, 0x013A, *b"Ll"), (0x013B, 0x013B, *b"Lu"), (0x013C, 0x013C, *b"Ll"),
+    (0x013D, 0x013D, *b"Lu"), (0x013E, 0x013E, *b"Ll"), (0x013F, 0x013F, *b"Lu"),
+    (0x0140, 0x0140, *b"Ll"), (0x0141, 0x0141, *b"Lu"), (0x0142, 0x0142, *b"Ll"),
+    (0x0143, 0x0143, *b"Lu"), (0x0144, 0x0144, *b"Ll"), (0x0145, 0x0145, *b"Lu"),
+    (0x0146, 0x0146, *b"Ll"), (0x0147, 0x0147, *b"Lu"), (0x0148, 0x0149, *b"Ll"),
+    (0x014A, 0x014A, *b"Lu"), (0x014B, 0x014B, *b"Ll"), (0x014C, 0x014C, *b"Lu"),
+    (0x014D, 0x014D, *b"Ll"), (0x014E, 0x014E, *b"Lu"), (0x014F, 0x014F, *b"Ll"),
+    (0x0150, 0x0150, *b"Lu"), (0x0151, 0x0151, *b"Ll"), (0x0152, 0x0152, *b"Lu"),
+    (0x0153, 0x0153, *b"Ll"), (0x0154, 0x0154, *b"Lu"), (0x0155, 0x0155, *b"Ll"),
+    (0x0156, 0x0156, *b"Lu"), (0x0157, 0x0157, *b"Ll"), (0x0158, 0x0158, *b"Lu"),
+    (0x0159, 0x0159, *b"Ll"), (0x015A, 0x015A, *b"Lu"), (0x015B, 0x015B, *b"Ll"),
+    (0x015C, 0x015C, *b"Lu"), (0x015D, 0x015D, *b"Ll"), (0x015E, 0x015E, *b"Lu"),
+    (0x015F, 0x015F, *b"Ll"), (0x0160, 0x0160, *b"Lu"), (0x0161, 0x0161, *b"Ll"),
+    (0x0162, 0x0162, *b"Lu"), (0x0163, 0x0163, *b"Ll"), (0x0164, 0x0164, *b"Lu"),
+    (0x0165, 0x0165, *b"Ll"), (0x0166, 0x0166, *b"Lu"), (0x0167, 0x0167, *b"Ll"),
+    (0x0168, 0x0168, *b"Lu"), (0x0169, 0x0169, *b"Ll"), (0x016A, 0x016A, *b"Lu"),
+    (0x016B, 0x016B, *b"Ll"), (0x016C, 0x016C, *b"Lu"), (0x016D, 0x016D, *b"Ll"),
+    (0x016E, 0x016E, *b"Lu"), (0x016F, 0x016F, *b"Ll"), (0x0170, 0x0170, *b"Lu"),
+    (0x0171, 0x0171, *b"Ll"), (0x0172, 0x0172, *b"Lu"), (0x0173, 0x0173, *b"Ll"),
+    (0x0174, 0x0174, *b"Lu"), (0x0175, 0x0175, *b"Ll"), (0x0176, 0x0176, *b"Lu"),
+    (0x0177, 0x0177, *b"Ll"), (0x0178, 0x0179, *b"Lu"), (0x017A, 0x017A, *b"Ll"),
+    (0x017B, 0x017B, *b"Lu"), (0x017C, 0x017C, *b"Ll"), (0x017D, 0x017D, *b"Lu"),
+    (0x017E, 0x0180, *b"Ll"), (0x0181, 0x0182, *b"Lu"), (0x0183, 0x0183, *b"Ll"),
+    (0x0184, 0x0184, *b"Lu"), (0x0185, 0x0185, *b"Ll"), (0x0186, 0x0187, *b"Lu"),
+    (0x0188, 0x0188, *b"Ll"), (0x0189, 0x018B, *b"Lu"), (0x018C, 0x018D, *b"Ll"),
+    (0x018E, 0x0191, *b"Lu"), (0x0192, 0x0192, *b"Ll"), (0x0193, 0x0194, *b"Lu"),
+    (0x0195, 0x0195, *b"Ll"), (0x0196, 0x0198, *b"Lu"), (0x0199, 0x019B, *b"Ll"),
+    (0x019C, 0x019D, *b"Lu"), (0x019E, 0x019E, *b"Ll"), (0x019F, 0x01A0, *b"Lu"),
+    (0x01A1, 0x01A1, *b"Ll"), (0x01A2, 0x01A2, *b"Lu"), (0x01A3, 0x01A3, *b"Ll"),
+    (0x01A4, 0x01A4, *b"Lu"), (0x01A5, 0x01A5, *b"Ll"), (0x01A6, 0x01A7, *b"Lu"),
+    (0x01A8, 0x01A8, *b"Ll"), (0x01A9, 0x01A9, *b"Lu"), (0x01AA, 0x01AB, *b"Ll"),
+    (0x01AC, 0x01AC, *b"Lu"), (0x01AD, 0x01AD, *b"Ll"), (0x01AE, 0x01AF, *b"Lu"),
+    (0x01B0, 0x01B0, *b"Ll"), (0x01B1, 0x01B3, *b"Lu"), (0x01B4, 0x01B4, *b"Ll"),
+    (0x01B5, 0x01B5, *b"Lu"), (0x01B6, 0x01B6, *b"Ll"), (0x01B7, 0x01B8, *b"Lu"),
+    (0x01B9, 0x01BA, *b"Ll"), (0x01BB, 0x01BB, *b"Lo"), (0x01BC, 0x01BC, *b"Lu"),
+    (0x01BD, 0x01BF, *b"Ll"), (0x01C0, 0x01C3, *b"Lo"), (0x01C4, 0x01C4, *b"Lu"),
+    (0x01C5, 0x01C5, *b"Lt"), (0x01C6, 0x01C6, *b"Ll"), (0x01C7, 0x01C7, *b"Lu"),
+    (0x01C8, 0x01C8, *b"Lt"), (0x01C9, 0x01C9, *b"Ll"), (0x01CA, 0x01CA, *b"Lu"),
+    (0x01CB, 0x01CB, *b"Lt"), (0x01CC, 0x01CC, *b"Ll"), (0x01CD, 0x01CD, *b"Lu"),
+    (0x01CE, 0x01CE, *b"Ll"), (0x01CF, 0x01CF, *b"Lu"), (0x01D0, 0x01D0, *b"Ll"),
+    (0x01D1, 0x01D1, *b"Lu"), (0x01D2, 0x01D2, *b"Ll"), (0x01D3, 0x01D3, *b"Lu"),
+    (0x01D4, 0x01D4, *b"Ll"), (0x01D5, 0x01D5, *b"Lu"), (0x01D6, 0x01D6, *b"Ll"),
+    (0x01D7, 0x01D7, *b"Lu"), (0x01D8, 0x01D8, *b"Ll"), (0x01D9, 0x01D9, *b"Lu"),
+    (0x01DA, 0x01DA, *b"Ll"), (0x01DB, 0x01DB, *b"Lu"), (0x01DC, 0x01DD, *b"Ll"),
+    (0x01DE, 0x01DE, *b"Lu"), (0x01DF, 0x01DF, *b"Ll"), (0x01E0, 0x01E0, *b"Lu"),
+    (0x01E1, 0x01E1, *b"Ll"), (0x01E2, 0x01E2, *b"Lu"), (0x01E3, 0x01E3, *b"Ll"),
+    (0x01E4, 0x01E4, *b"Lu"), (0x01E5, 0x01E5, *b"Ll"), (0x01E6, 0x01E6, *b"Lu"),
+    (0x01E7, 0x01E7, *b"Ll"), (0x01E8, 0x01E8, *b"Lu"), (0x01E9, 0x01E9, *b"Ll"),
+    (0x01EA, 0x01EA, *b"Lu"), (0x01EB, 0x01EB, *b"Ll"), (0x01EC, 0x01EC, *b"Lu"),
+    (0x01ED, 0x01ED, *b"Ll"), (0x01EE, 0x01EE, *b"Lu"), (0x01EF, 0x01F0, *b"Ll"),
+    (0x01F1, 0x01F1, *b"Lu"), (0x01F2, 0x01F2, *b"Lt"), (0x01F3, 0x01F3, *b"Ll"),
+    (0x01F4, 0x01F4, *b"Lu"), (0x01F5, 0x01F5, *b"Ll"), (0x01F6, 0x01F8, *b"Lu"),
+    (0x01F9, 0x01F9, *b"Ll"), (0x01FA, 0x01FA, *b"Lu"), (0x01FB, 0x01FB, *b"Ll"),
+    (0x01FC, 0x01FC, *b"Lu"), (0x01FD, 0x01FD, *b"Ll"), (0x01FE, 0x01FE, *b"Lu"),
+    (0x01FF, 0x01FF, *b"Ll"), (0x0200, 0x0200, *b"Lu"), (0x0201, 0x0201, *b"Ll"),
+    (0x0202, 0x0202, *b"Lu"), (0x0203, 0x0203, *b"Ll"), (0x0204, 0x0204, *b"Lu"),
+    (0x0205, 0x0205, *b"Ll"), (0x0206, 0x0206, *b"Lu"), (0x0207, 0x0207, *b"Ll"),
+    (0x0208, 0x0208, *b"Lu"), (0x0209, 0x0209, *b"Ll"), (0x020A, 0x020A, *b"Lu"),
+    (0x020B, 0x020B, *b"Ll"), (0x020C, 0x020C, *b"Lu"), (0x020D, 0x020D, *b"Ll"),
+    (0x020E, 0x020E, *b"Lu"), (0x020F, 0x020F, *b"Ll"), (0x0210, 0x0210, *b"Lu"),
+    (0x0211, 0x0211, *b"Ll"), (0x0212, 0x0212, *b"Lu"), (0x0213, 0x0213, *b"Ll"),
+    (0x0214, 0x0214, *b"Lu"), (0x0215, 0x0215, *b"Ll"), (0x0216, 0x0216, *b"Lu"),
+    (0x0217, 0x0217, *b"Ll"), (0x0218, 0x0218, *b"Lu"), (0x0219, 0x0219, *b"Ll"),
+    (0x021A, 0x021A, *b"Lu"), (0x021B, 0x021B, *b"Ll"), (0x021C, 0x021C, *b"Lu"),
+    (0x021D, 0x021D, *b"Ll"), (0x021E, 0x021E, *b"Lu"), (0x021F, 0x021F, *b"Ll"),
+    (0x0220, 0x0220, *b"Lu"), (0x0221, 0x0221, *b"Ll"), (0x0222, 0x0222, *b"Lu"),
+    (0x0223, 0x0223, *b"Ll"), (0x0224, 0x0224, *b"Lu"), (0x0225, 0x0225, *b"Ll"),
+    (0x0226, 0x0226, *b"Lu"), (0x0227, 0x0227, *b"Ll"), (0x0228, 0x0228, *b"Lu"),
+    (0x0229, 0x0229, *b"Ll"), (0x022A, 0x022A, *b"Lu"), (0x022B, 0x022B, *b"Ll"),
+    (0x022C, 0x022C, *b"Lu"), (0x022D, 0x022D, *b"Ll"), (0x022E, 0x022E, *b"Lu"),
+    (0x022F, 0x022F, *b"Ll"), (0x0230, 0x0230, *b"Lu"), (0x0231, 0x0231, *b"Ll"),
+    (0x0232, 0x0232, *b"Lu"), (0x0233, 0x0239, *b"Ll"), (0x023A, 0x023B, *b"Lu"),
+    (0x023C, 0x023C, *b"Ll"), (0x023D, 0x023E, *b"Lu"), (0x023F, 0x0240, *b"Ll"),
+    (0x0241, 0x0241, *b"Lu"), (0x0242, 0x0242, *b"Ll"), (0x0243, 0x0246, *b"Lu"),
+    (0x0247, 0x0247, *b"Ll"), (0x0248, 0x0248, *b"Lu"), (0x0249, 0x0249, *b"Ll"),
+    (0x024A, 0x024A, *b"Lu"), (0x024B, 0x024B, *b"Ll"), (0x024C, 0x024C, *b"Lu"),
+    (0x024D, 0x024D, *b"Ll"), (0x024E, 0x024E, *b"Lu"), (0x024F, 0x0293, *b"Ll"),
+    (0x0294, 0x0294, *b"Lo"), (0x0295, 0x02AF, *b"Ll"), (0x02B0, 0x02C1, *b"Lm"),
+    (0x02C2, 0x02C5, *b"Sk"), (0x02C6, 0x02D1, *b"Lm"), (0x02D2, 0x02DF, *b"Sk"),
+    (0x02E0, 0x02E4, *b"Lm"), (0x02E5, 0x02EB, *b"Sk"), (0x02EC, 0x02EC, *b"Lm"),
+    (0x02ED, 0x02ED, *b"Sk"), (0x02EE, 0x02EE, *b"Lm"), (0x02EF, 0x02FF, *b"Sk"),
+    (0x0300, 0x036F, *b"Mn"), (0x0370, 0x0370, *b"Lu"), (0x0371, 0x0371, *b"Ll"),
+    (0x0372, 0x0372, *b"Lu"), (0x0373, 0x0373, *b"Ll"), (0x0374, 0x0374, *b"Lm"),
+    (0x0375, 0x0375, *b"Sk"), (0x0376, 0x0376, *b"Lu"), (0x0377, 0x0377, *b"Ll"),
+    (0x037A, 0x037A, *b"Lm"), (0x037B, 0x037D, *b"Ll"), (0x037E, 0x037E, *b"Po"),
+    (0x037F, 0x037F, *b"Lu"), (0x0384, 0x0385, *b"Sk"), (0x0386, 0x0386, *b"Lu"),
+    (0x0387, 0x0387, *b"Po"), (0x0388, 0x038A, *b"Lu"), (0x038C, 0x038C, *b"Lu"),
+    (0x038E, 0x038F, *b"Lu"), (0x0390, 0x0390, *b"Ll"), (0x0391, 0x03A1, *b"Lu"),
+    (0x03A3, 0x03AB, *b"Lu"), (0x03AC, 0x03CE, *b"Ll"), (0x03CF, 0x03CF, *b"Lu"),
+    (0x03D0, 0x03D1, *b"Ll"), (0x03D2, 0x03D4, *b"Lu"), (0x03D5, 0x03D7, *b"Ll"),
+    (0x03D8, 0x03D8, *b"Lu"), (0x03D9, 0x03D9, *b"Ll"), (0x03DA, 0x03DA, *b"Lu"),
+    (0x03DB, 0x03DB, *b"Ll"), (0x03DC, 0x03DC, *b"Lu"), (0x03DD, 0x03DD, *b"Ll"),
+    (0x03DE, 0x03DE, *b"Lu"), (0x03DF, 0x03DF, *b"Ll"), (0x03E0, 0x03E0, *b"Lu"),
+    (0x03E1, 0x03E1, *b"Ll"), (0x03E2, 0x03E2, *b"Lu"), (0x03E3, 0x03E3, *b"Ll"),
+    (0x03E4, 0x03E4, *b"Lu"), (0x03E5, 0x03E5, *b"Ll"), (0x03E6, 0x03E6, *b"Lu"),
+    (0x03E7, 0x03E7, *b"Ll"), (0x03E8, 0x03E8, *b"Lu"), (0x03E9, 0x03E9, *b"Ll"),
+    (0x03EA, 0x03EA, *b"Lu"), (0x03EB, 0x03EB, *b"Ll"), (0x03EC, 0x03EC, *b"Lu"),
+    (0x03ED, 0x03ED, *b"Ll"), (0x03EE, 0x03EE, *b"Lu"), (0x03EF, 0x03F3, *b"Ll"),
+    (0x03F4, 0x03F4, *b"Lu"), (0x03F5, 0x03F5, *b"Ll"), (0x03F6, 0x03F6, *b"Sm"),
+    (0x03F7, 0x03F7, *b"Lu"), (0x03F8, 0x03F8, *b"Ll"), (0x03F9, 0x03FA, *b"Lu"),
+    (0x03FB, 0x03FC, *b"Ll"), (0x03FD, 0x042F, *b"Lu"), (0x0430, 0x045F, *b"Ll"),
+    (0x0460, 0x0460, *b"Lu"), (0x0461, 0x0461, *b"Ll"), (0x0462, 0x0462, *b"Lu"),
+    (0x0463, 0x0463, *b"Ll"), (0x0464, 0x0464, *b"Lu"), (0x0465, 0x0465, *b"Ll"),
+    (0x0466, 0x0466, *b"Lu"), (0x0467, 0x0467, *b"Ll"), (0x0468, 0x0468, *b"Lu"),
+    (0x0469, 0x0469, *b"Ll"), (0x046A, 0x046A, *b"Lu"), (0x046B, 0x046B, *b"Ll"),
+    (0x046C, 0x046C, *b"Lu"), (0x046D, 0x046D, *b"Ll"), (0x046E, 0x046E, *b"Lu"),
+    (0x046F, 0x046F, *b"Ll"), (0x0470, 0x0470, *b"Lu"), (0x0471, 0x0471, *b"Ll"),
+    (0x0472, 0x0472, *b"Lu"), (0x0473, 0x0473, *b"Ll"), (0x0474, 0x0474, *b"Lu"),
+    (0x0475, 0x0475, *b"Ll"), (0x0476, 0x0476, *b"Lu"), (0x0477, 0x0477, *b"Ll"),
+    (0x0478, 0x0478, *b"Lu"), (0x0479, 0x0479, *b"Ll"), (0x047A, 0x047A, *b"Lu"),
+    (0x047B, 0x047B, *b"Ll"), (0x047C, 0x047C, *b"Lu"), (0x047D, 0x047D, *b"Ll"),
+    (0x047E, 0x047E, *b"Lu"), (0x047F, 0x047F, *b"Ll"), (0x0480, 0x0480, *b"Lu"),
+    (0x0481, 0x0481, *b"Ll"), (0x0482, 0x0482, *b"So"), (0x0483, 0x0487, *b"Mn"),
+    (0x0488, 0x0489, *b"Me"), (0x048A, 0x048A, *b"Lu"), (0x048B, 0x048B, *b"Ll"),
+    (0x048C, 0x048C, *b"Lu"), (0x048D, 0x048D, *b"Ll"), (0x048E, 0x048E, *b"Lu"),
+    (0x048F, 0x048F, *b"Ll"), (0x0490, 0x0490, *b"Lu"), (0x0491, 0x0491, *b"Ll"),
+    (0x0492, 0x0492, *b"Lu"), (0x0493, 0x0493, *b"Ll"), (0x0494, 0x0494, *b"Lu"),
+    (0x0495, 0x0495, *b"Ll"), (0x0496, 0x0496, *b"Lu"), (0x0497, 0x0497, *b"Ll"),
+    (0x0498, 0x0498, *b"Lu"), (0x0499, 0x0499, *b"Ll"), (0x049A, 0x049A, *b"Lu"),
+    (0x049B, 0x049B, *b"Ll"), (0x049C, 0x049C, *b"Lu"), (0x049D, 0x049D, *b"Ll"),
+    (0x049E, 0x049E, *b"Lu"), (0x049F, 0x049F, *b"Ll"), (0x04A0, 0x04A0, *b"Lu"),
+    (0x04A1, 0x04A1, *b"Ll"), (0x04A2, 0x04A2, *b"Lu"), (0x04A3, 0x04A3, *b"Ll"),
+    (0x04A4, 0x04A4, *b"Lu"), (0x04A5, 0x04A5, *b"Ll"), (0x04A6, 0x04A6, *b"Lu"),
+    (0x04A7, 0x04A7, *b"Ll"), (0x04A8, 0x04A8, *b"Lu"), (0x04A9, 0x04A9, *b"Ll"),
+    (0x04AA, 0x04AA, *b"Lu"), (0x04AB, 0x04AB, *b"Ll"), (0x04AC, 0x04AC, *b"Lu"),
+    (0x04AD, 0x04AD, *b"Ll"), (0x04AE, 0x04AE, *b"Lu"), (0x04AF, 0x04AF, *b"Ll"),
+    (0x04B0, 0x04B0, *b"Lu"), (0x04B1, 0x04B1, *b"Ll"), (0x04B2, 0x04B2, *b"Lu"),
+    (0x04B3, 0x04B3, *b"Ll"), (0x04B4, 0x04B4, *b"Lu"), (0x04B5, 0x04B5, *b"Ll"),
+    (0x04B6, 0x04B6, *b"Lu"), (0x04B7, 0x04B7, *b"Ll"), (0x04B8, 0x04B8, *b"Lu"),
+    (0x04B9, 0x04B9, *b"Ll"), (0x04BA, 0x04BA, *b"Lu"), (0x04BB, 0x04BB, *b"Ll"),
+    (0x04BC, 0x04BC, *b"Lu"), (0x04BD, 0x04BD, *b"Ll"), (0x04BE, 0x04BE, *b"Lu"),
+    (0x04BF, 0x04BF, *b"Ll"), (0x04C0, 0x04C1, *b"Lu"), (0x04C2, 0x04C2, *b"Ll"),
+    (0x04C3, 0x04C3, *b"Lu"), (0x04C4, 0x04C4, *b"Ll"), (0x04C5, 0x04C5, *b"Lu"),
+    (0x04C6, 0x04C6, *b"Ll"), (0x04C7, 0x04C7, *b"Lu"), (0x04C8, 0x04C8, *b"Ll"),
+    (0x04C9, 0x04C9, *b"Lu"), (0x04CA, 0x04CA, *b"Ll"), (0x04CB, 0x04CB, *b"Lu"),
+    (0x04CC, 0x04CC, *b"Ll"), (0x04CD, 0x04CD, *b"Lu"), (0x04CE, 0x04CF, *b"Ll"),
+    (0x04D0, 0x04D0, *b"Lu"), (0x04D1, 0x04D1, *b"Ll"), (0x04D2, 0x04D2, *b"Lu"),
+    (0x04D3, 0x04D3, *b"Ll"), (0x04D4, 0x04D4, *b"Lu"), (0x04D5, 0x04D5, *b"Ll"),
+    (0x04D6, 0x04D6, *b"Lu"), (0x04D7, 0x04D7, *b"Ll"), (0x04D8, 0x04D8, *b"Lu"),
+    (0x04D9, 0x04D9, *b"Ll"), (0x04DA, 0x04DA, *b"Lu"), (0x04DB, 0x04DB, *b"Ll"),
+    (0x04DC, 0x04DC, *b"Lu"), (0x04DD, 0x04DD, *b"Ll"), (0x04DE, 0x04DE, *b"Lu"),
+    (0x04DF, 0x04DF, *b"Ll"), (0x04E0, 0x04E0, *b"Lu"), (0x04E1, 0x04E1, *b"Ll"),
+    (0x04E2, 0x04E2, *b"Lu"), (0x04E3, 0x04E3, *b"Ll"), (0x04E4, 0x04E4, *b"Lu"),
+    (0x04E5, 0x04E5, *b"Ll"), (0x04E6, 0x04E6, *b"Lu"), (0x04E7, 0x04E7, *b"Ll"),
+    (0x04E8, 0x04E8, *b"Lu"), (0x04E9, 0x04E9, *b"Ll"), (0x04EA, 0x04EA, *b"Lu"),
+    (0x04EB, 0x04EB, *b"Ll"), (0x04EC, 0x04EC, *b"Lu"), (0x04ED, 0x04ED, *b"Ll"),
+    (0x04EE, 0x04EE, *b"Lu"), (0x04EF, 0x04EF, *b"Ll"), (0x04F0, 0x04F0, *b"Lu"),
+    (0x04F1, 0x04F1, *b"Ll"), (0x04F2, 0x04F2, *b"Lu"), (0x04F3, 0x04F3, *b"Ll"),
+    (0x04F4, 0x04F4, *b"Lu"), (0x04F5, 0x04F5, *b"Ll"), (0x04F6, 0x04F6, *b"Lu"),
+    (0x04F7, 0x04F7, *b"Ll"), (0x04F8, 0x04F8, *b"Lu"), (0x04F9, 0x04F9, *b"Ll"),
+    (0x04FA, 0x04FA, *b"Lu"), (0x04FB, 0x04FB, *b"Ll"), (0x04FC, 0x04FC, *b"Lu"),
+    (0x04FD, 0x04FD, *b"Ll"), (0x04FE, 0x04FE, *b"Lu"), (0x04FF, 0x04FF, *b"Ll"),
+    (0x0500, 0x0500, *b"Lu"), (0x0501, 0x0501, *b"Ll"), (0x0502, 0x0502, *b"Lu"),
+    (0x0503, 0x0503, *b"Ll"), (0x0504, 0x0504, *b"Lu"), (0x0505, 0x0505, *b"Ll"),
+    (0x0506, 0x0506, *b"Lu"), (0x0507, 0x0507, *b"Ll"), (0x0508, 0x0508, *b"Lu"),
+    (0x0509, 0x0509, *b"Ll"), (0x050A, 0x050A, *b"Lu"), (0x050B, 0x050B, *b"Ll"),
+    (0x050C, 0x050C, *b"Lu"), (0x050D, 0x050D, *b"Ll"), (0x050E, 0x050E, *b"Lu"),
+    (0x050F, 0x050F, *b"Ll"), (0x0510, 0x0510, *b"Lu"), (0x0511, 0x0511, *b"Ll"),
+    (0x0512, 0x0512, *b"Lu"), (0x0513, 0x0513, *b"Ll"), (0x0514, 0x0514, *b"Lu"),
+    (0x0515, 0x0515, *b"Ll"), (0x0516, 0x0516, *b"Lu"), (0x0517, 0x0517, *b"Ll"),
+    (0x0518, 0x0518, *b"Lu"), (0x0519, 0x0519, *b"Ll"), (0x051A, 0x051A, *b"Lu"),
+    (0x051B, 0x051B, *b"Ll"), (0x051C, 0x051C, *b"Lu"), (0x051D, 0x051D, *b"Ll"),
+    (0x051E, 0x051E, *b"Lu"), (0x051F, 0x051F, *b"Ll"), (0x0520, 0x0520, *b"Lu"),
+    (0x0521, 0x0521, *b"Ll"), (0x0522, 0x0522, *b"Lu"), (0x0523, 0x0523, *b"Ll"),
+    (0x0524, 0x0524, *b"Lu"), (0x0525, 0x0525, *b"Ll"), (0x0526, 0x0526, *b"Lu"),
+    (0x0527, 0x0527, *b"Ll"), (0x0528, 0x0528, *b"Lu"), (0x0529, 0x0529, *b"Ll"),
+    (0x052A, 0x052A, *b"Lu"), (0x052B, 0x052B, *b"Ll"), (0x052C, 0x052C, *b"Lu"),
+    (0x052D, 0x052D, *b"Ll"), (0x052E, 0x052E, *b"Lu"), (0x052F, 0x052F, *b"Ll"),
+    (0x0531, 0x0556, *b"Lu"), (0x0559, 0x0559, *b"Lm"), (0x055A, 0x055F, *b"Po"),
+    (0x0560, 0x0588, *b"Ll"), (0x0589, 0x0589, *b"Po"), (0x058A, 0x058A, *b"Pd"),
+    (0x058D, 0x058E, *b"So"), (0x058F, 0x058F, *b"Sc"), (0x0591, 0x05BD, *b"Mn"),
+    (0x05BE, 0x05BE, *b"Pd"), (0x05BF, 0x05BF, *b"Mn"), (0x05C0, 0x05C0, *b"Po"),
+    (0x05C1, 0x05C2, *b"Mn"), (0x05C3, 0x05C3, *b"Po"), (0x05C4, 0x05C5, *b"Mn"),
+    (0x05C6, 0x05C6, *b"Po"), (0x05C7, 0x05C7, *b"Mn"), (0x05D0, 0x05EA, *b"Lo"),
+    (0x05EF, 0x05F2, *b"Lo"), (0x05F3, 0x05F4, *b"Po"), (0x0600, 0x0605, *b"Cf"),
+    (0x0606, 0x0608, *b"Sm"), (0x0609, 0x060A, *b"Po"), (0x060B, 0x060B, *b"Sc"),
+    (0x060C, 0x060D, *b"Po"), (0x060E, 0x060F, *b"So"), (0x0610, 0x061A, *b"Mn"),
+    (0x061B, 0x061B, *b"Po"), (0x061C, 0x061C, *b"Cf"), (0x061D, 0x061F, *b"Po"),
+    (0x0620, 0x063F, *b"Lo"), (0x0640, 0x0640, *b"Lm"), (0x0641, 0x064A, *b"Lo"),
+    (0x064B, 0x065F, *b"Mn"), (0x0660, 0x0669, *b"Nd"), (0x066A, 0x066D, *b"Po"),
+    (0x066E, 0x066F, *b"Lo"), (0x0670, 0x0670, *b"Mn"), (0x0671, 0x06D3, *b"Lo"),
+    (0x06D4, 0x06D4, *b"Po"), (0x06D5, 0x06D5, *b"Lo"), (0x06D6, 0x06DC, *b"Mn"),
+    (0x06DD, 0x06DD, *b"Cf"), (0x06DE, 0x06DE, *b"So"), (0x06DF, 0x06E4, *b"Mn"),
+    (0x06E5, 0x06E6, *b"Lm"), (0x06E7, 0x06E8, *b"Mn"), (0x06E9, 0x06E9, *b"So"),
+    (0x06EA, 0x06ED, *b"Mn"), (0x06EE, 0x06EF, *b"Lo"), (0x06F0, 0x06F9, *b"Nd"),
+    (0x06FA, 0x06FC, *b"Lo"), (0x06FD, 0x06FE, *b"So"), (0x06FF, 0x06FF, *b"Lo"),
+    (0x0700, 0x070D, *b"Po"), (0x070F, 0x070F, *b"Cf"), (0x0710, 0x0710, *b"Lo"),
+    (0x0711, 0x0711, *b"Mn"), (0x0712, 0x072F, *b"Lo"), (0x0730, 0x074A, *b"Mn"),
+    (0x074D, 0x07A5, *b"Lo"), (0x07A6, 0x07B0, *b"Mn"), (0x07B1, 0x07B1, *b"Lo"),
+    (0x07C0, 0x07C9, *b"Nd"), (0x07CA, 0x07EA, *b"Lo"), (0x07EB, 0x07F3, *b"Mn"),
+    (0x07F4, 0x07F5, *b"Lm"), (0x07F6, 0x07F6, *b"So"), (0x07F7, 0x07F9, *b"Po"),
+    (0x07FA, 0x07FA, *b"Lm"), (0x07FD, 0x07FD, *b"Mn"), (0x07FE, 0x07FF, *b"Sc"),
+    (0x0800, 0x0815, *b"Lo"), (0x0816, 0x0819, *b"Mn"), (0x081A, 0x081A, *b"Lm"),
+    (0x081B, 0x0823, *b"Mn"), (0x0824, 0x0824, *b"Lm"), (0x0825, 0x0827, *b"Mn"),
+    (0x0828, 0x0828, *b"Lm"), (0x0829, 0x082D, *b"Mn"), (0x0830, 0x083E, *b"Po"),
+    (0x0840, 0x0858, *b"Lo"), (0x0859, 0x085B, *b"Mn"), (0x085E, 0x085E, *b"Po"),
+    (0x0860, 0x086A, *b"Lo"), (0x0870, 0x0887, *b"Lo"), (0x0888, 0x0888, *b"Sk"),
+    (0x0889, 0x088E, *b"Lo"), (0x0890, 0x0891, *b"Cf"), (0x0898, 0x089F, *b"Mn"),
+    (0x08A0, 0x08C8, *b"Lo"), (0x08C9, 0x08C9, *b"Lm"), (0x08CA, 0x08E1, *b"Mn"),
+    (0x08E2, 0x08E2, *b"Cf"), (0x08E3, 0x0902, *b"Mn"), (0x0903, 0x0903, *b"Mc"),
+    (0x0904, 0x0939, *b"Lo"), (0x093A, 0x093A, *b"Mn"), (0x093B, 0x093B, *b"Mc"),
+    (0x093C, 0x093C, *b"Mn"), (0x093D, 0x093D, *b"Lo"), (0x093E, 0x0940, *b"Mc"),
+    (0x0941, 0x0948, *b"Mn"), (0x0949, 0x094C, *b"Mc"), (0x094D, 0x094D, *b"Mn"),
+    (0x094E, 0x094F, *b"Mc"), (0x0950, 0x0950, *b"Lo"), (0x0951, 0x0957, *b"Mn"),
+    (0x0958, 0x0961, *b"Lo"), (0x0962, 0x0963, *b"Mn"), (0x0964, 0x0965, *b"Po"),
+    (0x0966, 0x096F, *b"Nd"), (0x0970, 0x0970, *b"Po"), (0x0971, 0x0971, *b"Lm"),
+    (0x0972, 0x0980, *b"Lo"), (0x0981, 0x0981, *b"Mn"), (0x0982, 0x0983, *b"Mc"),
+    (0x0985, 0x098C, *b"Lo"), (0x098F, 0x0990, *b"Lo"), (0x0993, 0x09A8, *b"Lo"),
+    (0x09AA, 0x09B0, *b"Lo"), (0x09B2, 0x09B2, *b"Lo"), (0x09B6, 0x09B9, *b"Lo"),
+    (0x09BC, 0x09BC, *b"Mn"), (0x09BD, 0x09BD, *b"Lo"), (0x09BE, 0x09C0, *b"Mc"),
+    (0x09C1, 0x09C4, *b"Mn"), (0x09C7, 0x09C8, *b"Mc"), (0x09CB, 0x09CC, *b"Mc"),
+    (0x09CD, 0x09CD, *b"Mn"), (0x09CE, 0x09CE, *b"Lo"), (0x09D7, 0x09D7, *b"Mc"),
+    (0x09DC, 0x09DD, *b"Lo"), (0x09DF, 0x09E1, *b"Lo"), (0x09E2, 0x09E3, *b"Mn"),
+    (0x09E6, 0x09EF, *b"Nd"), (0x09F0, 0x09F1, *b"Lo"), (0x09F2, 0x09F3, *b"Sc"),
+    (0x09F4, 0x09F9, *b"No"), (0x09FA, 0x09FA, *b"So"), (0x09FB, 0x09FB, *b"Sc"),
+    (0x09FC, 0x09FC, *b"Lo"), (0x09FD, 0x09FD, *b"Po"), (0x09FE, 0x09FE, *b"Mn"),
+    (0x0A01, 0x0A02, *b"Mn"), (0x0A03, 0x0A03, *b"Mc"), (0x0A05, 0x0A0A, *b"Lo"),
+    (0x0A0F, 0x0A10, *b"Lo"), (0x0A13, 0x0A28, *b"Lo"), (0x0A2A, 0x0A30, *b"Lo"),
+    (0x0A32, 0x0A33, *b"Lo"), (0x0A35, 0x0A36, *b"Lo"), (0x0A38, 0x0A39, *b"Lo"),
+    (0x0A3C, 0x0A3C, *b"Mn"), (0x0A3E, 0x0A40, *b"Mc"), (0x0A41, 0x0A42, *b"Mn"),
+    (0x0A47, 0x0A48, *b"Mn"), (0x0A4B, 0x0A4D, *b"Mn"), (0x0A51, 0x0A51, *b"Mn"),
+    (0x0A59, 0x0A5C, *b"Lo"), (0x0A5E, 0x0A5E, *b"Lo"), (0x0A66, 0x0A6F, *b"Nd"),
+    (0x0A70, 0x0A71, *b"Mn"), (0x0A72, 0x0A74, *b"Lo"), (0x0A75, 0x0A75, *b"Mn"),
+    (0x0A76, 0x0A76, *b"Po"), (0x0A81, 0x0A82, *b"Mn"), (0x0A83, 0x0A83, *b"Mc"),
+    (0x0A85, 0x0A8D, *b"Lo"), (0x0A8F, 0x0A91, *b"Lo"), (0x0A93, 0x0AA8, *b"Lo"),
+    (0x0AAA, 0x0AB0, *b"Lo"), (0x0AB2, 0x0AB3, *b"Lo"), (0x0AB5, 0x0AB9, *b"Lo"),
+    (0x0ABC, 0x0ABC, *b"Mn"), (0x0ABD, 0x0ABD, *b"Lo"), (0x0ABE, 0x0AC0, *b"Mc"),
+    (0x0AC1, 0x0AC5, *b"Mn"), (0x0AC7, 0x0AC8, *b"Mn"), (0x0AC9, 0x0AC9, *b"Mc"),
+    (0x0ACB, 0x0ACC, *b"Mc"), (0x0ACD, 0x0ACD, *b"Mn"), (0x0AD0, 0x0AD0, *b"Lo"),
+    (0x0AE0, 0x0AE1, *b"Lo"), (0x0AE2, 0x0AE3, *b"Mn"), (0x0AE6, 0x0AEF, *b"Nd"),
+    (0x0AF0, 0x0AF0, *b"Po"), (0x0AF1, 0x0AF1, *b"Sc"), (0x0AF9, 0x0AF9, *b"Lo"),
+    (0x0AFA, 0x0AFF, *b"Mn"), (0x0B01, 0x0B01, *b"Mn"), (0x0B02, 0x0B03, *b"Mc"),
+    (0x0B05, 0x0B0C, *b"Lo"), (0x0B0F, 0x0B10, *b"Lo"), (0x0B13, 0x0B28, *b"Lo"),
+    (0x0B2A, 0x0B30, *b"Lo"), (0x0B32, 0x0B33, *b"Lo"), (0x0B35, 0x0B39, *b"Lo"),
+    (0x0B3C, 0x0B3C, *b"Mn"), (0x0B3D, 0x0B3D, *b"Lo"), (0x0B3E, 0x0B3E, *b"Mc"),
+    (0x0B3F, 0x0B3F, *b"Mn"), (0x0B40, 0x0B40, *b"Mc"), (0x0B41, 0x0B44, *b"Mn"),
+    (0x0B47, 0x0B48, *b"Mc"), (0x0B4B, 0x0B4C, *b"Mc"), (0x0B4D, 0x0B4D, *b"Mn"),
+    (0x0B55, 0x0B56, *b"Mn"), (0x0B57, 0x0B57, *b"Mc"), (0x0B5C, 0x0B5D, *b"Lo"),
+    (0x0B5F, 0x0B61, *b"Lo"), (0x0B62, 0x0B63, *b"Mn"), (0x0B66, 0x0B6F, *b"Nd"),
+    (0x0B70, 0x0B70, *b"So"), (0x0B71, 0x0B71, *b"Lo"), (0x0B72, 0x0B77, *b"No"),
+    (0x0B82, 0x0B82, *b"Mn"), (0x0B83, 0x0B83, *b"Lo"), (0x0B85, 0x0B8A, *b"Lo"),
+    (0x0B8E, 0x0B90, *b"Lo"), (0x0B92, 0x0B95, *b"Lo"), (0x0B99, 0x0B9A, *b"Lo"),
+    (0x0B9C, 0x0B9C, *b"Lo"), (0x0B9E, 0x0B9F, *b"Lo"), (0x0BA3, 0x0BA4, *b"Lo"),
+    (0x0BA8, 0x0BAA, *b"Lo"), (0x0BAE, 0x0BB9, *b"Lo"), (0x0BBE, 0x0BBF, *b"Mc"),
+    (0x0BC0, 0x0BC0, *b"Mn"), (0x0BC1, 0x0BC2, *b"Mc"), (0x0BC6, 0x0BC8, *b"Mc"),
+    (0x0BCA, 0x0BCC, *b"Mc"), (0x0BCD, 0x0BCD, *b"Mn"), (0x0BD0, 0x0BD0, *b"Lo"),
+    (0x0BD7, 0x0BD7, *b"Mc"), (0x0BE6, 0x0BEF, *b"Nd"), (0x0BF0, 0x0BF2, *b"No"),
+    (0x0BF3, 0x0BF8, *b"So"), (0x0BF9, 0x0BF9, *b"Sc"), (0x0BFA, 0x0BFA, *b"So"),
+    (0x0C00, 0x0C00, *b"Mn"), (0x0C01, 0x0C03, *b"Mc"), (0x0C04, 0x0C04, *b"Mn"),
+    (0x0C05, 0x0C0C, *b"Lo"), (0x0C0E, 0x0C10, *b"Lo"), (0x0C12, 0x0C28, *b"Lo"),
+    (0x0C2A, 0x0C39, *b"Lo"), (0x0C3C, 0x0C3C, *b"Mn"), (0x0C3D, 0x0C3D, *b"Lo"),
+    (0x0C3E, 0x0C40, *b"Mn"), (0x0C41, 0x0C44, *b"Mc"), (0x0C46, 0x0C48, *b"Mn"),
+    (0x0C4A, 0x0C4D, *b"Mn"), (0x0C55, 0x0C56, *b"Mn"), (0x0C58, 0x0C5A, *b"Lo"),
+    (0x0C5D, 0x0C5D, *b"Lo"), (0x0C60, 0x0C61, *b"Lo"), (0x0C62, 0x0C63, *b"Mn"),
+    (0x0C66, 0x0C6F, *b"Nd"), (0x0C77, 0x0C77, *b"Po"), (0x0C78, 0x0C7E, *b"No"),
+    (0x0C7F, 0x0C7F, *b"So"), (0x0C80, 0x0C80, *b"Lo"), (0x0C81, 0x0C81, *b"Mn"),
+    (0x0C82, 0x0C83, *b"Mc"), (0x0C84, 0x0C84, *b"Po"), (0x0C85, 0x0C8C, *b"Lo"),
+    (0x0C8E, 0x0C90, *b"Lo"), (0x0C92, 0x0CA8, *b"Lo"), (0x0CAA, 0x0CB3, *b"Lo"),
+    (0x0CB5, 0x0CB9, *b"Lo"), (0x0CBC, 0x0CBC, *b"Mn"), (0x0CBD, 0x0CBD, *b"Lo"),
+    (0x0CBE, 0x0CBE, *b"Mc"), (0x0CBF, 0x0CBF, *b"Mn"), (0x0CC0, 0x0CC4, *b"Mc"),
+    (0x0CC6, 0x0CC6, *b"Mn"), (0x0CC7, 0x0CC8, *b"Mc"), (0x0CCA, 0x0CCB, *b"Mc"),
+    (0x0CCC, 0x0CCD, *b"Mn"), (0x0CD5, 0x0CD6, *b"Mc"), (0x0CDD, 0x0CDE, *b"Lo"),
+    (0x0CE0, 0x0CE1, *b"Lo"), (0x0CE2, 0x0CE3, *b"Mn"), (0x0CE6, 0x0CEF, *b"Nd"),
+    (0x0CF1, 0x0CF2, *b"Lo"), (0x0D00, 0x0D01, *b"Mn"), (0x0D02, 0x0D03, *b"Mc"),
+    (0x0D04, 0x0D0C, *b"Lo"), (0x0D0E, 0x0D10, *b"Lo"), (0x0D12, 0x0D3A, *b"Lo"),
+    (0x0D3B, 0x0D3C, *b"Mn"), (0x0D3D, 0x0D3D, *b"Lo"), (0x0D3E, 0x0D40, *b"Mc"),
+    (0x0D41, 0x0D44, *b"Mn"), (0x0D46, 0x0D48, *b"Mc"), (0x0D4A, 0x0D4C, *b"Mc"),
+    (0x0D4D, 0x0D4D, *b"Mn"), (0x0D4E, 0x0D4E, *b"Lo"), (0x0D4F, 0x0D4F, *b"So"),
+    (0x0D54, 0x0D56, *b"Lo"), (0x0D57, 0x0D57, *b"Mc"), (0x0D58, 0x0D5E, *b"No"),
+    (0x0D5F, 0x0D61, *b"Lo"), (0x0D62, 0x0D63, *b"Mn"), (0x0D66, 0x0D6F, *b"Nd"),
+    (0x0D70, 0x0D78, *b"No"), (0x0D79, 0x0D79, *b"So"), (0x0D7A, 0x0D7F, *b"Lo"),
+    (0x0D81, 0x0D81, *b"Mn"), (0x0D82, 0x0D83, *b"Mc"), (0x0D85, 0x0D96, *b"Lo"),
+    (0x0D9A, 0x0DB1, *b"Lo"), (0x0DB3, 0x0DBB, *b"Lo"), (0x0DBD, 0x0DBD, *b"Lo"),
+    (0x0DC0, 0x0DC6, *b"Lo"), (0x0DCA, 0x0DCA, *b"Mn"), (0x0DCF, 0x0DD1, *b"Mc"),
+    (0x0DD2, 0x0DD4, *b"Mn"), (0x0DD6, 0x0DD6, *b"Mn"), (0x0DD8, 0x0DDF, *b"Mc"),
+    (0x0DE6, 0x0DEF, *b"Nd"), (0x0DF2, 0x0DF3, *b"Mc"), (0x0DF4, 0x0DF4, *b"Po"),
+    (0x0E01, 0x0E30, *b"Lo"), (0x0E31, 0x0E31, *b"Mn"), (0x0E32, 0x0E33, *b"Lo"),
+    (0x0E34, 0x0E3A, *b"Mn"), (0x0E3F, 0x0E3F, *b"Sc"), (0x0E40, 0x0E45, *b"Lo"),
+    (0x0E46, 0x0E46, *b"Lm"), (0x0E47, 0x0E4E, *b"Mn"), (0x0E4F, 0x0E4F, *b"Po"),
+    (0x0E50, 0x0E59, *b"Nd"), (0x0E5A, 0x0E5B, *b"Po"), (0x0E81, 0x0E82, *b"Lo"),
+    (0x0E84, 0x0E84, *b"Lo"), (0x0E86, 0x0E8A, *b"Lo"), (0x0E8C, 0x0EA3, *b"Lo"),
+    (0x0EA5, 0x0EA5, *b"Lo"), (0x0EA7, 0x0EB0, *b"Lo"), (0x0EB1, 0x0EB1, *b"Mn"),
+    (0x0EB2, 0x0EB3, *b"Lo"), (0x0EB4, 0x0EBC, *b"Mn"), (0x0EBD, 0x0EBD, *b"Lo"),
+    (0x0EC0, 0x0EC4, *b"Lo"), (0x0EC6, 0x0EC6, *b"Lm"), (0x0EC8, 0x0ECD, *b"Mn"),
+    (0x0ED0, 0x0ED9, *b"Nd"), (0x0EDC, 0x0EDF, *b"Lo"), (0x0F00, 0x0F00, *b"Lo"),
+    (0x0F01, 0x0F03, *b"So"), (0x0F04, 0x0F12, *b"Po"), (0x0F13, 0x0F13, *b"So"),
+    (0x0F14, 0x0F14, *b"Po"), (0x0F15, 0x0F17, *b"So"), (0x0F18, 0x0F19, *b"Mn"),
+    (0x0F1A, 0x0F1F, *b"So"), (0x0F20, 0x0F29, *b"Nd"), (0x0F2A, 0x0F33, *b"No"),
+    (0x0F34, 0x0F34, *b"So"), (0x0F35, 0x0F35, *b"Mn"), (0x0F36, 0x0F36, *b"So"),
+    (0x0F37, 0x0F37, *b"Mn"), (0x0F38, 0x0F38, *b"So"), (0x0F39, 0x0F39, *b"Mn"),
+    (0x0F3A, 0x0F3A, *b"Ps"), (0x0F3B, 0x0F3B, *b"Pe"), (0x0F3C, 0x0F3C, *b"Ps"),
+    (0x0F3D, 0x0F3D, *b"Pe"), (0x0F3E, 0x0F3F, *b"Mc"), (0x0F40, 0x0F47, *b"Lo"),
+    (0x0F49, 0x0F6C, *b"Lo"), (0x0F71, 0x0F7E, *b"Mn"), (0x0F7F, 0x0F7F, *b"Mc"),
+    (0x0F80, 0x0F84, *b"Mn"), (0x0F85, 0x0F85, *b"Po"), (0x0F86, 0x0F87, *b"Mn"),
+    (0x0F88, 0x0F8C, *b"Lo"), (0x0F8D, 0x0F97, *b"Mn"), (0x0F99, 0x0FBC, *b"Mn"),
+    (0x0FBE, 0x0FC5, *b"So"), (0x0FC6, 0x0FC6, *b"Mn"), (0x0FC7, 0x0FCC, *b"So"),
+    (0x0FCE, 0x0FCF, *b"So"), (0x0FD0, 0x0FD4, *b"Po"), (0x0FD5, 0x0FD8, *b"So"),
+    (0x0FD9, 0x0FDA, *b"Po"), (0x1000, 0x102A, *b"Lo"), (0x102B, 0x102C, *b"Mc"),
+    (0x102D, 0x1030, *b"Mn"), (0x1031, 0x1031, *b"Mc"), (0x1032, 0x1037, *b"Mn"),
+    (0x1038, 0x1038, *b"Mc"), (0x1039, 0x103A, *b"Mn"), (0x103B, 0x103C, *b"Mc"),
+    (0x103D, 0x103E, *b"Mn"), (0x103F, 0x103F, *b"Lo"), (0x1040, 0x1049, *b"Nd"),
+    (0x104A, 0x104F, *b"Po"), (0x1050, 0x1055, *b"Lo"), (0x1056, 0x1057, *b"Mc"),
+    (0x1058, 0x1059, *b"Mn"), (0x105A, 0x105D, *b"Lo"), (0x105E, 0x1060, *b"Mn"),
+    (0x1061, 0x1061, *b"Lo"), (0x1062, 0x1064, *b"Mc"), (0x1065, 0x1066, *b"Lo"),
+    (0x1067, 0x106D, *b"Mc"), (0x106E, 0x1070, *b"Lo"), (0x1071, 0x1074, *b"Mn"),
+    (0x1075, 0x1081, *b"Lo"), (0x1082, 0x1082, *b"Mn"), (0x1083, 0x1084, *b"Mc"),
+    (0x1085, 0x1086, *b"Mn"), (0x1087, 0x108C, *b"Mc"), (0x108D, 0x108D, *b"Mn"),
+    (0x108E, 0x108E, *b"Lo"), (0x108F, 0x108F, *b"Mc"), (0x1090, 0x1099, *b"Nd"),
+    (0x109A, 0x109C, *b"Mc"), (0x109D, 0x109D, *b"Mn"), (0x109E, 0x109F, *b"So"),
+    (0x10A0, 0x10C5, *b"Lu"), (0x10C7, 0x10C7, *b"Lu"), (0x10CD, 0x10CD, *b"Lu"),
+    (0x10D0, 0x10FA, *b"Ll"), (0x10FB, 0x10FB, *b"Po"), (0x10FC, 0x10FC, *b"Lm"),
+    (0x10FD, 0x10FF, *b"Ll"), (0x1100, 0x1248, *b"Lo"), (0x124A, 0x124D, *b"Lo"),
+    (0x1250, 0x1256, *b"Lo"), (0x1258, 0x1258, *b"Lo"), (0x125A, 0x125D, *b"Lo"),
+    (0x1260, 0x1288, *b"Lo"), (0x128A, 0x128D, *b"Lo"), (0x1290, 0x12B0, *b"Lo"),
+    (0x12B2, 0x12B5, *b"Lo"), (0x12B8, 0x12BE, *b"Lo"), (0x12C0, 0x12C0, *b"Lo"),
+    (0x12C2, 0x12C5, *b"Lo"), (0x12C8, 0x12D6, *b"Lo"), (0x12D8, 0x1310, *b"Lo"),
+    (0x1312, 0x1315, *b"Lo"), (0x1318, 0x135A, *b"Lo"), (0x135D, 0x135F, *b"Mn"),
+    (0x1360, 0x1368, *b"Po"), (0x1369, 0x137C, *b"No"), (0x1380, 0x138F, *b"Lo"),
+    (0x1390, 0x1399, *b"So"), (0x13A0, 0x13F5, *b"Lu"), (0x13F8, 0x13FD, *b"Ll"),
+    (0x1400, 0x1400, *b"Pd"), (0x1401, 0x166C, *b"Lo"), (0x166D, 0x166D, *b"So"),
+    (0x166E, 0x166E, *b"Po"), (0x166F, 0x167F, *b"Lo"), (0x1680, 0x1680, *b"Zs"),
+    (0x1681, 0x169A, *b"Lo"), (0x169B, 0x169B, *b"Ps"), (0x169C, 0x169C, *b"Pe"),
+    (0x16A0, 0x16EA, *b"Lo"), (0x16EB, 0x16ED, *b"Po"), (0x16EE, 0x16F0, *b"Nl"),
+    (0x16F1, 0x16F8, *b"Lo"), (0x1700, 0x1711, *b"Lo"), (0x1712, 0x1714, *b"Mn"),
+    (0x1715, 0x1715, *b"Mc"), (0x171F, 0x1731, *b"Lo"), (0x1732, 0x1733, *b"Mn"),
+    (0x1734, 0x1734, *b"Mc"), (0x1735, 0x1736, *b"Po"), (0x1740, 0x1751, *b"Lo"),
+    (0x1752, 0x1753, *b"Mn"), (0x1760, 0x176C, *b"Lo"), (0x176E, 0x1770, *b"Lo"),
+    (0x1772, 0x1773, *b"Mn"), (0x1780, 0x17B3, *b"Lo"), (0x17B4, 0x17B5, *b"Mn"),
+    (0x17B6, 0x17B6, *b"Mc"), (0x17B7, 0x17BD, *b"Mn"), (0x17BE, 0x17C5, *b"Mc"),
+    (0x17C6, 0x17C6, *b"Mn"), (0x17C7, 0x17C8, *b"Mc"), (0x17C9, 0x17D3, *b"Mn"),
+    (0x17D4, 0x17D6, *b"Po"), (0x17D7, 0x17D7, *b"Lm"), (0x17D8, 0x17DA, *b"Po"),
+    (0x17DB, 0x17DB, *b"Sc"), (0x17DC, 0x17DC, *b"Lo"), (0x17DD, 0x17DD, *b"Mn"),
+    (0x17E0, 0x17E9, *b"Nd"), (0x17F0, 0x17F9, *b"No"), (0x1800, 0x1805, *b"Po"),
+    (0x1806, 0x1806, *b"Pd"), (0x1807, 0x180A, *b"Po"), (0x180B, 0x180D, *b"Mn"),
+    (0x180E, 0x180E, *b"Cf"), (0x180F, 0x180F, *b"Mn"), (0x1810, 0x1819, *b"Nd"),
+    (0x1820, 0x1842, *b"Lo"), (0x1843, 0x1843, *b"Lm"), (0x1844, 0x1878, *b"Lo"),
+    (0x1880, 0x1884, *b"Lo"), (0x1885, 0x1886, *b"Mn"), (0x1887, 0x18A8, *b"Lo"),
+    (0x18A9, 0x18A9, *b"Mn"), (0x18AA, 0x18AA, *b"Lo"), (0x18B0, 0x18F5, *b"Lo"),
+    (0x1900, 0x191E, *b"Lo"), (0x1920, 0x1922, *b"Mn"), (0x1923, 0x1926, *b"Mc"),
+    (0x1927, 0x1928, *b"Mn"), (0x1929, 0x192B, *b"Mc"), (0x1930, 0x1931, *b"Mc"),
+    (0x1932, 0x1932, *b"Mn"), (0x1933, 0x1938, *b"Mc"), (0x1939, 0x193B, *b"Mn"),
+    (0x1940, 0x1940, *b"So"), (0x1944, 0x1945, *b"Po"), (0x1946, 0x194F, *b"Nd"),
+    (0x1950, 0x196D, *b"Lo"), (0x1970, 0x1974, *b"Lo"), (0x1980, 0x19AB, *b"Lo"),
+    (0x19B0, 0x19C9, *b"Lo"), (0x19D0, 0x19D9, *b"Nd"), (0x19DA, 0x19DA, *b"No"),
+    (0x19DE, 0x19FF, *b"So"), (0x1A00, 0x1A16, *b"Lo"), (0x1A17, 0x1A18, *b"Mn"),
+    (0x1A19, 0x1A1A, *b"Mc"), (0x1A1B, 0x1A1B, *b"Mn"), (0x1A1E, 0x1A1F, *b"Po"),
+    (0x1A20, 0x1A54, *b"Lo"), (0x1A55, 0x1A55, *b"Mc"), (0x1A56, 0x1A56, *b"Mn"),
+    (0x1A57, 0x1A57, *b"Mc"), (0x1A58, 0x1A5E, *b"Mn"), (0x1A60, 0x1A60, *b"Mn"),
+    (0x1A61, 0x1A61, *b"Mc"), (0x1A62, 0x1A62, *b"Mn"), (0x1A63, 0x1A64, *b"Mc"),
+    (0x1A65, 0x1A6C, *b"Mn"), (0x1A6D, 0x1A72, *b"Mc"), (0x1A73, 0x1A7C, *b"Mn"),
+    (0x1A7F, 0x1A7F, *b"Mn"), (0x1A80, 0x1A89, *b"Nd"), (0x1A90, 0x1A99, *b"Nd"),
+    (0x1AA0, 0x1AA6, *b"Po"), (0x1AA7, 0x1AA7, *b"Lm"), (0x1AA8, 0x1AAD, *b"Po"),
+    (0x1AB0, 0x1ABD, *b"Mn"), (0x1ABE, 0x1ABE, *b"Me"), (0x1ABF, 0x1ACE, *b"Mn"),
+    (0x1B00, 0x1B03, *b"Mn"), (0x1B04, 0x1B04, *b"Mc"), (0x1B05, 0x1B33, *b"Lo"),
+    (0x1B34, 0x1B34, *b"Mn"), (0x1B35, 0x1B35, *b"Mc"), (0x1B36, 0x1B3A, *b"Mn"),
+    (0x1B3B, 0x1B3B, *b"Mc"), (0x1B3C, 0x1B3C, *b"Mn"), (0x1B3D, 0x1B41, *b"Mc"),
+    (0x1B42, 0x1B42, *b"Mn"), (0x1B43, 0x1B44, *b"Mc"), (0x1B45, 0x1B4C, *b"Lo"),
+    (0x1B50, 0x1B59, *b"Nd"), (0x1B5A, 0x1B60, *b"Po"), (0x1B61, 0x1B6A, *b"So"),
+    (0x1B6B, 0x1B73, *b"Mn"), (0x1B74, 0x1B7C, *b"So"), (0x1B7D, 0x1B7E, *b"Po"),
+    (0x1B80, 0x1B81, *b"Mn"), (0x1B82, 0x1B82, *b"Mc"), (0x1B83, 0x1BA0, *b"Lo"),
+    (0x1BA1, 0x1BA1, *b"Mc"), (0x1BA2, 0x1BA5, *b"Mn"), (0x1BA6, 0x1BA7, *b"Mc"),
+    (0x1BA8, 0x1BA9, *b"Mn"), (0x1BAA, 0x1BAA, *b"Mc"), (0x1BAB, 0x1BAD, *b"Mn"),
+    (0x1BAE, 0x1BAF, *b"Lo"), (0x1BB0, 0x1BB9, *b"Nd"), (0x1BBA, 0x1BE5, *b"Lo"),
+    (0x1BE6, 0x1BE6, *b"Mn"), (0x1BE7, 0x1BE7, *b"Mc"), (0x1BE8, 0x1BE9, *b"Mn"),
+    (0x1BEA, 0x1BEC, *b"Mc"), (0x1BED, 0x1BED, *b"Mn"), (0x1BEE, 0x1BEE, *b"Mc"),
+    (0x1BEF, 0x1BF1, *b"Mn"), (0x1BF2, 0x1BF3, *b"Mc"), (0x1BFC, 0x1BFF, *b"Po"),
+    (0x1C00, 0x1C23, *b"Lo"), (0x1C24, 0x1C2B, *b"Mc"), (0x1C2C, 0x1C33, *b"Mn"),
+    (0x1C34, 0x1C35, *b"Mc"), (0x1C36, 0x1C37, *b"Mn"), (0x1C3B, 0x1C3F, *b"Po"),
+    (0x1C40, 0x1C49, *b"Nd"), (0x1C4D, 0x1C4F, *b"Lo"), (0x1C50, 0x1C59, *b"Nd"),
+    (0x1C5A, 0x1C77, *b"Lo"), (0x1C78, 0x1C7D, *b"Lm"), (0x1C7E, 0x1C7F, *b"Po"),
+    (0x1C80, 0x1C88, *b"Ll"), (0x1C90, 0x1CBA, *b"Lu"), (0x1CBD, 0x1CBF, *b"Lu"),
+    (0x1CC0, 0x1CC7, *b"Po"), (0x1CD0, 0x1CD2, *b"Mn"), (0x1CD3, 0x1CD3, *b"Po"),
+    (0x1CD4, 0x1CE0, *b"Mn"), (0x1CE1, 0x1CE1, *b"Mc"), (0x1CE2, 0x1CE8, *b"Mn"),
+    (0x1CE9, 0x1CEC, *b"Lo"), (0x1CED, 0x1CED, *b"Mn"), (0x1CEE, 0x1CF3, *b"Lo"),
+    (0x1CF4, 0x1CF4, *b"Mn"), (0x1CF5, 0x1CF6, *b"Lo"), (0x1CF7, 0x1CF7, *b"Mc"),
+    (0x1CF8, 0x1CF9, *b"Mn"), (0x1CFA, 0x1CFA, *b"Lo"), (0x1D00, 0x1D2B, *b"Ll"),
+    (0x1D2C, 0x1D6A, *b"Lm"), (0x1D6B, 0x1D77, *b"Ll"), (0x1D78, 0x1D78, *b"Lm"),
+    (0x1D79, 0x1D9A, *b"Ll"), (0x1D9B, 0x1DBF, *b"Lm"), (0x1DC0, 0x1DFF, *b"Mn"),
+    (0x1E00, 0x1E00, *b"Lu"), (0x1E01, 0x1E01, *b"Ll"), (0x1E02, 0x1E02, *b"Lu"),
+    (0x1E03, 0x1E03, *b"Ll"), (0x1E04, 0x1E04, *b"Lu"), (0x1E05, 0x1E05, *b"Ll"),
+    (0x1E06, 0x1E06, *b"Lu"), (0x1E07, 0x1E07, *b"Ll"), (0x1E08, 0x1E08, *b"Lu"),
+    (0x1E09, 0x1E09, *b"Ll"), (0x1E0A, 0x1E0A, *b"Lu"), (0x1E0B, 0x1E0B, *b"Ll"),
+    (0x1E0C, 0x1E0C, *b"Lu"), (0x1E0D, 0x1E0D, *b"Ll"), (0x1E0E, 0x1E0E, *b"Lu"),
+    (0x1E0F, 0x1E0F, *b"Ll"), (0x1E10, 0x1E10, *b"Lu"), (0x1E11, 0x1E11, *b"Ll"),
+    (0x1E12, 0x1E12, *b"Lu"), (0x1E13, 0x1E13, *b"Ll"), (0x1E14, 0x1E14, *b"Lu"),
+    (0x1E15, 0x1E15, *b"Ll"), (0x1E16, 0x1E16, *b"Lu"), (0x1E17, 0x1E17, *b"Ll"),
+    (0x1E18, 0x1E18, *b"Lu"), (0x1E19, 0x1E19, *b"Ll"), (0x1E1A, 0x1E1A, *b"Lu"),
+    (0x1E1B, 0x1E1B, *b"Ll"), (0x1E1C, 0x1E1C, *b"Lu"), (0x1E1D, 0x1E1D, *b"Ll"),
+    (0x1E1E, 0x1E1E, *b"Lu"), (0x1E1F, 0x1E1F, *b"Ll"), (0x1E20, 0x1E20, *b"Lu"),
+    (0x1E21, 0x1E21, *b"Ll"), (0x1E22, 0x1E22, *b"Lu"), (0x1E23, 0x1E23, *b"Ll"),
+    (0x1E24, 0x1E24, *b"Lu"), (0x1E25, 0x1E25, *b"Ll"), (0x1E26, 0x1E26, *b"Lu"),
+    (0x1E27, 0x1E27, *b"Ll"), (0x1E28, 0x1E28, *b"Lu"), (0x1E29, 0x1E29, *b"Ll"),
+    (0x1E2A, 0x1E2A, *b"Lu"), (0x1E2B, 0x1E2B, *b"Ll"), (0x1E2C, 0x1E2C, *b"Lu"),
+    (0x1E2D, 0x1E2D, *b"Ll"), (0x1E2E, 0x1E2E, *b"Lu"), (0x1E2F, 0x1E2F, *b"Ll"),
+    (0x1E30, 0x1E30, *b"Lu"), (0x1E31, 0x1E31, *b"Ll"), (0x1E32, 0x1E32, *b"Lu"),
+    (0x1E33, 0x1E33, *b"Ll"), (0x1E34, 0x1E34, *b"Lu"), (0x1E35, 0x1E35, *b"Ll"),
+    (0x1E36, 0x1E36, *b"Lu"), (0x1E37, 0x1E37, *b"Ll"), (0x1E38, 0x1E38, *b"Lu"),
+    (0x1E39, 0x1E39, *b"Ll"), (0x1E3A, 0x1E3A, *b"Lu"), (0x1E3B, 0x1E3B, *b"Ll"),
+    (0x1E3C, 0x1E3C, *b"Lu"), (0x1E3D, 0x1E3D, *b"Ll"), (0x1E3E, 0x1E3E, *b"Lu"),
+    (0x1E3F, 0x1E3F, *b"Ll"), (0x1E40, 0x1E40, *b"Lu"), (0x1E41, 0x1E41, *b"Ll"),
+    (0x1E42, 0x1E42, *b"Lu"), (0x1E43, 0x1E43, *b"Ll"), (0x1E44, 0x1E44, *b"Lu"),
+    (0x1E45, 0x1E45, *b"Ll"), (0x1E46, 0x1E46, *b"Lu"), (0x1E47, 0x1E47, *b"Ll"),
+    (0x1E48, 0x1E48, *b"Lu"), (0x1E49, 0x1E49, *b"Ll"), (0x1E4A, 0x1E4A, *b"Lu"),
+    (0x1E4B, 0x1E4B, *b"Ll"), (0x1E4C, 0x1E4C, *b"Lu"), (0x1E4D, 0x1E4D, *b"Ll"),
+    (0x1E4E, 0x1E4E, *b"Lu"), (0x1E4F, 0x1E4F, *b"Ll"), (0x1E50, 0x1E50, *b"Lu"),
+    (0x1E51, 0x1E51, *b"Ll"), (0x1E52, 0x1E52, *b"Lu"), (0x1E53, 0x1E53, *b"Ll"),
+    (0x1E54, 0x1E54, *b"Lu"), (0x1E55, 0x1E55, *b"Ll"), (0x1E56, 0x1E56, *b"Lu"),
+    (0x1E57, 0x1E57, *b"Ll"), (0x1E58, 0x1E58, *b"Lu"), (0x1E59, 0x1E59, *b"Ll"),
+    (0x1E5A, 0x1E5A, *b"Lu"), (0x1E5B, 0x1E5B, *b"Ll"), (0x1E5C, 0x1E5C, *b"Lu"),
+    (0x1E5D, 0x1E5D, *b"Ll"), (0x1E5E, 0x1E5E, *b"Lu"), (0x1E5F, 0x1E5F, *b"Ll"),
+    (0x1E60, 0x1E60, *b"Lu"), (0x1E61, 0x1E61, *b"Ll"), (0x1E62, 0x1E62, *b"Lu"),
+    (0x1E63, 0x1E63, *b"Ll"), (0x1E64, 0x1E64, *b"Lu"), (0x1E65, 0x1E65, *b"Ll"),
+    (0x1E66, 0x1E66, *b"Lu"), (0x1E67, 0x1E67, *b"Ll"), (0x1E68, 0x1E68, *b"Lu"),
+    (0x1E69, 0x1E69, *b"Ll"), (0x1E6A, 0x1E6A, *b"Lu"), (0x1E6B, 0x1E6B, *b"Ll"),
+    (0x1E6C, 0x1E6C, *b"Lu"), (0x1E6D, 0x1E6D, *b"Ll"), (0x1E6E, 0x1E6E, *b"Lu"),
+    (0x1E6F, 0x1E6F, *b"Ll"), (0x1E70, 0x1E70, *b"Lu"), (0x1E71, 0x1E71, *b"Ll"),
+    (0x1E72, 0x1E72, *b"Lu"), (0x1E73, 0x1E73, *b"Ll"), (0x1E74, 0x1E74, *b"Lu"),
+    (0x1E75, 0x1E75, *b"Ll"), (0x1E76, 0x1E76, *b"Lu"), (0x1E77, 0x1E77, *b"Ll"),
+    (0x1E78, 0x1E78, *b"Lu"), (0x1E79, 0x1E79, *b"Ll"), (0x1E7A, 0x1E7A, *b"Lu"),
+    (0x1E7B, 0x1E7B, *b"Ll"), (0x1E7C, 0x1E7C, *b"Lu"), (0x1E7D, 0x1E7D, *b"Ll"),
+    (0x1E7E, 0x1E7E, *b"Lu"), (0x1E7F, 0x1E7F, *b"Ll"), (0x1E80, 0x1E80, *b"Lu"),
+    (0x1E81, 0x1E81, *b"Ll"), (0x1E82, 0x1E82, *b"Lu"), (0x1E83, 0x1E83, *b"Ll"),
+    (0x1E84, 0x1E84, *b"Lu"), (0x1E85, 0x1E85, *b"Ll"), (0x1E86, 0x1E86, *b"Lu"),
+    (0x1E87, 0x1E87, *b"Ll"), (0x1E88, 0x1E88, *b"Lu"), (0x1E89, 0x1E89, *b"Ll"),
+    (0x1E8A, 0x1E8A, *b"Lu"), (0x1E8B, 0x1E8B, *b"Ll"), (0x1E8C, 0x1E8C, *b"Lu"),
+    (0x1E8D, 0x1E8D, *b"Ll"), (0x1E8E, 0x1E8E, *b"Lu"), (0x1E8F, 0x1E8F, *b"Ll"),
+    (0x1E90, 0x1E90, *b"Lu"), (0x1E91, 0x1E91, *b"Ll"), (0x1E92, 0x1E92, *b"Lu"),
+    (0x1E93, 0x1E93, *b"Ll"), (0x1E94, 0x1E94, *b"Lu"), (0x1E95, 0x1E9D, *b"Ll"),
+    (0x1E9E, 0x1E9E, *b"Lu"), (0x1E9F, 0x1E9F, *b"Ll"), (0x1EA0, 0x1EA0, *b"Lu"),
+    (0x1EA1, 0x1EA1, *b"Ll"), (0x1EA2, 0x1EA2, *b"Lu"), (0x1EA3, 0x1EA3, *b"Ll"),
+    (0x1EA4, 0x1EA4, *b"Lu"), (0x1EA5, 0x1EA5, *b"Ll"), (0x1EA6, 0x1EA6, *b"Lu"),
+    (0x1EA7, 0x1EA7, *b"Ll"), (0x1EA8, 0x1EA8, *b"Lu"), (0x1EA9, 0x1EA9, *b"Ll"),
+    (0x1EAA, 0x1EAA, *b"Lu"), (0x1EAB, 0x1EAB, *b"Ll"), (0x1EAC, 0x1EAC, *b"Lu"),
+    (0x1EAD, 0x1EAD, *b"Ll"), (0x1EAE, 0x1EAE, *b"Lu"), (0x1EAF, 0x1EAF, *b"Ll"),
+    (0x1EB0, 0x1EB0, *b"Lu"), (0x1EB1, 0x1EB1, *b"Ll"), (0x1EB2, 0x1EB2, *b"Lu"),
+    (0x1EB3, 0x1EB3, *b"Ll"), (0x1EB4, 0x1EB4, *b"Lu"), (0x1EB5, 0x1EB5, *b"Ll"),
+    (0x1EB6, 0x1EB6, *b"Lu"), (0x1EB7, 0x1EB7, *b"Ll"), (0x1EB8, 0x1EB8, *b"Lu"),
+    (0x1EB9, 0x1EB9, *b"Ll"), (0x1EBA, 0x1EBA, *b"Lu"), (0x1EBB, 0x1EBB, *b"Ll"),
+    (0x1EBC, 0x1EBC, *b"Lu"), (0x1EBD, 0x1EBD, *b"Ll"), (0x1EBE, 0x1EBE, *b"Lu"),
+    (0x1EBF, 0x1EBF, *b"Ll"), (0x1EC0, 0x1EC0, *b"Lu"), (0x1EC1, 0x1EC1, *b"Ll"),
+    (0x1EC2, 0x1EC2, *b"Lu"), (0x1EC3, 0x1EC3, *b"Ll"), (0x1EC4, 0x1EC4, *b"Lu"),
+    (0x1EC5, 0x1EC5, *b"Ll"), (0x1EC6, 0x1EC6, *b"Lu"), (0x1EC7, 0x1EC7, *b"Ll"),
+    (0x1EC8, 0x1EC8, *b"Lu"), (0x1EC9, 0x1EC9, *b"Ll"), (0x1ECA, 0x1ECA, *b"Lu"),
+    (0x1ECB, 0x1ECB, *b"Ll"), (0x1ECC, 0x1ECC, *b"Lu"), (0x1ECD, 0x1ECD, *b"Ll"),
+    (0x1ECE, 0x1ECE, *b"Lu"), (0x1ECF, 0x1ECF, *b"Ll"), (0x1ED0, 0x1ED0, *b"Lu"),
+    (0x1ED1, 0x1ED1, *b"Ll"), (0x1ED2, 0x1ED2, *b"Lu"), (0x1ED3, 0x1ED3, *b"Ll"),
+    (0x1ED4, 0x1ED4, *b"Lu"), (0x1ED5, 0x1ED5, *b"Ll"), (0x1ED6, 0x1ED6, *b"Lu"),
+    (0x1ED7, 0x1ED7, *b"Ll"), (0x1ED8, 0x1ED8, *b"Lu"), (0x1ED9, 0x1ED9, *b"Ll"),
+    (0x1EDA, 0x1EDA, *b"Lu"), (0x1EDB, 0x1EDB, *b"Ll"), (0x1EDC, 0x1EDC, *b"Lu"),
+    (0x1EDD, 0x1EDD, *b"Ll"), (0x1EDE, 0x1EDE, *b"Lu"), (0x1EDF, 0x1EDF, *b"Ll"),
+    (0x1EE0, 0x1EE0, *b"Lu"), (0x1EE1, 0x1EE1, *b"Ll"), (0x1EE2, 0x1EE2, *b"Lu"),
+    (0x1EE3, 0x1EE3, *b"Ll"), (0x1EE4, 0x1EE4, *b"Lu"), (0x1EE5, 0x1EE5, *b"Ll"),
+    (0x1EE6, 0x1EE6, *b"Lu"), (0x1EE7, 0x1EE7, *b"Ll"), (0x1EE8, 0x1EE8, *b"Lu"),
+    (0x1EE9, 0x1EE9, *b"Ll"), (0x1EEA, 0x1EEA, *b"Lu"), (0x1EEB, 0x1EEB, *b"Ll"),
+    (0x1EEC, 0x1EEC, *b"Lu"), (0x1EED, 0x1EED, *b"Ll"), (0x1EEE, 0x1EEE, *b"Lu"),
+    (0x1EEF, 0x1EEF, *b"Ll"), (0x1EF0, 0x1EF0, *b"Lu"), (0x1EF1, 0x1EF1, *b"Ll"),
+    (0x1EF2, 0x1EF2, *b"Lu"), (0x1EF3, 0x1EF3, *b"Ll"), (0x1EF4, 0x1EF4, *b"Lu"),
+    (0x1EF5, 0x1EF5, *b"Ll"), (0x1EF6, 0x1EF6, *b"Lu"), (0x1EF7, 0x1EF7, *b"Ll"),
+    (0x1EF8, 0x1EF8, *b"Lu"), (0x1EF9, 0x1EF9, *b"Ll"), (0x1EFA, 0x1EFA, *b"Lu"),
+    (0x1EFB, 0x1EFB, *b"Ll"), (0x1EFC, 0x1EFC, *b"Lu"), (0x1EFD, 0x1EFD, *b"Ll"),
+    (0x1EFE, 0x1EFE, *b"Lu"), (0x1EFF, 0x1F07, *b"Ll"), (0x1F08, 0x1F0F, *b"Lu"),
+    (0x1F10, 0x1F15, *b"Ll"), (0x1F18, 0x1F1D, *b"Lu"), (0x1F20, 0x1F27, *b"Ll"),
+    (0x1F28, 0x1F2F, *b"Lu"), (0x1F30, 0x1F37, *b"Ll"), (0x1F38, 0x1F3F, *b"Lu"),
+    (0x1F40, 0x1F45, *b"Ll"), (0x1F48, 0x1F4D, *b"Lu"), (0x1F50, 0x1F57, *b"Ll"),
+    (0x1F59, 0x1F59, *b"Lu"), (0x1F5B, 0x1F5B, *b"Lu"), (0x1F5D, 0x1F5D, *b"Lu"),
+    (0x1F5F, 0x1F5F, *b"Lu"), (0x1F60, 0x1F67, *b"Ll"), (0x1F68, 0x1F6F, *b"Lu"),
+    (0x1F70, 0x1F7D, *b"Ll"), (0x1F80, 0x1F87, *b"Ll"), (0x1F88, 0x1F8F, *b"Lt"),
+    (0x1F90, 0x1F97, *b"Ll"), (0x1F98, 0x1F9F, *b"Lt"), (0x1FA0, 0x1FA7, *b"Ll"),
+    (0x1FA8, 0x1FAF, *b"Lt"), (0x1FB0, 0x1FB4, *b"Ll"), (0x1FB6, 0x1FB7, *b"Ll"),
+    (0x1FB8, 0x1FBB, *b"Lu"), (0x1FBC, 0x1FBC, *b"Lt"), (0x1FBD, 0x1FBD, *b"Sk"),
+    (0x1FBE, 0x1FBE, *b"Ll"), (0x1FBF, 0x1FC1, *b"Sk"), (0x1FC2, 0x1FC4, *b"Ll"),
+    (0x1FC6, 0x1FC7, *b"Ll"), (0x1FC8, 0x1FCB, *b"Lu"), (0x1FCC, 0x1FCC, *b"Lt"),
+    (0x1FCD, 0x1FCF, *b"Sk"), (0x1FD0, 0x1FD3, *b"Ll"), (0x1FD6, 0x1FD7, *b"Ll"),
+    (0x1FD8, 0x1FDB, *b"Lu"), (0x1FDD, 0x1FDF, *b"Sk"), (0x1FE0, 0x1FE7, *b"Ll"),
+    (0x1FE8, 0x1FEC, *b"Lu"), (0x1FED, 0x1FEF, *b"Sk"), (0x1FF2, 0x1FF4, *b"Ll"),
+    (0x1FF6, 0x1FF7, *b"Ll"), (0x1FF8, 0x1FFB, *b"Lu"), (0x1FFC, 0x1FFC, *b"Lt"),
+    (0x1FFD, 0x1FFE, *b"Sk"), (0x2000, 0x200A, *b"Zs"), (0x200B, 0x200F, *b"Cf"),
+    (0x2010, 0x2015, *b"Pd"), (0x2016, 0x2017, *b"Po"), (0x2018, 0x2018, *b"Pi"),
+    (0x2019, 0x2019, *b"Pf"), (0x201A, 0x201A, *b"Ps"), (0x201B, 0x201C, *b"Pi"),
+    (0x201D, 0x201D, *b"Pf"), (0x201E, 0x201E, *b"Ps"), (0x201F, 0x201F, *b"Pi"),
+    (0x2020, 0x2027, *b"Po"), (0x2028, 0x2028, *b"Zl"), (0x2029, 0x2029, *b"Zp"),
+    (0x202A, 0x202E, *b"Cf"), (0x202F, 0x202F, *b"Zs"), (0x2030, 0x2038, *b"Po"),
+    (0x2039, 0x2039, *b"Pi"), (0x203A, 0x203A, *b"Pf"), (0x203B, 0x203E, *b"Po"),
+    (0x203F, 0x2040, *b"Pc"), (0x2041, 0x2043, *b"Po"), (0x2044, 0x2044, *b"Sm"),
+    (0x2045, 0x2045, *b"Ps"), (0x2046, 0x2046, *b"Pe"), (0x2047, 0x2051, *b"Po"),
+    (0x2052, 0x2052, *b"Sm"), (0x2053, 0x2053, *b"Po"), (0x2054, 0x2054, *b"Pc"),
+    (0x2055, 0x205E, *b"Po"), (0x205F, 0x205F, *b"Zs"), (0x2060, 0x2064, *b"Cf"),
+    (0x2066, 0x206F, *b"Cf"), (0x2070, 0x2070, *b"No"), (0x2071, 0x2071, *b"Lm"),
+    (0x2074, 0x2079, *b"No"), (0x207A, 0x207C, *b"Sm"), (0x207D, 0x207D, *b"Ps"),
+    (0x207E, 0x207E, *b"Pe"), (0x207F, 0x207F, *b"Lm"), (0x2080, 0x2089, *b"No"),
+    (0x208A, 0x208C, *b"Sm"), (0x208D, 0x208D, *b"Ps"), (0x208E, 0x208E, *b"Pe"),
+    (0x2090, 0x209C, *b"Lm"), (0x20A0, 0x20C0, *b"Sc"), (0x20D0, 0x20DC, *b"Mn"),
+    (0x20DD, 0x20E0, *b"Me"), (0x20E1, 0x20E1, *b"Mn"), (0x20E2, 0x20E4, *b"Me"),
+    (0x20E5, 0x20F0, *b"Mn"), (0x2100, 0x2101, *b"So"), (0x2102, 0x2102, *b"Lu"),
+    (0x2103, 0x2106, *b"So"), (0x2107, 0x2107, *b"Lu"), (0x2108, 0x2109, *b"So"),
+    (0x210A, 0x210A, *b"Ll"), (0x210B, 0x210D, *b"Lu"), (0x210E, 0x210F, *b"Ll"),
+    (0x2110, 0x2112, *b"Lu"), (0x2113, 0x2113, *b"Ll"), (0x2114, 0x2114, *b"So"),
+    (0x2115, 0x2115, *b"Lu"), (0x2116, 0x2117, *b"So"), (0x2118, 0x2118, *b"Sm"),
+    (0x2119, 0x211D, *b"Lu"), (0x211E, 0x2123, *b"So"), (0x2124, 0x2124, *b"Lu"),
+    (0x2125, 0x2125, *b"So"), (0x2126, 0x2126, *b"Lu"), (0x2127, 0x2127, *b"So"),
+    (0x2128, 0x2128, *b"Lu"), (0x2129, 0x2129, *b"So"), (0x212A, 0x212D, *b"Lu"),
+    (0x212E, 0x212E, *b"So"), (0x212F, 0x212F, *b"Ll"), (0x2130, 0x2133, *b"Lu"),
+    (0x2134, 0x2134, *b"Ll"), (0x2135, 0x2138, *b"Lo"), (0x2139, 0x2139, *b"Ll"),
+    (0x213A, 0x213B, *b"So"), (0x213C, 0x213D, *b"Ll"), (0x213E, 0x213F, *b"Lu"),
+    (0x2140, 0x2144, *b"Sm"), (0x2145, 0x2145, *b"Lu"), (0x2146, 0x2149, *b"Ll"),
+    (0x214A, 0x214A, *b"So"), (0x214B, 0x214B, *b"Sm"), (0x214C, 0x214D, *b"So"),
+    (0x214E, 0x214E, *b"Ll"), (0x214F, 0x214F, *b"So"), (0x2150, 0x215F, *b"No"),
+    (0x2160, 0x2182, *b"Nl"), (0x2183, 0x2183, *b"Lu"), (0x2184, 0x2184, *b"Ll"),
+    (0x2185, 0x2188, *b"Nl"), (0x2189, 0x2189, *b"No"), (0x218A, 0x218B, *b"So"),
+    (0x2190, 0x2194, *b"Sm"), (0x2195, 0x2199, *b"So"), (0x219A, 0x219B, *b"Sm"),
+    (0x219C, 0x219F, *b"So"), (0x21A0, 0x21A0, *b"Sm"), (0x21A1, 0x21A2, *b"So"),
+    (0x21A3, 0x21A3, *b"Sm"), (0x21A4, 0x21A5, *b"So"), (0x21A6, 0x21A6, *b"Sm"),
+    (0x21A7, 0x21AD, *b"So"), (0x21AE, 0x21AE, *b"Sm"), (0x21AF, 0x21CD, *b"So"),
+    (0x21CE, 0x21CF, *b"Sm"), (0x21D0, 0x21D1, *b"So"), (0x21D2, 0x21D2, *b"Sm"),
+    (0x21D3, 0x21D3, *b"So"), (0x21D4, 0x21D4, *b"Sm"), (0x21D5, 0x21F3, *b"So"),
+    (0x21F4, 0x22FF, *b"Sm"), (0x2300, 0x2307, *b"So"), (0x2308, 0x2308, *b"Ps"),
+    (0x2309, 0x2309, *b"Pe"), (0x230A, 0x230A, *b"Ps"), (0x230B, 0x230B, *b"Pe"),
+    (0x230C, 0x231F, *b"So"), (0x2320, 0x2321, *b"Sm"), (0x2322, 0x2328, *b"So"),
+    (0x2329, 0x2329, *b"Ps"), (0x232A, 0x232A, *b"Pe"), (0x232B, 0x237B, *b"So"),
+    (0x237C, 0x237C, *b"Sm"), (0x237D, 0x239A, *b"So"), (0x239B, 0x23B3, *b"Sm"),
+    (0x23B4, 0x23DB, *b"So"), (0x23DC, 0x23E1, *b"Sm"), (0x23E2, 0x2426, *b"So"),
+    (0x2440, 0x244A, *b"So"), (0x2460, 0x249B, *b"No"), (0x249C, 0x24E9, *b"So"),
+    (0x24EA, 0x24FF, *b"No"), (0x2500, 0x25B6, *b"So"), (0x25B7, 0x25B7, *b"Sm"),
+    (0x25B8, 0x25C0, *b"So"), (0x25C1, 0x25C1, *b"Sm"), (0x25C2, 0x25F7, *b"So"),
+    (0x25F8, 0x25FF, *b"Sm"), (0x2600, 0x266E, *b"So"), (0x266F, 0x266F, *b"Sm"),
+    (0x2670, 0x2767, *b"So"), (0x2768, 0x2768, *b"Ps"), (0x2769, 0x2769, *b"Pe"),
+    (0x276A, 0x276A, *b"Ps"), (0x276B, 0x276B, *b"Pe"), (0x276C, 0x276C, *b"Ps"),
+    (0x276D, 0x276D, *b"Pe"), (0x276E, 0x276E, *b"Ps"), (0x276F, 0x276F, *b"Pe"),
+    (0x2770, 0x2770, *b"Ps"), (0x2771, 0x2771, *b"Pe"), (0x2772, 0x2772, *b"Ps"),
+    (0x2773, 0x2773, *b"Pe"), (0x2774, 0x2774, *b"Ps"), (0x2775, 0x2775, *b"Pe"),
+    (0x2776, 0x2793, *b"No"), (0x2794, 0x27BF, *b"So"), (0x27C0, 0x27C4, *b"Sm"),
+    (0x27C5, 0x27C5, *b"Ps"), (0x27C6, 0x27C6, *b"Pe"), (0x27C7, 0x27E5, *b"Sm"),
+    (0x27E6, 0x27E6, *b"Ps"), (0x27E7, 0x27E7, *b"Pe"), (0x27E8, 0x27E8, *b"Ps"),
+    (0x27E9, 0x27E9, *b"Pe"), (0x27EA, 0x27EA, *b"Ps"), (0x27EB, 0x27EB, *b"Pe"),
+    (0x27EC, 0x27EC, *b"Ps"), (0x27ED, 0x27ED, *b"Pe"), (0x27EE, 0x27EE, *b"Ps"),
+    (0x27EF, 0x27EF, *b"Pe"), (0x27F0, 0x27FF, *b"Sm"), (0x2800, 0x28FF, *b"So"),
+    (0x2900, 0x2982, *b"Sm"), (0x2983, 0x2983, *b"Ps"), (0x2984, 0x2984, *b"Pe"),
+    (0x2985, 0x2985, *b"Ps"), (0x2986, 0x2986, *b"Pe"), (0x2987, 0x2987, *b"Ps"),
+    (0x2988, 0x2988, *b"Pe"), (0x2989, 0x2989, *b"Ps"), (0x298A, 0x298A, *b"Pe"),
+    (0x298B, 0x298B, *b"Ps"), (0x298C, 0x298C, *b"Pe"), (0x298D, 0x298D, *b"Ps"),
+    (0x298E, 0x298E, *b"Pe"), (0x298F, 0x298F, *b"Ps"), (0x2990, 0x2990, *b"Pe"),
+    (0x2991, 0x2991, *b"Ps"), (0x2992, 0x2992, *b"Pe"), (0x2993, 0x2993, *b"Ps"),
+    (0x2994, 0x2994, *b"Pe"), (0x2995, 0x2995, *b"Ps"), (0x2996, 0x2996, *b"Pe"),
+    (0x2997, 0x2997, *b"Ps"), (0x2998, 0x2998, *b"Pe"), (0x2999, 0x29D7, *b"Sm"),
+    (0x29D8, 0x29D8, *b"Ps"), (0x29D9, 0x29D9, *b"Pe"), (0x29DA, 0x29DA, *b"Ps"),
+    (0x29DB, 0x29DB, *b"Pe"), (0x29DC, 0x29FB, *b"Sm"), (0x29FC, 0x29FC, *b"Ps"),
+    (0x29FD, 0x29FD, *b"Pe"), (0x29FE, 0x2AFF, *b"Sm"), (0x2B00, 0x2B2F, *b"So"),
+    (0x2B30, 0x2B44, *b"Sm"), (0x2B45, 0x2B46, *b"So"), (0x2B47, 0x2B4C, *b"Sm"),
+    (0x2B4D, 0x2B73, *b"So"), (0x2B76, 0x2B95, *b"So"), (0x2B97, 0x2BFF, *b"So"),
+    (0x2C00, 0x2C2F, *b"Lu"), (0x2C30, 0x2C5F, *b"Ll"), (0x2C60, 0x2C60, *b"Lu"),
+    (0x2C61, 0x2C61, *b"Ll"), (0x2C62, 0x2C64, *b"Lu"), (0x2C65, 0x2C66, *b"Ll"),
+    (0x2C67, 0x2C67, *b"Lu"), (0x2C68, 0x2C68, *b"Ll"), (0x2C69, 0x2C69, *b"Lu"),
+    (0x2C6A, 0x2C6A, *b"Ll"), (0x2C6B, 0x2C6B, *b"Lu"), (0x2C6C, 0x2C6C, *b"Ll"),
+    (0x2C6D, 0x2C70, *b"Lu"), (0x2C71, 0x2C71, *b"Ll"), (0x2C72, 0x2C72, *b"Lu"),
+    (0x2C73, 0x2C74, *b"Ll"), (0x2C75, 0x2C75, *b"Lu"), (0x2C76, 0x2C7B, *b"Ll"),
+    (0x2C7C, 0x2C7D, *b"Lm"), (0x2C7E, 0x2C80, *b"Lu"), (0x2C81, 0x2C81, *b"Ll"),
+    (0x2C82, 0x2C82, *b"Lu"), (0x2C83, 0x2C83, *b"Ll"), (0x2C84, 0x2C84, *b"Lu"),
+    (0x2C85, 0x2C85, *b"Ll"), (0x2C86, 0x2C86, *b"Lu"), (0x2C87, 0x2C87, *b"Ll"),
+    (0x2C88, 0x2C88, *b"Lu"), (0x2C89, 0x2C89, *b"Ll"), (0x2C8A, 0x2C8A, *b"Lu"),
+    (0x2C8B, 0x2C8B, *b"Ll"), (0x2C8C, 0x2C8C, *b"Lu"), (0x2C8D, 0x2C8D, *b"Ll"),
+    (0x2C8E, 0x2C8E, *b"Lu"), (0x2C8F, 0x2C8F, *b"Ll"), (0x2C90, 0x2C90, *b"Lu"),
+    (0x2C91, 0x2C91, *b"Ll"), (0x2C92, 0x2C92, *b"Lu"), (0x2C93, 0x2C93, *b"Ll"),
+    (0x2C94, 0x2C94, *b"Lu"), (0x2C95, 0x2C95, *b"Ll"), (0x2C96, 0x2C96, *b"Lu"),
+    (0x2C97, 0x2C97, *b"Ll"), (0x2C98, 0x2C98, *b"Lu"), (0x2C99, 0x2C99, *b"Ll"),
+    (0x2C9A, 0x2C9A, *b"Lu"), (0x2C9B, 0x2C9B, *b"Ll"), (0x2C9C, 0x2C9C, *b"Lu"),
+    (0x2C9D, 0x2C9D, *b"Ll"), (0x2C9E, 0x2C9E, *b"Lu"), (0x2C9F, 0x2C9F, *b"Ll"),
+    (0x2CA0, 0x2CA0, *b"Lu"), (0x2CA1, 0x2CA1, *b"Ll"), (0x2CA2, 0x2CA2, *b"Lu"),
+    (0x2CA3, 0x2CA3, *b"Ll"), (0x2CA4, 0x2CA4, *b"Lu"), (0x2CA5, 0x2CA5, *b"Ll"),
+    (0x2CA6, 0x2CA6, *b"Lu"), (0x2CA7, 0x2CA7, *b"Ll"), (0x2CA8, 0x2CA8, *b"Lu"),
+    (0x2CA9, 0x2CA9, *b"Ll"), (0x2CAA, 0x2CAA, *b"Lu"), (0x2CAB, 0x2CAB, *b"Ll"),
+    (0x2CAC, 0x2CAC, *b"Lu"), (0x2CAD, 0x2CAD, *b"Ll"), (0x2CAE, 0x2CAE, *b"Lu"),
+    (0x2CAF, 0x2CAF, *b"Ll"), (0x2CB0, 0x2CB0, *b"Lu"), (0x2CB1, 0x2CB1, *b"Ll"),
+    (0x2CB2, 0x2CB2, *b"Lu"), (0x2CB3, 0x2CB3, *b"Ll"), (0x2CB4, 0x2CB4, *b"Lu"),
+    (0x2CB5, 0x2CB5, *b"Ll"), (0x2CB6, 0x2CB6, *b"Lu"), (0x2CB7, 0x2CB7, *b"Ll"),
+    (0x2CB8, 0x2CB8, *b"Lu"), (0x2CB9, 0x2CB9, *b"Ll"), (0x2CBA, 0x2CBA, *b"Lu"),
+    (0x2CBB, 0x2CBB, *b"Ll"), (0x2CBC, 0x2CBC, *b"Lu"), (0x2CBD, 0x2CBD, *b"Ll"),
+    (0x2CBE, 0x2CBE, *b"Lu"), (0x2CBF, 0x2CBF, *b"Ll"), (0x2CC0, 0x2CC0, *b"Lu"),
+    (0x2CC1, 0x2CC1, *b"Ll"), (0x2CC2, 0x2CC2, *b"Lu"), (0x2CC3, 0x2CC3, *b"Ll"),
+    (0x2CC4, 0x2CC4, *b"Lu"), (0x2CC5, 0x2CC5, *b"Ll"), (0x2CC6, 0x2CC6, *b"Lu"),
+    (0x2CC7, 0x2CC7, *b"Ll"), (0x2CC8, 0x2CC8, *b"Lu"), (0x2CC9, 0x2CC9, *b"Ll"),
+    (0x2CCA, 0x2CCA, *b"Lu"), (0x2CCB, 0x2CCB, *b"Ll"), (0x2CCC, 0x2CCC, *b"Lu"),
+    (0x2CCD, 0x2CCD, *b"Ll"), (0x2CCE, 0x2CCE, *b"Lu"), (0x2CCF, 0x2CCF, *b"Ll"),
+    (0x2CD0, 0x2CD0, *b"Lu"), (0x2CD1, 0x2CD1, *b"Ll"), (0x2CD2, 0x2CD2, *b"Lu"),
+    (0x2CD3, 0x2CD3, *b"Ll"), (0x2CD4, 0x2CD4, *b"Lu"), (0x2CD5, 0x2CD5, *b"Ll"),
+    (0x2CD6, 0x2CD6, *b"Lu"), (0x2CD7, 0x2CD7, *b"Ll"), (0x2CD8, 0x2CD8, *b"Lu"),
+    (0x2CD9, 0x2CD9, *b"Ll"), (0x2CDA, 0x2CDA, *b"Lu"), (0x2CDB, 0x2CDB, *b"Ll"),
+    (0x2CDC, 0x2CDC, *b"Lu"), (0x2CDD, 0x2CDD, *b"Ll"), (0x2CDE, 0x2CDE, *b"Lu"),
+    (0x2CDF, 0x2CDF, *b"Ll"), (0x2CE0, 0x2CE0, *b"Lu"), (0x2CE1, 0x2CE1, *b"Ll"),
+    (0x2CE2, 0x2CE2, *b"Lu"), (0x2CE3, 0x2CE4, *b"Ll"), (0x2CE5, 0x2CEA, *b"So"),
+    (0x2CEB, 0x2CEB, *b"Lu"), (0x2CEC, 0x2CEC, *b"Ll"), (0x2CED, 0x2CED, *b"Lu"),
+    (0x2CEE, 0x2CEE, *b"Ll"), (0x2CEF, 0x2CF1, *b"Mn"), (0x2CF2, 0x2CF2, *b"Lu"),
+    (0x2CF3, 0x2CF3, *b"Ll"), (0x2CF9, 0x2CFC, *b"Po"), (0x2CFD, 0x2CFD, *b"No"),
+    (0x2CFE, 0x2CFF, *b"Po"), (0x2D00, 0x2D25, *b"Ll"), (0x2D27, 0x2D27, *b"Ll"),
+    (0x2D2D, 0x2D2D, *b"Ll"), (0x2D30, 0x2D67, *b"Lo"), (0x2D6F, 0x2D6F, *b"Lm"),
+    (0x2D70, 0x2D70, *b"Po"), (0x2D7F, 0x2D7F, *b"Mn"), (0x2D80, 0x2D96, *b"Lo"),
+    (0x2DA0, 0x2DA6, *b"Lo"), (0x2DA8, 0x2DAE, *b"Lo"), (0x2DB0, 0x2DB6, *b"Lo"),
+    (0x2DB8, 0x2DBE, *b"Lo"), (0x2DC0, 0x2DC6, *b"Lo"), (0x2DC8, 0x2DCE, *b"Lo"),
+    (0x2DD0, 0x2DD6, *b"Lo"), (0x2DD8, 0x2DDE, *b"Lo"), (0x2DE0, 0x2DFF, *b"Mn"),
+    (0x2E00, 0x2E01, *b"Po"), (0x2E02, 0x2E02, *b"Pi"), (0x2E03, 0x2E03, *b"Pf"),
+    (0x2E04, 0x2E04, *b"Pi"), (0x2E05, 0x2E05, *b"Pf"), (0x2E06, 0x2E08, *b"Po"),
+    (0x2E09, 0x2E09, *b"Pi"), (0x2E0A, 0x2E0A, *b"Pf"), (0x2E0B, 0x2E0B, *b"Po"),
+    (0x2E0C, 0x2E0C, *b"Pi"), (0x2E0D, 0x2E0D, *b"Pf"), (0x2E0E, 0x2E16, *b"Po"),
+    (0x2E17, 0x2E17, *b"Pd"), (0x2E18, 0x2E19, *b"Po"), (0x2E1A, 0x2E1A, *b"Pd"),
+    (0x2E1B, 0x2E1B, *b"Po"), (0x2E1C, 0x2E1C, *b"Pi"), (0x2E1D, 0x2E1D, *b"Pf"),
+    (0x2E1E, 0x2E1F, *b"Po"), (0x2E20, 0x2E20, *b"Pi"), (0x2E21, 0x2E21, *b"Pf"),
+    (0x2E22, 0x2E22, *b"Ps"), (0x2E23, 0x2E23, *b"Pe"), (0x2E24, 0x2E24, *b"Ps"),
+    (0x2E25, 0x2E25, *b"Pe"), (0x2E26, 0x2E26, *b"Ps"), (0x2E27, 0x2E27, *b"Pe"),
+    (0x2E28, 0x2E28, *b"Ps"), (0x2E29, 0x2E29, *b"Pe"), (0x2E2A, 0x2E2E, *b"Po"),
+    (0x2E2F, 0x2E2F, *b"Lm"), (0x2E30, 0x2E39, *b"Po"), (0x2E3A, 0x2E3B, *b"Pd"),
+    (0x2E3C, 0x2E3F, *b"Po"), (0x2E40, 0x2E40, *b"Pd"), (0x2E41, 0x2E41, *b"Po"),
+    (0x2E42, 0x2E42, *b"Ps"), (0x2E43, 0x2E4F, *b"Po"), (0x2E50, 0x2E51, *b"So"),
+    (0x2E52, 0x2E54, *b"Po"), (0x2E55, 0x2E55, *b"Ps"), (0x2E56, 0x2E56, *b"Pe"),
+    (0x2E57, 0x2E57, *b"Ps"), (0x2E58, 0x2E58, *b"Pe"), (0x2E59, 0x2E59, *b"Ps"),
+    (0x2E5A, 0x2E5A, *b"Pe"), (0x2E5B, 0x2E5B, *b"Ps"), (0x2E5C, 0x2E5C, *b"Pe"),
+    (0x2E5D, 0x2E5D, *b"Pd"), (0x2E80, 0x2E99, *b"So"), (0x2E9B, 0x2EF3, *b"So"),
+    (0x2F00, 0x2FD5, *b"So"), (0x2FF0, 0x2FFB, *b"So"), (0x3000, 0x3000, *b"Zs"),
+    (0x3001, 0x3003, *b"Po"), (0x3004, 0x3004, *b"So"), (0x3005, 0x3005, *b"Lm"),
+    (0x3006, 0x3006, *b"Lo"), (0x3007, 0x3007, *b"Nl"), (0x3008, 0x3008, *b"Ps"),
+    (0x3009, 0x3009, *b"Pe"), (0x300A, 0x300A, *b"Ps"), (0x300B, 0x300B, *b"Pe"),
+    (0x300C, 0x300C, *b"Ps"), (0x300D, 0x300D, *b"Pe"), (0x300E, 0x300E, *b"Ps"),
+    (0x300F, 0x300F, *b"Pe"), (0x3010, 0x3010, *b"Ps"), (0x3011, 0x3011, *b"Pe"),
+    (0x3012, 0x3013, *b"So"), (0x3014, 0x3014, *b"Ps"), (0x3015, 0x3015, *b"Pe"),
+    (0x3016, 0x3016, *b"Ps"), (0x3017, 0x3017, *b"Pe"), (0x3018, 0x3018, *b"Ps"),
+    (0x3019, 0x3019, *b"Pe"), (0x301A, 0x301A, *b"Ps"), (0x301B, 0x301B, *b"Pe"),
+    (0x301C, 0x301C, *b"Pd"), (0x301D, 0x301D, *b"Ps"), (0x301E, 0x301F, *b"Pe"),
+    (0x3020, 0x3020, *b"So"), (0x3021, 0x3029, *b"Nl"), (0x302A, 0x302D, *b"Mn"),
+    (0x302E, 0x302F, *b"Mc"), (0x3030, 0x3030, *b"Pd"), (0x3031, 0x3035, *b"Lm"),
+    (0x3036, 0x3037, *b"So"), (0x3038, 0x303A, *b"Nl"), (0x303B, 0x303B, *b"Lm"),
+    (0x303C, 0x303C, *b"Lo"), (0x303D, 0x303D, *b"Po"), (0x303E, 0x303F, *b"So"),
+    (0x3041, 0x3096, *b"Lo"), (0x3099, 0x309A, *b"Mn"), (0x309B, 0x309C, *b"Sk"),
+    (0x309D, 0x309E, *b"Lm"), (0x309F, 0x309F, *b"Lo"), (0x30A0, 0x30A0, *b"Pd"),
+    (0x30A1, 0x30FA, *b"Lo"), (0x30FB, 0x30FB, *b"Po"), (0x30FC, 0x30FE, *b"Lm"),
+    (0x30FF, 0x30FF, *b"Lo"), (0x3105, 0x312F, *b"Lo"), (0x3131, 0x318E, *b"Lo"),
+    (0x3190, 0x3191, *b"So"), (0x3192, 0x3195, *b"No"), (0x3196, 0x319F, *b"So"),
+    (0x31A0, 0x31BF, *b"Lo"), (0x31C0, 0x31E3, *b"So"), (0x31F0, 0x31FF, *b"Lo"),
+    (0x3200, 0x321E, *b"So"), (0x3220, 0x3229, *b"No"), (0x322A, 0x3247, *b"So"),
+    (0x3248, 0x324F, *b"No"), (0x3250, 0x3250, *b"So"), (0x3251, 0x325F, *b"No"),
+    (0x3260, 0x327F, *b"So"), (0x3280, 0x3289, *b"No"), (0x328A, 0x32B0, *b"So"),
+    (0x32B1, 0x32BF, *b"No"), (0x32C0, 0x33FF, *b"So"), (0x3400, 0x4DBF, *b"Lo"),
+    (0x4DC0, 0x4DFF, *b"So"), (0x4E00, 0xA014, *b"Lo"), (0xA015, 0xA015, *b"Lm"),
+    (0xA016, 0xA48C, *b"Lo"), (0xA490, 0xA4C6, *b"So"), (0xA4D0, 0xA4F7, *b"Lo"),
+    (0xA4F8, 0xA4FD, *b"Lm"), (0xA4FE, 0xA4FF, *b"Po"), (0xA500, 0xA60B, *b"Lo"),
+    (0xA60C, 0xA60C, *b"Lm"), (0xA60D, 0xA60F, *b"Po"), (0xA610, 0xA61F, *b"Lo"),
+    (0xA620, 0xA629, *b"Nd"), (0xA62A, 0xA62B, *b"Lo"), (0xA640, 0xA640, *b"Lu"),
+    (0xA641, 0xA641, *b"Ll"), (0xA642, 0xA642, *b"Lu"), (0xA643, 0xA643, *b"Ll"),
+    (0xA644, 0xA644, *b"Lu"), (0xA645, 0xA645, *b"Ll"), (0xA646, 0xA646, *b"Lu"),
+    (0xA647, 0xA647, *b"Ll"), (0xA648, 0xA648, *b"Lu"), (0xA649, 0xA649, *b"Ll"),
+    (0xA64A, 0xA64A, *b"Lu"), (0xA64B, 0xA64B, *b"Ll"), (0xA64C, 0xA64C, *b"Lu"),
+    (0xA64D, 0xA64D, *b"Ll"), (0xA64E, 0xA64E, *b"Lu"), (0xA64F, 0xA64F, *b"Ll"),
+    (0xA650, 0xA650, *b"Lu"), (0xA651, 0xA651, *b"Ll"), (0xA652, 0xA652, *b"Lu"),
+    (0xA653, 0xA653, *b"Ll"), (0xA654, 0xA654, *b"Lu"), (0xA655, 0xA655, *b"Ll"),
+    (0xA656, 0xA656, *b"Lu"), (0xA657, 0xA657, *b"Ll"), (0xA658, 0xA658, *b"Lu"),
+    (0xA659, 0xA659, *b"Ll"), (0xA65A, 0xA65A, *b"Lu"), (0xA65B, 0xA65B, *b"Ll"),
+    (0xA65C, 0xA65C, *b"Lu"), (0xA65D, 0xA65D, *b"Ll"), (0xA65E, 0xA65E, *b"Lu"),
+    (0xA65F, 0xA65F, *b"Ll"), (0xA660, 0xA660, *b"Lu"), (0xA661, 0xA661, *b"Ll"),
+    (0xA662, 0xA662, *b"Lu"), (0xA663, 0xA663, *b"Ll"), (0xA664, 0xA664, *b"Lu"),
+    (0xA665, 0xA665, *b"Ll"), (0xA666, 0xA666, *b"Lu"), (0xA667, 0xA667, *b"Ll"),
+    (0xA668, 0xA668, *b"Lu"), (0xA669, 0xA669, *b"Ll"), (0xA66A, 0xA66A, *b"Lu"),
+    (0xA66B, 0xA66B, *b"Ll"), (0xA66C, 0xA66C, *b"Lu"), (0xA66D, 0xA66D, *b"Ll"),
+    (0xA66E, 0xA66E, *b"Lo"), (0xA66F, 0xA66F, *b"Mn"), (0xA670, 0xA672, *b"Me"),
+    (0xA673, 0xA673, *b"Po"), (0xA674, 0xA67D, *b"Mn"), (0xA67E, 0xA67E, *b"Po"),
+    (0xA67F, 0xA67F, *b"Lm"), (0xA680, 0xA680, *b"Lu"), (0xA681, 0xA681, *b"Ll"),
+    (0xA682, 0xA682, *b"Lu"), (0xA683, 0xA683, *b"Ll"), (0xA684, 0xA684, *b"Lu"),
+    (0xA685, 0xA685, *b"Ll"), (0xA686, 0xA686, *b"Lu"), (0xA687, 0xA687, *b"Ll"),
+    (0xA688, 0xA688, *b"Lu"), (0xA689, 0xA689, *b"Ll"), (0xA68A, 0xA68A, *b"Lu"),
+    (0xA68B, 0xA68B, *b"Ll"), (0xA68C, 0xA68C, *b"Lu"), (0xA68D, 0xA68D, *b"Ll"),
+    (0xA68E, 0xA68E, *b"Lu"), (0xA68F, 0xA68F, *b"Ll"), (0xA690, 0xA690, *b"Lu"),
+    (0xA691, 0xA691, *b"Ll"), (0xA692, 0xA692, *b"Lu"), (0xA693, 0xA693, *b"Ll"),
+    (0xA694, 0xA694, *b"Lu"), (0xA695, 0xA695, *b"Ll"), (0xA696, 0xA696, *b"Lu"),
+    (0xA697, 0xA697, *b"Ll"), (0xA698, 0xA698, *b"Lu"), (0xA699, 0xA699, *b"Ll"),
+    (0xA69A, 0xA69A, *b"Lu"), (0xA69B, 0xA69B, *b"Ll"), (0xA69C, 0xA69D, *b"Lm"),
+    (0xA69E, 0xA69F, *b"Mn"), (0xA6A0, 0xA6E5, *b"Lo"), (0xA6E6, 0xA6EF, *b"Nl"),
+    (0xA6F0, 0xA6F1, *b"Mn"), (0xA6F2, 0xA6F7, *b"Po"), (0xA700, 0xA716, *b"Sk"),
+    (0xA717, 0xA71F, *b"Lm"), (0xA720, 0xA721, *b"Sk"), (0xA722, 0xA722, *b"Lu"),
+    (0xA723, 0xA723, *b"Ll"), (0xA724, 0xA724, *b"Lu"), (0xA725, 0xA725, *b"Ll"),
+    (0xA726, 0xA726, *b"Lu"), (0xA727, 0xA727, *b"Ll"), (0xA728, 0xA728, *b"Lu"),
+    (0xA729, 0xA729, *b"Ll"), (0xA72A, 0xA72A, *b"Lu"), (0xA72B, 0xA72B, *b"Ll"),
+    (0xA72C, 0xA72C, *b"Lu"), (0xA72D, 0xA72D, *b"Ll"), (0xA72E, 0xA72E, *b"Lu"),
+    (0xA72F, 0xA731, *b"Ll"), (0xA732, 0xA732, *b"Lu"), (0xA733, 0xA733, *b"Ll"),
+    (0xA734, 0xA734, *b"Lu"), (0xA735, 0xA735, *b"Ll"), (0xA736, 0xA736, *b"Lu"),
+    (0xA737, 0xA737, *b"Ll"), (0xA738, 0xA738, *b"Lu"), (0xA739, 0xA739, *b"Ll"),
+    (0xA73A, 0xA73A, *b"Lu"), (0xA73B, 0xA73B, *b"Ll"), (0xA73C, 0xA73C, *b"Lu"),
+    (0xA73D, 0xA73D, *b"Ll"), (0xA73E, 0xA73E, *b"Lu"), (0xA73F, 0xA73F, *b"Ll"),
+    (0xA740, 0xA740, *b"Lu"), (0xA741, 0xA741, *b"Ll"), (0xA742, 0xA742, *b"Lu"),
+    (0xA743, 0xA743, *b"Ll"), (0xA744, 0xA744, *b"Lu"), (0xA745, 0xA745, *b"Ll"),
+    (0xA746, 0xA746, *b"Lu"), (0xA747, 0xA747, *b"Ll"), (0xA748, 0xA748, *b"Lu"),
+    (0xA749, 0xA749, *b"Ll"), (0xA74A, 0xA74A, *b"Lu"), (0xA74B, 0xA74B, *b"Ll"),
+    (0xA74C, 0xA74C, *b"Lu"), (0xA74D, 0xA74D, *b"Ll"), (0xA74E, 0xA74E, *b"Lu"),
+    (0xA74F, 0xA74F, *b"Ll"), (0xA750, 0xA750, *b"Lu"), (0xA751, 0xA751, *b"Ll"),
+    (0xA752, 0xA752, *b"Lu"), (0xA753, 0xA753, *b"Ll"), (0xA754, 0xA754, *b"Lu"),
+    (0xA755, 0xA755, *b"Ll"), (0xA756, 0xA756, *b"Lu"), (0xA757, 0xA757, *b"Ll"),
+    (0xA758, 0xA758, *b"Lu"), (0xA759, 0xA759, *b"Ll"), (0xA75A, 0xA75A, *b"Lu"),
+    (0xA75B, 0xA75B, *b"Ll"), (0xA75C, 0xA75C, *b"Lu"), (0xA75D, 0xA75D, *b"Ll"),
+    (0xA75E, 0xA75E, *b"Lu"), (0xA75F, 0xA75F, *b"Ll"), (0xA760, 0xA760, *b"Lu"),
+    (0xA761, 0xA761, *b"Ll"), (0xA762, 0xA762, *b"Lu"), (0xA763, 0xA763, *b"Ll"),
+    (0xA764, 0xA764, *b"Lu"), (0xA765, 0xA765, *b"Ll"), (0xA766, 0xA766, *b"Lu"),
+    (0xA767, 0xA767, *b"Ll"), (0xA768, 0xA768, *b"Lu"), (0xA769, 0xA769, *b"Ll"),
+    (0xA76A, 0xA76A, *b"Lu"), (0xA76B, 0xA76B, *b"Ll"), (0xA76C, 0xA76C, *b"Lu"),
+    (0xA76D, 0xA76D, *b"Ll"), (0xA76E, 0xA76E, *b"Lu"), (0xA76F, 0xA76F, *b"Ll"),
+    (0xA770, 0xA770, *b"Lm"), (0xA771, 0xA778, *b"Ll"), (0xA779, 0xA779, *b"Lu"),
+    (0xA77A, 0xA77A, *b"Ll"), (0xA77B, 0xA77B, *b"Lu"), (0xA77C, 0xA77C, *b"Ll"),
+    (0xA77D, 0xA77E, *b"Lu"), (0xA77F, 0xA77F, *b"Ll"), (0xA780, 0xA780, *b"Lu"),
+    (0xA781, 0xA781, *b"Ll"), (0xA782, 0xA782, *b"Lu"), (0xA783, 0xA783, *b"Ll"),
+    (0xA784, 0xA784, *b"Lu"), (0xA785, 0xA785, *b"Ll"), (0xA786, 0xA786, *b"Lu"),
+    (0xA787, 0xA787, *b"Ll"), (0xA788, 0xA788, *b"Lm"), (0xA789, 0xA78A, *b"Sk"),
+    (0xA78B, 0xA78B, *b"Lu"), (0xA78C, 0xA78C, *b"Ll"), (0xA78D, 0xA78D, *b"Lu"),
+    (0xA78E, 0xA78E, *b"Ll"), (0xA78F, 0xA78F, *b"Lo"), (0xA790, 0xA790, *b"Lu"),
+    (0xA791, 0xA791, *b"Ll"), (0xA792, 0xA792, *b"Lu"), (0xA793, 0xA795, *b"Ll"),
+    (0xA796, 0xA796, *b"Lu"), (0xA797, 0xA797, *b"Ll"), (0xA798, 0xA798, *b"Lu"),
+    (0xA799, 0xA799, *b"Ll"), (0xA79A, 0xA79A, *b"Lu"), (0xA79B, 0xA79B, *b"Ll"),
+    (0xA79C, 0xA79C, *b"Lu"), (0xA79D, 0xA79D, *b"Ll"), (0xA79E, 0xA79E, *b"Lu"),
+    (0xA79F, 0xA79F, *b"Ll"), (0xA7A0, 0xA7A0, *b"Lu"), (0xA7A1, 0xA7A1, *b"Ll"),
+    (0xA7A2, 0xA7A2, *b"Lu"), (0xA7A3, 0xA7A3, *b"Ll"), (0xA7A4, 0xA7A4, *b"Lu"),
+    (0xA7A5, 0xA7A5, *b"Ll"), (0xA7A6, 0xA7A6, *b"Lu"), (0xA7A7, 0xA7A7, *b"Ll"),
+    (0xA7A8, 0xA7A8, *b"Lu"), (0xA7A9, 0xA7A9, *b"Ll"), (0xA7AA, 0xA7AE, *b"Lu"),
+    (0xA7AF, 0xA7AF, *b"Ll"), (0xA7B0, 0xA7B4, *b"Lu"), (0xA7B5, 0xA7B5, *b"Ll"),
+    (0xA7B6, 0xA7B6, *b"Lu"), (0xA7B7, 0xA7B7, *b"Ll"), (0xA7B8, 0xA7B8, *b"Lu"),
+    (0xA7B9, 0xA7B9, *b"Ll"), (0xA7BA, 0xA7BA, *b"Lu"), (0xA7BB, 0xA7BB, *b"Ll"),
+    (0xA7BC, 0xA7BC, *b"Lu"), (0xA7BD, 0xA7BD, *b"Ll"), (0xA7BE, 0xA7BE, *b"Lu"),
+    (0xA7BF, 0xA7BF, *b"Ll"), (0xA7C0, 0xA7C0, *b"Lu"), (0xA7C1, 0xA7C1, *b"Ll"),
+    (0xA7C2, 0xA7C2, *b"Lu"), (0xA7C3, 0xA7C3, *b"Ll"), (0xA7C4, 0xA7C7, *b"Lu"),
+    (0xA7C8, 0xA7C8, *b"Ll"), (0xA7C9, 0xA7C9, *b"Lu"), (0xA7CA, 0xA7CA, *b"Ll"),
+    (0xA7D0, 0xA7D0, *b"Lu"), (0xA7D1, 0xA7D1, *b"Ll"), (0xA7D3, 0xA7D3, *b"Ll"),
+    (0xA7D5, 0xA7D5, *b"Ll"), (0xA7D6, 0xA7D6, *b"Lu"), (0xA7D7, 0xA7D7, *b"Ll"),
+    (0xA7D8, 0xA7D8, *b"Lu"), (0xA7D9, 0xA7D9, *b"Ll"), (0xA7F2, 0xA7F4, *b"Lm"),
+    (0xA7F5, 0xA7F5, *b"Lu"), (0xA7F6, 0xA7F6, *b"Ll"), (0xA7F7, 0xA7F7, *b"Lo"),
+    (0xA7F8, 0xA7F9, *b"Lm"), (0xA7FA, 0xA7FA, *b"Ll"), (0xA7FB, 0xA801, *b"Lo"),
+    (0xA802, 0xA802, *b"Mn"), (0xA803, 0xA805, *b"Lo"), (0xA806, 0xA806, *b"Mn"),
+    (0xA807, 0xA80A, *b"Lo"), (0xA80B, 0xA80B, *b"Mn"), (0xA80C, 0xA822, *b"Lo"),
+    (0xA823, 0xA824, *b"Mc"), (0xA825, 0xA826, *b"Mn"), (0xA827, 0xA827, *b"Mc"),
+    (0xA828, 0xA82B, *b"So"), (0xA82C, 0xA82C, *b"Mn"), (0xA830, 0xA835, *b"No"),
+    (0xA836, 0xA837, *b"So"), (0xA838, 0xA838, *b"Sc"), (0xA839, 0xA839, *b"So"),
+    (0xA840, 0xA873, *b"Lo"), (0xA874, 0xA877, *b"Po"), (0xA880, 0xA881, *b"Mc"),
+    (0xA882, 0xA8B3, *b"Lo"), (0xA8B4, 0xA8C3, *b"Mc"), (0xA8C4, 0xA8C5, *b"Mn"),
+    (0xA8CE, 0xA8CF, *b"Po"), (0xA8D0, 0xA8D9, *b"Nd"), (0xA8E0, 0xA8F1, *b"Mn"),
+    (0xA8F2, 0xA8F7, *b"Lo"), (0xA8F8, 0xA8FA, *b"Po"), (0xA8FB, 0xA8FB, *b"Lo"),
+    (0xA8FC, 0xA8FC, *b"Po"), (0xA8FD, 0xA8FE, *b"Lo"), (0xA8FF, 0xA8FF, *b"Mn"),
+    (0xA900, 0xA909, *b"Nd"), (0xA90A, 0xA925, *b"Lo"), (0xA926, 0xA92D, *b"Mn"),
+    (0xA92E, 0xA92F, *b"Po"), (0xA930, 0xA946, *b"Lo"), (0xA947, 0xA951, *b"Mn"),
+    (0xA952, 0xA953, *b"Mc"), (0xA95F, 0xA95F, *b"Po"), (0xA960, 0xA97C, *b"Lo"),
+    (0xA980, 0xA982, *b"Mn"), (0xA983, 0xA983, *b"Mc"), (0xA984, 0xA9B2, *b"Lo"),
+    (0xA9B3, 0xA9B3, *b"Mn"), (0xA9B4, 0xA9B5, *b"Mc"), (0xA9B6, 0xA9B9, *b"Mn"),
+    (0xA9BA, 0xA9BB, *b"Mc"), (0xA9BC, 0xA9BD, *b"Mn"), (0xA9BE, 0xA9C0, *b"Mc"),
+    (0xA9C1, 0xA9CD, *b"Po"), (0xA9CF, 0xA9CF, *b"Lm"), (0xA9D0, 0xA9D9, *b"Nd"),
+    (0xA9DE, 0xA9DF, *b"Po"), (0xA9E0, 0xA9E4, *b"Lo"), (0xA9E5, 0xA9E5, *b"Mn"),
+    (0xA9E6, 0xA9E6, *b"Lm"), (0xA9E7, 0xA9EF, *b"Lo"), (0xA9F0, 0xA9F9, *b"Nd"),
+    (0xA9FA, 0xA9FE, *b"Lo"), (0xAA00, 0xAA28, *b"Lo"), (0xAA29, 0xAA2E, *b"Mn"),
+    (0xAA2F, 0xAA30, *b"Mc"), (0xAA31, 0xAA32, *b"Mn"), (0xAA33, 0xAA34, *b"Mc"),
+    (0xAA35, 0xAA36, *b"Mn"), (0xAA40, 0xAA42, *b"Lo"), (0xAA43, 0xAA43, *b"Mn"),
+    (0xAA44, 0xAA4B, *b"Lo"), (0xAA4C, 0xAA4C, *b"Mn"), (0xAA4D, 0xAA4D, *b"Mc"),
+    (0xAA50, 0xAA59, *b"Nd"), (0xAA5C, 0xAA5F, *b"Po"), (0xAA60, 0xAA6F, *b"Lo"),
+    (0xAA70, 0xAA70, *b"Lm"), (0xAA71, 0xAA76, *b"Lo"), (0xAA77, 0xAA79, *b"So"),
+    (0xAA7A, 0xAA7A, *b"Lo"), (0xAA7B, 0xAA7B, *b"Mc"), (0xAA7C, 0xAA7C, *b"Mn"),
+    (0xAA7D, 0xAA7D, *b"Mc"), (0xAA7E, 0xAAAF, *b"Lo"), (0xAAB0, 0xAAB0, *b"Mn"),
+    (0xAAB1, 0xAAB1, *b"Lo"), (0xAAB2, 0xAAB4, *b"Mn"), (0xAAB5, 0xAAB6, *b"Lo"),
+    (0xAAB7, 0xAAB8, *b"Mn"), (0xAAB9, 0xAABD, *b"Lo"), (0xAABE, 0xAABF, *b"Mn"),
+    (0xAAC0, 0xAAC0, *b"Lo"), (0xAAC1, 0xAAC1, *b"Mn"), (0xAAC2, 0xAAC2, *b"Lo"),
+    (0xAADB, 0xAADC, *b"Lo"), (0xAADD, 0xAADD, *b"Lm"), (0xAADE, 0xAADF, *b"Po"),
+    (0xAAE0, 0xAAEA, *b"Lo"), (0xAAEB, 0xAAEB, *b"Mc"), (0xAAEC, 0xAAED, *b"Mn"),
+    (0xAAEE, 0xAAEF, *b"Mc"), (0xAAF0, 0xAAF1, *b"Po"), (0xAAF2, 0xAAF2, *b"Lo"),
+    (0xAAF3, 0xAAF4, *b"Lm"), (0xAAF5, 0xAAF5, *b"Mc"), (0xAAF6, 0xAAF6, *b"Mn"),
+    (0xAB01, 0xAB06, *b"Lo"), (0xAB09, 0xAB0E, *b"Lo"), (0xAB11, 0xAB16, *b"Lo"),
+    (0xAB20, 0xAB26, *b"Lo"), (0xAB28, 0xAB2E, *b"Lo"), (0xAB30, 0xAB5A, *b"Ll"),
+    (0xAB5B, 0xAB5B, *b"Sk"), (0xAB5C, 0xAB5F, *b"Lm"), (0xAB60, 0xAB68, *b"Ll"),
+    (0xAB69, 0xAB69, *b"Lm"), (0xAB6A, 0xAB6B, *b"Sk"), (0xAB70, 0xABBF, *b"Ll"),
+    (0xABC0, 0xABE2, *b"Lo"), (0xABE3, 0xABE4, *b"Mc"), (0xABE5, 0xABE5, *b"Mn"),
+    (0xABE6, 0xABE7, *b"Mc"), (0xABE8, 0xABE8, *b"Mn"), (0xABE9, 0xABEA, *b"Mc"),
+    (0xABEB, 0xABEB, *b"Po"), (0xABEC, 0xABEC, *b"Mc"), (0xABED, 0xABED, *b"Mn"),
+    (0xABF0, 0xABF9, *b"Nd"), (0xAC00, 0xD7A3, *b"Lo"), (0xD7B0, 0xD7C6, *b"Lo"),
+    (0xD7CB, 0xD7FB, *b"Lo"), (0xE000, 0xF8FF, *b"Co"), (0xF900, 0xFA6D, *b"Lo"),
+    (0xFA70, 0xFAD9, *b"Lo"), (0xFB00, 0xFB06, *b"Ll"), (0xFB13, 0xFB17, *b"Ll"),
+    (0xFB1D, 0xFB1D, *b"Lo"), (0xFB1E, 0xFB1E, *b"Mn"), (0xFB1F, 0xFB28, *b"Lo"),
+    (0xFB29, 0xFB29, *b"Sm"), (0xFB2A, 0xFB36, *b"Lo"), (0xFB38, 0xFB3C, *b"Lo"),
+    (0xFB3E, 0xFB3E, *b"Lo"), (0xFB40, 0xFB41, *b"Lo"), (0xFB43, 0xFB44, *b"Lo"),
+    (0xFB46, 0xFBB1, *b"Lo"), (0xFBB2, 0xFBC2, *b"Sk"), (0xFBD3, 0xFD3D, *b"Lo"),
+    (0xFD3E, 0xFD3E, *b"Pe"), (0xFD3F, 0xFD3F, *b"Ps"), (0xFD40, 0xFD4F, *b"So"),
+    (0xFD50, 0xFD8F, *b"Lo"), (0xFD92, 0xFDC7, *b"Lo"), (0xFDCF, 0xFDCF, *b"So"),
+    (0xFDF0, 0xFDFB, *b"Lo"), (0xFDFC, 0xFDFC, *b"Sc"), (0xFDFD, 0xFDFF, *b"So"),
+    (0xFE00, 0xFE0F, *b"Mn"), (0xFE10, 0xFE16, *b"Po"), (0xFE17, 0xFE17, *b"Ps"),
+    (0xFE18, 0xFE18, *b"Pe"), (0xFE19, 0xFE19, *b"Po"), (0xFE20, 0xFE2F, *b"Mn"),
+    (0xFE30, 0xFE30, *b"Po"), (0xFE31, 0xFE32, *b"Pd"), (0xFE33, 0xFE34, *b"Pc"),
+    (0xFE35, 0xFE35, *b"Ps"), (0xFE36, 0xFE36, *b"Pe"), (0xFE37, 0xFE37, *b"Ps"),
+    (0xFE38, 0xFE38, *b"Pe"), (0xFE39, 0xFE39, *b"Ps"), (0xFE3A, 0xFE3A, *b"Pe"),
+    (0xFE3B, 0xFE3B, *b"Ps"), (0xFE3C, 0xFE3C, *b"Pe"), (0xFE3D, 0xFE3D, *b"Ps"),
+    (0xFE3E, 0xFE3E, *b"Pe"), (0xFE3F, 0xFE3F, *b"Ps"), (0xFE40, 0xFE40, *b"Pe"),
+    (0xFE41, 0xFE41, *b"Ps"), (0xFE42, 0xFE42, *b"Pe"), (0xFE43, 0xFE43, *b"Ps"),
+    (0xFE44, 0xFE44, *b"Pe"), (0xFE45, 0xFE46, *b"Po"), (0xFE47, 0xFE47, *b"Ps"),
+    (0xFE48, 0xFE48, *b"Pe"), (0xFE49, 0xFE4C, *b"Po"), (0xFE4D, 0xFE4F, *b"Pc"),
+    (0xFE50, 0xFE52, *b"Po"), (0xFE54, 0xFE57, *b"Po"), (0xFE58, 0xFE58, *b"Pd"),
+    (0xFE59, 0xFE59, *b"Ps"), (0xFE5A, 0xFE5A, *b"Pe"), (0xFE5B, 0xFE5B, *b"Ps"),
+    (0xFE5C, 0xFE5C, *b"Pe"), (0xFE5D, 0xFE5D, *b"Ps"), (0xFE5E, 0xFE5E, *b"Pe"),
+    (0xFE5F, 0xFE61, *b"Po"), (0xFE62, 0xFE62, *b"Sm"), (0xFE63, 0xFE63, *b"Pd"),
+    (0xFE64, 0xFE66, *b"Sm"), (0xFE68, 0xFE68, *b"Po"), (0xFE69, 0xFE69, *b"Sc"),
+    (0xFE6A, 0xFE6B, *b"Po"), (0xFE70, 0xFE74, *b"Lo"), (0xFE76, 0xFEFC, *b"Lo"),
+    (0xFEFF, 0xFEFF, *b"Cf"), (0xFF01, 0xFF03, *b"Po"), (0xFF04, 0xFF04, *b"Sc"),
+    (0xFF05, 0xFF07, *b"Po"), (0xFF08, 0xFF08, *b"Ps"), (0xFF09, 0xFF09, *b"Pe"),
+    (0xFF0A, 0xFF0A, *b"Po"), (0xFF0B, 0xFF0B, *b"Sm"), (0xFF0C, 0xFF0C, *b"Po"),
+    (0xFF0D, 0xFF0D, *b"Pd"), (0xFF0E, 0xFF0F, *b"Po"), (0xFF10, 0xFF19, *b"Nd"),
+    (0xFF1A, 0xFF1B, *b"Po"), (0xFF1C, 0xFF1E, *b"Sm"), (0xFF1F, 0xFF20, *b"Po"),
+    (0xFF21, 0xFF3A, *b"Lu"), (0xFF3B, 0xFF3B, *b"Ps"), (0xFF3C, 0xFF3C, *b"Po"),
+    (0xFF3D, 0xFF3D, *b"Pe"), (0xFF3E, 0xFF3E, *b"Sk"), (0xFF3F, 0xFF3F, *b"Pc"),
+    (0xFF40, 0xFF40, *b"Sk"), (0xFF41, 0xFF5A, *b"Ll"), (0xFF5B, 0xFF5B, *b"Ps"),
+    (0xFF5C, 0xFF5C, *b"Sm"), (0xFF5D, 0xFF5D, *b"Pe"), (0xFF5E, 0xFF5E, *b"Sm"),
+    (0xFF5F, 0xFF5F, *b"Ps"), (0xFF60, 0xFF60, *b"Pe"), (0xFF61, 0xFF61, *b"Po"),
+    (0xFF62, 0xFF62, *b"Ps"), (0xFF63, 0xFF63, *b"Pe"), (0xFF64, 0xFF65, *b"Po"),
+    (0xFF66, 0xFF6F, *b"Lo"), (0xFF70, 0xFF70, *b"Lm"), (0xFF71, 0xFF9D, *b"Lo"),
+    (0xFF9E, 0xFF9F, *b"Lm"), (0xFFA0, 0xFFBE, *b"Lo"), (0xFFC2, 0xFFC7, *b"Lo"),
+    (0xFFCA, 0xFFCF, *b"Lo"), (0xFFD2, 0xFFD7, *b"Lo"), (0xFFDA, 0xFFDC, *b"Lo"),
+    (0xFFE0, 0xFFE1, *b"Sc"), (0xFFE2, 0xFFE2, *b"Sm"), (0xFFE3, 0xFFE3, *b"Sk"),
+    (0xFFE4, 0xFFE4, *b"So"), (0xFFE5, 0xFFE6, *b"Sc"), (0xFFE8, 0xFFE8, *b"So"),
+    (0xFFE9, 0xFFEC, *b"Sm"), (0xFFED, 0xFFEE, *b"So"), (0xFFF9, 0xFFFB, *b"Cf"),
+    (0xFFFC, 0xFFFD, *b"So"), (0x10000, 0x1000B, *b"Lo"), (0x1000D, 0x10026, *b"Lo"),
+    (0x10028, 0x1003A, *b"Lo"), (0x1003C, 0x1003D, *b"Lo"), (0x1003F, 0x1004D, *b"Lo"),
+    (0x10050, 0x1005D, *b"Lo"), (0x10080, 0x100FA, *b"Lo"), (0x10100, 0x10102, *b"Po"),
+    (0x10107, 0x10133, *b"No"), (0x10137, 0x1013F, *b"So"), (0x10140, 0x10174, *b"Nl"),
+    (0x10175, 0x10178, *b"No"), (0x10179, 0x10189, *b"So"), (0x1018A, 0x1018B, *b"No"),
+    (0x1018C, 0x1018E, *b"So"), (0x10190, 0x1019C, *b"So"), (0x101A0, 0x101A0, *b"So"),
+    (0x101D0, 0x101FC, *b"So"), (0x101FD, 0x101FD, *b"Mn"), (0x10280, 0x1029C, *b"Lo"),
+    (0x102A0, 0x102D0, *b"Lo"), (0x102E0, 0x102E0, *b"Mn"), (0x102E1, 0x102FB, *b"No"),
+    (0x10300, 0x1031F, *b"Lo"), (0x10320, 0x10323, *b"No"), (0x1032D, 0x10340, *b"Lo"),
+    (0x10341, 0x10341, *b"Nl"), (0x10342, 0x10349, *b"Lo"), (0x1034A, 0x1034A, *b"Nl"),
+    (0x10350, 0x10375, *b"Lo"), (0x10376, 0x1037A, *b"Mn"), (0x10380, 0x1039D, *b"Lo"),
+    (0x1039F, 0x1039F, *b"Po"), (0x103A0, 0x103C3, *b"Lo"), (0x103C8, 0x103CF, *b"Lo"),
+    (0x103D0, 0x103D0, *b"Po"), (0x103D1, 0x103D5, *b"Nl"), (0x10400, 0x10427, *b"Lu"),
+    (0x10428, 0x1044F, *b"Ll"), (0x10450, 0x1049D, *b"Lo"), (0x104A0, 0x104A9, *b"Nd"),
+    (0x104B0, 0x104D3, *b"Lu"), (0x104D8, 0x104FB, *b"Ll"), (0x10500, 0x10527, *b"Lo"),
+    (0x10530, 0x10563, *b"Lo"), (0x1056F, 0x1056F, *b"Po"), (0x10570, 0x1057A, *b"Lu"),
+    (0x1057C, 0x1058A, *b"Lu"), (0x1058C, 0x10592, *b"Lu"), (0x10594, 0x10595, *b"Lu"),
+    (0x10597, 0x105A1, *b"Ll"), (0x105A3, 0x105B1, *b"Ll"), (0x105B3, 0x105B9, *b"Ll"),
+    (0x105BB, 0x105BC, *b"Ll"), (0x10600, 0x10736, *b"Lo"), (0x10740, 0x10755, *b"Lo"),
+    (0x10760, 0x10767, *b"Lo"), (0x10780, 0x10785, *b"Lm"), (0x10787, 0x107B0, *b"Lm"),
+    (0x107B2, 0x107BA, *b"Lm"), (0x10800, 0x10805, *b"Lo"), (0x10808, 0x10808, *b"Lo"),
+    (0x1080A, 0x10835, *b"Lo"), (0x10837, 0x10838, *b"Lo"), (0x1083C, 0x1083C, *b"Lo"),
+    (0x1083F, 0x10855, *b"Lo"), (0x10857, 0x10857, *b"Po"), (0x10858, 0x1085F, *b"No"),
+    (0x10860, 0x10876, *b"Lo"), (0x10877, 0x10878, *b"So"), (0x10879, 0x1087F, *b"No"),
+    (0x10880, 0x1089E, *b"Lo"), (0x108A7, 0x108AF, *b"No"), (0x108E0, 0x108F2, *b"Lo"),
+    (0x108F4, 0x108F5, *b"Lo"), (0x108FB, 0x108FF, *b"No"), (0x10900, 0x10915, *b"Lo"),
+    (0x10916, 0x1091B, *b"No"), (0x1091F, 0x1091F, *b"Po"), (0x10920, 0x10939, *b"Lo"),
+    (0x1093F, 0x1093F, *b"Po"), (0x10980, 0x109B7, *b"Lo"), (0x109BC, 0x109BD, *b"No"),
+    (0x109BE, 0x109BF, *b"Lo"), (0x109C0, 0x109CF, *b"No"), (0x109D2, 0x109FF, *b"No"),
+    (0x10A00, 0x10A00, *b"Lo"), (0x10A01, 0x10A03, *b"Mn"), (0x10A05, 0x10A06, *b"Mn"),
+    (0x10A0C, 0x10A0F, *b"Mn"), (0x10A10, 0x10A13, *b"Lo"), (0x10A15, 0x10A17, *b"Lo"),
+    (0x10A19, 0x10A35, *b"Lo"), (0x10A38, 0x10A3A, *b"Mn"), (0x10A3F, 0x10A3F, *b"Mn"),
+    (0x10A40, 0x10A48, *b"No"), (0x10A50, 0x10A58, *b"Po"), (0x10A60, 0x10A7C, *b"Lo"),
+    (0x10A7D, 0x10A7E, *b"No"), (0x10A7F, 0x10A7F, *b"Po"), (0x10A80, 0x10A9C, *b"Lo"),
+    (0x10A9D, 0x10A9F, *b"No"), (0x10AC0, 0x10AC7, *b"Lo"), (0x10AC8, 0x10AC8, *b"So"),
+    (0x10AC9, 0x10AE4, *b"Lo"), (0x10AE5, 0x10AE6, *b"Mn"), (0x10AEB, 0x10AEF, *b"No"),
+    (0x10AF0, 0x10AF6, *b"Po"), (0x10B00, 0x10B35, *b"Lo"), (0x10B39, 0x10B3F, *b"Po"),
+    (0x10B40, 0x10B55, *b"Lo"), (0x10B58, 0x10B5F, *b"No"), (0x10B60, 0x10B72, *b"Lo"),
+    (0x10B78, 0x10B7F, *b"No"), (0x10B80, 0x10B91, *b"Lo"), (0x10B99, 0x10B9C, *b"Po"),
+    (0x10BA9, 0x10BAF, *b"No"), (0x10C00, 0x10C48, *b"Lo"), (0x10C80, 0x10CB2, *b"Lu"),
+    (0x10CC0, 0x10CF2, *b"Ll"), (0x10CFA, 0x10CFF, *b"No"), (0x10D00, 0x10D23, *b"Lo"),
+    (0x10D24, 0x10D27, *b"Mn"), (0x10D30, 0x10D39, *b"Nd"), (0x10E60, 0x10E7E, *b"No"),
+    (0x10E80, 0x10EA9, *b"Lo"), (0x10EAB, 0x10EAC, *b"Mn"), (0x10EAD, 0x10EAD, *b"Pd"),
+    (0x10EB0, 0x10EB1, *b"Lo"), (0x10F00, 0x10F1C, *b"Lo"), (0x10F1D, 0x10F26, *b"No"),
+    (0x10F27, 0x10F27, *b"Lo"), (0x10F30, 0x10F45, *b"Lo"), (0x10F46, 0x10F50, *b"Mn"),
+    (0x10F51, 0x10F54, *b"No"), (0x10F55, 0x10F59, *b"Po"), (0x10F70, 0x10F81, *b"Lo"),
+    (0x10F82, 0x10F85, *b"Mn"), (0x10F86, 0x10F89, *b"Po"), (0x10FB0, 0x10FC4, *b"Lo"),
+    (0x10FC5, 0x10FCB, *b"No"), (0x10FE0, 0x10FF6, *b"Lo"), (0x11000, 0x11000, *b"Mc"),
+    (0x11001, 0x11001, *b"Mn"), (0x11002, 0x11002, *b"Mc"), (0x11003, 0x11037, *b"Lo"),
+    (0x11038, 0x11046, *b"Mn"), (0x11047, 0x1104D, *b"Po"), (0x11052, 0x11065, *b"No"),
+    (0x11066, 0x1106F, *b"Nd"), (0x11070, 0x11070, *b"Mn"), (0x11071, 0x11072, *b"Lo"),
+    (0x11073, 0x11074, *b"Mn"), (0x11075, 0x11075, *b"Lo"), (0x1107F, 0x11081, *b"Mn"),
+    (0x11082, 0x11082, *b"Mc"), (0x11083, 0x110AF, *b"Lo"), (0x110B0, 0x110B2, *b"Mc"),
+    (0x110B3, 0x110B6, *b"Mn"), (0x110B7, 0x110B8, *b"Mc"), (0x110B9, 0x110BA, *b"Mn"),
+    (0x110BB, 0x110BC, *b"Po"), (0x110BD, 0x110BD, *b"Cf"), (0x110BE, 0x110C1, *b"Po"),
+    (0x110C2, 0x110C2, *b"Mn"), (0x110CD, 0x110CD, *b"Cf"), (0x110D0, 0x110E8, *b"Lo"),
+    (0x110F0, 0x110F9, *b"Nd"), (0x11100, 0x11102, *b"Mn"), (0x11103, 0x11126, *b"Lo"),
+    (0x11127, 0x1112B, *b"Mn"), (0x1112C, 0x1112C, *b"Mc"), (0x1112D, 0x11134, *b"Mn"),
+    (0x11136, 0x1113F, *b"Nd"), (0x11140, 0x11143, *b"Po"), (0x11144, 0x11144, *b"Lo"),
+    (0x11145, 0x11146, *b"Mc"), (0x11147, 0x11147, *b"Lo"), (0x11150, 0x11172, *b"Lo"),
+    (0x11173, 0x11173, *b"Mn"), (0x11174, 0x11175, *b"Po"), (0x11176, 0x11176, *b"Lo"),
+    (0x11180, 0x11181, *b"Mn"), (0x11182, 0x11182, *b"Mc"), (0x11183, 0x111B2, *b"Lo"),
+    (0x111B3, 0x111B5, *b"Mc"), (0x111B6, 0x111BE, *b"Mn"), (0x111BF, 0x111C0, *b"Mc"),
+    (0x111C1, 0x111C4, *b"Lo"), (0x111C5, 0x111C8, *b"Po"), (0x111C9, 0x111CC, *b"Mn"),
+    (0x111CD, 0x111CD, *b"Po"), (0x111CE, 0x111CE, *b"Mc"), (0x111CF, 0x111CF, *b"Mn"),
+    (0x111D0, 0x111D9, *b"Nd"), (0x111DA, 0x111DA, *b"Lo"), (0x111DB, 0x111DB, *b"Po"),
+    (0x111DC, 0x111DC, *b"Lo"), (0x111DD, 0x111DF, *b"Po"), (0x111E1, 0x111F4, *b"No"),
+    (0x11200, 0x11211, *b"Lo"), (0x11213, 0x1122B, *b"Lo"), (0x1122C, 0x1122E, *b"Mc"),
+    (0x1122F, 0x11231, *b"Mn"), (0x11232, 0x11233, *b"Mc"), (0x11234, 0x11234, *b"Mn"),
+    (0x11235, 0x11235, *b"Mc"), (0x11236, 0x11237, *b"Mn"), (0x11238, 0x1123D, *b"Po"),
+    (0x1123E, 0x1123E, *b"Mn"), (0x11280, 0x11286, *b"Lo"), (0x11288, 0x11288, *b"Lo"),
+    (0x1128A, 0x1128D, *b"Lo"), (0x1128F, 0x1129D, *b"Lo"), (0x1129F, 0x112A8, *b"Lo"),
+    (0x112A9, 0x112A9, *b"Po"), (0x112B0, 0x112DE, *b"Lo"), (0x112DF, 0x112DF, *b"Mn"),
+    (0x112E0, 0x112E2, *b"Mc"), (0x112E3, 0x112EA, *b"Mn"), (0x112F0, 0x112F9, *b"Nd"),
+    (0x11300, 0x11301, *b"Mn"), (0x11302, 0x11303, *b"Mc"), (0x11305, 0x1130C, *b"Lo"),
+    (0x1130F, 0x11310, *b"Lo"), (0x11313, 0x11328, *b"Lo"), (0x1132A, 0x11330, *b"Lo"),
+    (0x11332, 0x11333, *b"Lo"), (0x11335, 0x11339, *b"Lo"), (0x1133B, 0x1133C, *b"Mn"),
+    (0x1133D, 0x1133D, *b"Lo"), (0x1133E, 0x1133F, *b"Mc"), (0x11340, 0x11340, *b"Mn"),
+    (0x11341, 0x11344, *b"Mc"), (0x11347, 0x11348, *b"Mc"), (0x1134B, 0x1134D, *b"Mc"),
+    (0x11350, 0x11350, *b"Lo"), (0x11357, 0x11357, *b"Mc"), (0x1135D, 0x11361, *b"Lo"),
+    (0x11362, 0x11363, *b"Mc"), (0x11366, 0x1136C, *b"Mn"), (0x11370, 0x11374, *b"Mn"),
+    (0x11400, 0x11434, *b"Lo"), (0x11435, 0x11437, *b"Mc"), (0x11438, 0x1143F, *b"Mn"),
+    (0x11440, 0x11441, *b"Mc"), (0x11442, 0x11444, *b"Mn"), (0x11445, 0x11445, *b"Mc"),
+    (0x11446, 0x11446, *b"Mn"), (0x11447, 0x1144A, *b"Lo"), (0x1144B, 0x1144F, *b"Po"),
+    (0x11450, 0x11459, *b"Nd"), (0x1145A, 0x1145B, *b"Po"), (0x1145D, 0x1145D, *b"Po"),
+    (0x1145E, 0x1145E, *b"Mn"), (0x1145F, 0x11461, *b"Lo"), (0x11480, 0x114AF, *b"Lo"),
+    (0x114B0, 0x114B2, *b"Mc"), (0x114B3, 0x114B8, *b"Mn"), (0x114B9, 0x114B9, *b"Mc"),
+    (0x114BA, 0x114BA, *b"Mn"), (0x114BB, 0x114BE, *b"Mc"), (0x114BF, 0x114C0, *b"Mn"),
+    (0x114C1, 0x114C1, *b"Mc"), (0x114C2, 0x114C3, *b"Mn"), (0x114C4, 0x114C5, *b"Lo"),
+    (0x114C6, 0x114C6, *b"Po"), (0x114C7, 0x114C7, *b"Lo"), (0x114D0, 0x114D9, *b"Nd"),
+    (0x11580, 0x115AE, *b"Lo"), (0x115AF, 0x115B1, *b"Mc"), (0x115B2, 0x115B5, *b"Mn"),
+    (0x115B8, 0x115BB, *b"Mc"), (0x115BC, 0x115BD, *b"Mn"), (0x115BE, 0x115BE, *b"Mc"),
+    (0x115BF, 0x115C0, *b"Mn"), (0x115C1, 0x115D7, *b"Po"), (0x115D8, 0x115DB, *b"Lo"),
+    (0x115DC, 0x115DD, *b"Mn"), (0x11600, 0x1162F, *b"Lo"), (0x11630, 0x11632, *b"Mc"),
+    (0x11633, 0x1163A, *b"Mn"), (0x1163B, 0x1163C, *b"Mc"), (0x1163D, 0x1163D, *b"Mn"),
+    (0x1163E, 0x1163E, *b"Mc"), (0x1163F, 0x11640, *b"Mn"), (0x11641, 0x11643, *b"Po"),
+    (0x11644, 0x11644, *b"Lo"), (0x11650, 0x11659, *b"Nd"), (0x11660, 0x1166C, *b"Po"),
+    (0x11680, 0x116AA, *b"Lo"), (0x116AB, 0x116AB, *b"Mn"), (0x116AC, 0x116AC, *b"Mc"),
+    (0x116AD, 0x116AD, *b"Mn"), (0x116AE, 0x116AF, *b"Mc"), (0x116B0, 0x116B5, *b"Mn"),
+    (0x116B6, 0x116B6, *b"Mc"), (0x116B7, 0x116B7, *b"Mn"), (0x116B8, 0x116B8, *b"Lo"),
+    (0x116B9, 0x116B9, *b"Po"), (0x116C0, 0x116C9, *b"Nd"), (0x11700, 0x1171A, *b"Lo"),
+    (0x1171D, 0x1171F, *b"Mn"), (0x11720, 0x11721, *b"Mc"), (0x11722, 0x11725, *b"Mn"),
+    (0x11726, 0x11726, *b"Mc"), (0x11727, 0x1172B, *b"Mn"), (0x11730, 0x11739, *b"Nd"),
+    (0x1173A, 0x1173B, *b"No"), (0x1173C, 0x1173E, *b"Po"), (0x1173F, 0x1173F, *b"So"),
+    (0x11740, 0x11746, *b"Lo"), (0x11800, 0x1182B, *b"Lo"), (0x1182C, 0x1182E, *b"Mc"),
+    (0x1182F, 0x11837, *b"Mn"), (0x11838, 0x11838, *b"Mc"), (0x11839, 0x1183A, *b"Mn"),
+    (0x1183B, 0x1183B, *b"Po"), (0x118A0, 0x118BF, *b"Lu"), (0x118C0, 0x118DF, *b"Ll"),
+    (0x118E0, 0x118E9, *b"Nd"), (0x118EA, 0x118F2, *b"No"), (0x118FF, 0x11906, *b"Lo"),
+    (0x11909, 0x11909, *b"Lo"), (0x1190C, 0x11913, *b"Lo"), (0x11915, 0x11916, *b"Lo"),
+    (0x11918, 0x1192F, *b"Lo"), (0x11930, 0x11935, *b"Mc"), (0x11937, 0x11938, *b"Mc"),
+    (0x1193B, 0x1193C, *b"Mn"), (0x1193D, 0x1193D, *b"Mc"), (0x1193E, 0x1193E, *b"Mn"),
+    (0x1193F, 0x1193F, *b"Lo"), (0x11940, 0x11940, *b"Mc"), (0x11941, 0x11941, *b"Lo"),
+    (0x11942, 0x11942, *b"Mc"), (0x11943, 0x11943, *b"Mn"), (0x11944, 0x11946, *b"Po"),
+    (0x11950, 0x11959, *b"Nd"), (0x119A0, 0x119A7, *b"Lo"), (0x119AA, 0x119D0, *b"Lo"),
+    (0x119D1, 0x119D3, *b"Mc"), (0x119D4, 0x119D7, *b"Mn"), (0x119DA, 0x119DB, *b"Mn"),
+    (0x119DC, 0x119DF, *b"Mc"), (0x119E0, 0x119E0, *b"Mn"), (0x119E1, 0x119E1, *b"Lo"),
+    (0x119E2, 0x119E2, *b"Po"), (0x119E3, 0x119E3, *b"Lo"), (0x119E4, 0x119E4, *b"Mc"),
+    (0x11A00, 0x11A00, *b"Lo"), (0x11A01, 0x11A0A, *b"Mn"), (0x11A0B, 0x11A32, *b"Lo"),
+    (0x11A33, 0x11A38, *b"Mn"), (0x11A39, 0x11A39, *b"Mc"), (0x11A3A, 0x11A3A, *b"Lo"),
+    (0x11A3B, 0x11A3E, *b"Mn"), (0x11A3F, 0x11A46, *b"Po"), (0x11A47, 0x11A47, *b"Mn"),
+    (0x11A50, 0x11A50, *b"Lo"), (0x11A51, 0x11A56, *b"Mn"), (0x11A57, 0x11A58, *b"Mc"),
+    (0x11A59, 0x11A5B, *b"Mn"), (0x11A5C, 0x11A89, *b"Lo"), (0x11A8A, 0x11A96, *b"Mn"),
+    (0x11A97, 0x11A97, *b"Mc"), (0x11A98, 0x11A99, *b"Mn"), (0x11A9A, 0x11A9C, *b"Po"),
+    (0x11A9D, 0x11A9D, *b"Lo"), (0x11A9E, 0x11AA2, *b"Po"), (0x11AB0, 0x11AF8, *b"Lo"),
+    (0x11C00, 0x11C08, *b"Lo"), (0x11C0A, 0x11C2E, *b"Lo"), (0x11C2F, 0x11C2F, *b"Mc"),
+    (0x11C30, 0x11C36, *b"Mn"), (0x11C38, 0x11C3D, *b"Mn"), (0x11C3E, 0x11C3E, *b"Mc"),
+    (0x11C3F, 0x11C3F, *b"Mn"), (0x11C40, 0x11C40, *b"Lo"), (0x11C41, 0x11C45, *b"Po"),
+    (0x11C50, 0x11C59, *b"Nd"), (0x11C5A, 0x11C6C, *b"No"), (0x11C70, 0x11C71, *b"Po"),
+    (0x11C72, 0x11C8F, *b"Lo"), (0x11C92, 0x11CA7, *b"Mn"), (0x11CA9, 0x11CA9, *b"Mc"),
+    (0x11CAA, 0x11CB0, *b"Mn"), (0x11CB1, 0x11CB1, *b"Mc"), (0x11CB2, 0x11CB3, *b"Mn"),
+    (0x11CB4, 0x11CB4, *b"Mc"), (0x11CB5, 0x11CB6, *b"Mn"), (0x11D00, 0x11D06, *b"Lo"),
+    (0x11D08, 0x11D09, *b"Lo"), (0x11D0B, 0x11D30, *b"Lo"), (0x11D31, 0x11D36, *b"Mn"),
+    (0x11D3A, 0x11D3A, *b"Mn"), (0x11D3C, 0x11D3D, *b"Mn"), (0x11D3F, 0x11D45, *b"Mn"),
+    (0x11D46, 0x11D46, *b"Lo"), (0x11D47, 0x11D47, *b"Mn"), (0x11D50, 0x11D59, *b"Nd"),
+    (0x11D60, 0x11D65, *b"Lo"), (0x11D67, 0x11D68, *b"Lo"), (0x11D6A, 0x11D89, *b"Lo"),
+    (0x11D8A, 0x11D8E, *b"Mc"), (0x11D90, 0x11D91, *b"Mn"), (0x11D93, 0x11D94, *b"Mc"),
+    (0x11D95, 0x11D95, *b"Mn"), (0x11D96, 0x11D96, *b"Mc"), (0x11D97, 0x11D97, *b"Mn"),
+    (0x11D98, 0x11D98, *b"Lo"), (0x11DA0, 0x11DA9, *b"Nd"), (0x11EE0, 0x11EF2, *b"Lo"),
+    (0x11EF3, 0x11EF4, *b"Mn"), (0x11EF5, 0x11EF6, *b"Mc"), (0x11EF7, 0x11EF8, *b"Po"),
+    (0x11FB0, 0x11FB0, *b"Lo"), (0x11FC0, 0x11FD4, *b"No"), (0x11FD5, 0x11FDC, *b"So"),
+    (0x11FDD, 0x11FE0, *b"Sc"), (0x11FE1, 0x11FF1, *b"So"), (0x11FFF, 0x11FFF, *b"Po"),
+    (0x12000, 0x12399, *b"Lo"), (0x12400, 0x1246E, *b"Nl"), (0x12470, 0x12474, *b"Po"),
+    (0x12480, 0x12543, *b"Lo"), (0x12F90, 0x12FF0, *b"Lo"), (0x12FF1, 0x12FF2, *b"Po"),
+    (0x13000, 0x1342E, *b"Lo"), (0x13430, 0x13438, *b"Cf"), (0x14400, 0x14646, *b"Lo"),
+    (0x16800, 0x16A38, *b"Lo"), (0x16A40, 0x16A5E, *b"Lo"), (0x16A60, 0x16A69, *b"Nd"),
+    (0x16A6E, 0x16A6F, *b"Po"), (0x16A70, 0x16ABE, *b"Lo"), (0x16AC0, 0x16AC9, *b"Nd"),
+    (0x16AD0, 0x16AED, *b"Lo"), (0x16AF0, 0x16AF4, *b"Mn"), (0x16AF5, 0x16AF5, *b"Po"),
+    (0x16B00, 0x16B2F, *b"Lo"), (0x16B30, 0x16B36, *b"Mn"), (0x16B37, 0x16B3B, *b"Po"),
+    (0x16B3C, 0x16B3F, *b"So"), (0x16B40, 0x16B43, *b"Lm"), (0x16B44, 0x16B44, *b"Po"),
+    (0x16B45, 0x16B45, *b"So"), (0x16B50, 0x16B59, *b"Nd"), (0x16B5B, 0x16B61, *b"No"),
+    (0x16B63, 0x16B77, *b"Lo"), (0x16B7D, 0x16B8F, *b"Lo"), (0x16E40, 0x16E5F, *b"Lu"),
+    (0x16E60, 0x16E7F, *b"Ll"), (0x16E80, 0x16E96, *b"No"), (0x16E97, 0x16E9A, *b"Po"),
+    (0x16F00, 0x16F4A, *b"Lo"), (0x16F4F, 0x16F4F, *b"Mn"), (0x16F50, 0x16F50, *b"Lo"),
+    (0x16F51, 0x16F87, *b"Mc"), (0x16F8F, 0x16F92, *b"Mn"), (0x16F93, 0x16F9F, *b"Lm"),
+    (0x16FE0, 0x16FE1, *b"Lm"), (0x16FE2, 0x16FE2, *b"Po"), (0x16FE3, 0x16FE3, *b"Lm"),
+    (0x16FE4, 0x16FE4, *b"Mn"), (0x16FF0, 0x16FF1, *b"Mc"), (0x17000, 0x187F7, *b"Lo"),
+    (0x18800, 0x18CD5, *b"Lo"), (0x18D00, 0x18D08, *b"Lo"), (0x1AFF0, 0x1AFF3, *b"Lm"),
+    (0x1AFF5, 0x1AFFB, *b"Lm"), (0x1AFFD, 0x1AFFE, *b"Lm"), (0x1B000, 0x1B122, *b"Lo"),
+    (0x1B150, 0x1B152, *b"Lo"), (0x1B164, 0x1B167, *b"Lo"), (0x1B170, 0x1B2FB, *b"Lo"),
+    (0x1BC00, 0x1BC6A, *b"Lo"), (0x1BC70, 0x1BC7C, *b"Lo"), (0x1BC80, 0x1BC88, *b"Lo"),
+    (0x1BC90, 0x1BC99, *b"Lo"), (0x1BC9C, 0x1BC9C, *b"So"), (0x1BC9D, 0x1BC9E, *b"Mn"),
+    (0x1BC9F, 0x1BC9F, *b"Po"), (0x1BCA0, 0x1BCA3, *b"Cf"), (0x1CF00, 0x1CF2D, *b"Mn"),
+    (0x1CF30, 0x1CF46, *b"Mn"), (0x1CF50, 0x1CFC3, *b"So"), (0x1D000, 0x1D0F5, *b"So"),
+    (0x1D100, 0x1D126, *b"So"), (0x1D129, 0x1D164, *b"So"), (0x1D165, 0x1D166, *b"Mc"),
+    (0x1D167, 0x1D169, *b"Mn"), (0x1D16A, 0x1D16C, *b"So"), (0x1D16D, 0x1D172, *b"Mc"),
+    (0x1D173, 0x1D17A, *b"Cf"), (0x1D17B, 0x1D182, *b"Mn"), (0x1D183, 0x1D184, *b"So"),
+    (0x1D185, 0x1D18B, *b"Mn"), (0x1D18C, 0x1D1A9, *b"So"), (0x1D1AA, 0x1D1AD, *b"Mn"),
+    (0x1D1AE, 0x1D1EA, *b"So"), (0x1D200, 0x1D241, *b"So"), (0x1D242, 0x1D244, *b"Mn"),
+    (0x1D245, 0x1D245, *b"So"), (0x1D2E0, 0x1D2F3, *b"No"), (0x1D300, 0x1D356, *b"So"),
+    (0x1D360, 0x1D378, *b"No"), (0x1D400, 0x1D419, *b"Lu"), (0x1D41A, 0x1D433, *b"Ll"),
+    (0x1D434, 0x1D44D, *b"Lu"), (0x1D44E, 0x1D454, *b"Ll"), (0x1D456, 0x1D467, *b"Ll"),
+    (0x1D468, 0x1D481, *b"Lu"), (0x1D482, 0x1D49B, *b"Ll"), (0x1D49C, 0x1D49C, *b"Lu"),
+    (0x1D49E, 0x1D49F, *b"Lu"), (0x1D4A2, 0x1D4A2, *b"Lu"), (0x1D4A5, 0x1D4A6, *b"Lu"),
+    (0x1D4A9, 0x1D4AC, *b"Lu"), (0x1D4AE, 0x1D4B5, *b"Lu"), (0x1D4B6, 0x1D4B9, *b"Ll"),
+    (0x1D4BB, 0x1D4BB, *b"Ll"), (0x1D4BD, 0x1D4C3, *b"Ll"), (0x1D4C5, 0x1D4CF, *b"Ll"),
+    (0x1D4D0, 0x1D4E9, *b"Lu"), (0x1D4EA, 0x1D503, *b"Ll"), (0x1D504, 0x1D505, *b"Lu"),
+    (0x1D507, 0x1D50A, *b"Lu"), (0x1D50D, 0x1D514, *b"Lu"), (0x1D516, 0x1D51C, *b"Lu"),
+    (0x1D51E, 0x1D537, *b"Ll"), (0x1D538, 0x1D539, *b"Lu"), (0x1D53B, 0x1D53E, *b"Lu"),
+    (0x1D540, 0x1D544, *b"Lu"), (0x1D546, 0x1D546, *b"Lu"), (0x1D54A, 0x1D550, *b"Lu"),
+    (0x1D552, 0x1D56B, *b"Ll"), (0x1D56C, 0x1D585, *b"Lu"), (0x1D586, 0x1D59F, *b"Ll"),
+    (0x1D5A0, 0x1D5B9, *b"Lu"), (0x1D5BA, 0x1D5D3, *b"Ll"), (0x1D5D4, 0x1D5ED, *b"Lu"),
+    (0x1D5EE, 0x1D607, *b"Ll"), (0x1D608, 0x1D621, *b"Lu"), (0x1D622, 0x1D63B, *b"Ll"),
+    (0x1D63C, 0x1D655, *b"Lu"), (0x1D656, 0x1D66F, *b"Ll"), (0x1D670, 0x1D689, *b"Lu"),
+    (0x1D68A, 0x1D6A5, *b"Ll"), (0x1D6A8, 0x1D6C0, *b"Lu"), (0x1D6C1, 0x1D6C1, *b"Sm"),
+    (0x1D6C2, 0x1D6DA, *b"Ll"), (0x1D6DB, 0x1D6DB, *b"Sm"), (0x1D6DC, 0x1D6E1, *b"Ll"),
+    (0x1D6E2, 0x1D6FA, *b"Lu"), (0x1D6FB, 0x1D6FB, *b"Sm"), (0x1D6FC, 0x1D714, *b"Ll"),
+    (0x1D715, 0x1D715, *b"Sm"), (0x1D716, 0x1D71B, *b"Ll"), (0x1D71C, 0x1D734, *b"Lu"),
+    (0x1D735, 0x1D735, *b"Sm"), (0x1D736, 0x1D74E, *b"Ll"), (0x1D74F, 0x1D74F, *b"Sm"),
+    (0x1D750, 0x1D755, *b"Ll"), (0x1D756, 0x1D76E, *b"Lu"), (0x1D76F, 0x1D76F, *b"Sm"),
+    (0x1D770, 0x1D788, *b"Ll"), (0x1D789, 0x1D789, *b"Sm"), (0x1D78A, 0x1D78F, *b"Ll"),
+    (0x1D790, 0x1D7A8, *b"Lu"), (0x1D7A9, 0x1D7A9, *b"Sm"), (0x1D7AA, 0x1D7C2, *b"Ll"),
+    (0x1D7C3, 0x1D7C3, *b"Sm"), (0x1D7C4, 0x1D7C9, *b"Ll"), (0x1D7CA, 0x1D7CA, *b"Lu"),
+    (0x1D7CB, 0x1D7CB, *b"Ll"), (0x1D7CE, 0x1D7FF, *b"Nd"), (0x1D800, 0x1D9FF, *b"So"),
+    (0x1DA00, 0x1DA36, *b"Mn"), (0x1DA37, 0x1DA3A, *b"So"), (0x1DA3B, 0x1DA6C, *b"Mn"),
+    (0x1DA6D, 0x1DA74, *b"So"), (0x1DA75, 0x1DA75, *b"Mn"), (0x1DA76, 0x1DA83, *b"So"),
+    (0x1DA84, 0x1DA84, *b"Mn"), (0x1DA85, 0x1DA86, *b"So"), (0x1DA87, 0x1DA8B, *b"Po"),
+    (0x1DA9B, 0x1DA9F, *b"Mn"), (0x1DAA1, 0x1DAAF, *b"Mn"), (0x1DF00, 0x1DF09, *b"Ll"),
+    (0x1DF0A, 0x1DF0A, *b"Lo"), (0x1DF0B, 0x1DF1E, *b"Ll"), (0x1E000, 0x1E006, *b"Mn"),
+    (0x1E008, 0x1E018, *b"Mn"), (0x1E01B, 0x1E021, *b"Mn"), (0x1E023, 0x1E024, *b"Mn"),
+    (0x1E026, 0x1E02A, *b"Mn"), (0x1E100, 0x1E12C, *b"Lo"), (0x1E130, 0x1E136, *b"Mn"),
+    (0x1E137, 0x1E13D, *b"Lm"), (0x1E140, 0x1E149, *b"Nd"), (0x1E14E, 0x1E14E, *b"Lo"),
+    (0x1E14F, 0x1E14F, *b"So"), (0x1E290, 0x1E2AD, *b"Lo"), (0x1E2AE, 0x1E2AE, *b"Mn"),
+    (0x1E2C0, 0x1E2EB, *b"Lo"), (0x1E2EC, 0x1E2EF, *b"Mn"), (0x1E2F0, 0x1E2F9, *b"Nd"),
+    (0x1E2FF, 0x1E2FF, *b"Sc"), (0x1E7E0, 0x1E7E6, *b"Lo"), (0x1E7E8, 0x1E7EB, *b"Lo"),
+    (0x1E7ED, 0x1E7EE, *b"Lo"), (0x1E7F0, 0x1E7FE, *b"Lo"), (0x1E800, 0x1E8C4, *b"Lo"),
+    (0x1E8C7, 0x1E8CF, *b"No"), (0x1E8D0, 0x1E8D6, *b"Mn"), (0x1E900, 0x1E921, *b"Lu"),
+    (0x1E922, 0x1E943, *b"Ll"), (0x1E944, 0x1E94A, *b"Mn"), (0x1E94B, 0x1E94B, *b"Lm"),
+    (0x1E950, 0x1E959, *b"Nd"), (0x1E95E, 0x1E95F, *b"Po"), (0x1EC71, 0x1ECAB, *b"No"),
+    (0x1ECAC, 0x1ECAC, *b"So"), (0x1ECAD, 0x1ECAF, *b"No"), (0x1ECB0, 0x1ECB0, *b"Sc"),
+    (0x1ECB1, 0x1ECB4, *b"No"), (0x1ED01, 0x1ED2D, *b"No"), (0x1ED2E, 0x1ED2E, *b"So"),
+    (0x1ED2F, 0x1ED3D, *b"No"), (0x1EE00, 0x1EE03, *b"Lo"), (0x1EE05, 0x1EE1F, *b"Lo"),
+    (0x1EE21, 0x1EE22, *b"Lo"), (0x1EE24, 0x1EE24, *b"Lo"), (0x1EE27, 0x1EE27, *b"Lo"),
+    (0x1EE29, 0x1EE32, *b"Lo"), (0x1EE34, 0x1EE37, *b"Lo"), (0x1EE39, 0x1EE39, *b"Lo"),
+    (0x1EE3B, 0x1EE3B, *b"Lo"), (0x1EE42, 0x1EE42, *b"Lo"), (0x1EE47, 0x1EE47, *b"Lo"),
+    (0x1EE49, 0x1EE49, *b"Lo"), (0x1EE4B, 0x1EE4B, *b"Lo"), (0x1EE4D, 0x1EE4F, *b"Lo"),
+    (0x1EE51, 0x1EE52, *b"Lo"), (0x1EE54, 0x1EE54, *b"Lo"), (0x1EE57, 0x1EE57, *b"Lo"),
+    (0x1EE59, 0x1EE59, *b"Lo"), (0x1EE5B, 0x1EE5B, *b"Lo"), (0x1EE5D, 0x1EE5D, *b"Lo"),
+    (0x1EE5F, 0x1EE5F, *b"Lo"), (0x1EE61, 0x1EE62, *b"Lo"), (0x1EE64, 0x1EE64, *b"Lo"),
+    (0x1EE67, 0x1EE6A, *b"Lo"), (0x1EE6C, 0x1EE72, *b"Lo"), (0x1EE74, 0x1EE77, *b"Lo"),
+    (0x1EE79, 0x1EE7C, *b"Lo"), (0x1EE7E, 0x1EE7E, *b"Lo"), (0x1EE80, 0x1EE89, *b"Lo"),
+    (0x1EE8B, 0x1EE9B, *b"Lo"), (0x1EEA1, 0x1EEA3, *b"Lo"), (0x1EEA5, 0x1EEA9, *b"Lo"),
+    (0x1EEAB, 0x1EEBB, *b"Lo"), (0x1EEF0, 0x1EEF1, *b"Sm"), (0x1F000, 0x1F02B, *b"So"),
+    (0x1F030, 0x1F093, *b"So"), (0x1F0A0, 0x1F0AE, *b"So"), (0x1F0B1, 0x1F0BF, *b"So"),
+    (0x1F0C1, 0x1F0CF, *b"So"), (0x1F0D1, 0x1F0F5, *b"So"), (0x1F100, 0x1F10C, *b"No"),
+    (0x1F10D, 0x1F1AD, *b"So"), (0x1F1E6, 0x1F202, *b"So"), (0x1F210, 0x1F23B, *b"So"),
+    (0x1F240, 0x1F248, *b"So"), (0x1F250, 0x1F251, *b"So"), (0x1F260, 0x1F265, *b"So"),
+    (0x1F300, 0x1F3FA, *b"So"), (0x1F3FB, 0x1F3FF, *b"Sk"), (0x1F400, 0x1F6D7, *b"So"),
+    (0x1F6DD, 0x1F6EC, *b"So"), (0x1F6F0, 0x1F6FC, *b"So"), (0x1F700, 0x1F773, *b"So"),
+    (0x1F780, 0x1F7D8, *b"So"), (0x1F7E0, 0x1F7EB, *b"So"), (0x1F7F0, 0x1F7F0, *b"So"),
+    (0x1F800, 0x1F80B, *b"So"), (0x1F810, 0x1F847, *b"So"), (0x1F850, 0x1F859, *b"So"),
+    (0x1F860, 0x1F887, *b"So"), (0x1F890, 0x1F8AD, *b"So"), (0x1F8B0, 0x1F8B1, *b"So"),
+    (0x1F900, 0x1FA53, *b"So"), (0x1FA60, 0x1FA6D, *b"So"), (0x1FA70, 0x1FA74, *b"So"),
+    (0x1FA78, 0x1FA7C, *b"So"), (0x1FA80, 0x1FA86, *b"So"), (0x1FA90, 0x1FAAC, *b"So"),
+    (0x1FAB0, 0x1FABA, *b"So"), (0x1FAC0, 0x1FAC5, *b"So"), (0x1FAD0, 0x1FAD9, *b"So"),
+    (0x1FAE0, 0x1FAE7, *b"So"), (0x1FAF0, 0x1FAF6, *b"So"), (0x1FB00, 0x1FB92, *b"So"),
+    (0x1FB94, 0x1FBCA, *b"So"), (0x1FBF0, 0x1FBF9, *b"Nd"), (0x20000, 0x2A6DF, *b"Lo"),
+    (0x2A700, 0x2B738, *b"Lo"), (0x2B740, 0x2B81D, *b"Lo"), (0x2B820, 0x2CEA1, *b"Lo"),
+    (0x2CEB0, 0x2EBE0, *b"Lo"), (0x2F800, 0x2FA1D, *b"Lo"), (0x30000, 0x3134A, *b"Lo"),
+    (0xE0001, 0xE0001, *b"Cf"), (0xE0020, 0xE007F, *b"Cf"), (0xE0100, 0xE01EF, *b"Mn"),
+    (0xF0000, 0xFFFFD, *b"Co"), (0x100000, 0x10FFFD, *b"Co"),
+];
@@ -0,0 +1,463 @@
+use std::fmt;
+use std::io;
+
+use crate::error::WriteError;
+use crate::json_value::JsonValue;
+use crate::number::JsonNumber;
+use crate::serializer::{self, PrettyPrinter, MAX_COMPACT_ARRAY_WIDTH};
+use crate::JsonSerializable;
+
+/// Output is handed to the underlying writer once this many bytes are
+/// buffered.
+const FLUSH_THRESHOLD: usize = 8 * 1024;
+
+struct Frame {
+    is_object: bool,
+    len: usize,
+    key_written: bool,
+    /// Rendered elements of an array that has only contained scalars so far,
+    /// held back while [`PrettyPrinter::with_compact_scalar_arrays`] may
+    /// still put it on a single line.
+    pending: Option<Vec<String>>,
+    /// The length of the line the pending elements would take.
+    pending_width: usize,
+}
+
+/// Writes a single JSON value incrementally to an [`io::Write`], without
+/// building a [`JsonValue`] tree first.
+///
+/// Calls are checked as they are made, so a key outside an object or a
+/// mismatched `end_*` is reported as a [`WriteError`]. Output is compact
+/// unless a [`PrettyPrinter`] is set; key sorting is not applied to objects
+/// streamed member by member, since that would require buffering them.
+pub struct JsonWriter<W: io::Write> {
+    out: W,
+    buf: String,
+    pretty: Option<PrettyPrinter>,
+    stack: Vec<Frame>,
+    has_root: bool,
+}
+
+impl<W: io::Write> JsonWriter<W> {
+    pub fn new(out: W) -> JsonWriter<W> {
+        JsonWriter {
+            out,
+            buf: String::new(),
+            pretty: None,
+            stack: Vec::new(),
+            has_root: false,
+        }
+    }
+
+    pub fn with_pretty(mut self, printer: PrettyPrinter) -> Self {
+        self.pretty = Some(printer);
+        self
+    }
+
+    pub fn begin_object(&mut self) -> Result<(), WriteError> {
+        self.before_value()?;
+        self.push_frame(true);
+        self.buf.push('{');
+        Ok(())
+    }
+
+    pub fn key(&mut self, key: &str) -> Result<(), WriteError> {
+        let frame = match self.stack.last_mut() {
+            Some(frame) if frame.is_object && !frame.key_written => frame,
+            _ => return Err(WriteError::UnexpectedKey),
+        };
+        frame.key_written = true;
+        if frame.len > 0 {
+            self.buf.push(',');
+        }
+        self.write_newline(self.stack.len());
+        push(&mut self.buf, |out| serializer::write_str(out, key));
+        self.buf
+            .push_str(if self.pretty.is_some() { ": " } else { ":" });
+        Ok(())
+    }
+
+    pub fn end_object(&mut self) -> Result<(), WriteError> {
+        match self.stack.last() {
+            Some(frame) if frame.is_object && !frame.key_written => {}
+            _ => return Err(WriteError::MismatchedEnd),
+        }
+        let frame = self.stack.pop().unwrap();
+        if frame.len > 0 {
+            self.write_newline(self.stack.len());
+        }
+        self.buf.push('}');
+        self.after_value()
+    }
+
+    pub fn begin_array(&mut self) -> Result<(), WriteError> {
+        self.before_value()?;
+        self.push_frame(false);
+        if self.stack.last().unwrap().pending.is_none() {
+            self.buf.push('[');
+        }
+        Ok(())
+    }
+
+    pub fn end_array(&mut self) -> Result<(), WriteError> {
+        match self.stack.last() {
+            Some(frame) if !frame.is_object => {}
+            _ => return Err(WriteError::MismatchedEnd),
+        }
+        let frame = self.stack.pop().unwrap();
+        match frame.pending {
+            Some(elements) => {
+                self.buf.push('[');
+                self.buf.push_str(&elements.join(", "));
+            }
+            None if frame.len > 0 => self.write_newline(self.stack.len()),
+            None => {}
+        }
+        self.buf.push(']');
+        self.after_value()
+    }
+
+    /// Writes a complete value, formatting nested arrays and objects the
+    /// same way [`PrettyPrinter`] or the compact `Display` impl would.
+    pub fn value(&mut self, value: &JsonValue) -> Result<(), WriteError> {
+        if !matches!(value, JsonValue::Object(_) | JsonValue::Array(_)) {
+            return self.scalar(|out| serializer::write_compact(out, value));
+        }
+        self.before_value()?;
+        let level = self.stack.len();
+        match &self.pretty {
+            Some(printer) => push(&mut self.buf, |out| printer.write_value(out, value, level)),
+            None => push(&mut self.buf, |out| serializer::write_compact(out, value)),
+        }
+        self.after_value()
+    }
+
+    pub fn string(&mut self, s: &str) -> Result<(), WriteError> {
+        self.scalar(|out| serializer::write_str(out, s))
+    }
+
+    pub fn number<N: Into<JsonNumber>>(&mut self, n: N) -> Result<(), WriteError> {
+        let n = n.into();
+        self.scalar(|out| serializer::write_number(out, &n))
+    }
+
+    pub fn bool(&mut self, b: bool) -> Result<(), WriteError> {
+        self.scalar(|out| fmt::Write::write_str(out, if b { "true" } else { "false" }))
+    }
+
+    pub fn null(&mut self) -> Result<(), WriteError> {
+        self.scalar(|out| fmt::Write::write_str(out, "null"))
+    }
+
+    /// Writes `value` through [`JsonSerializable::write_json`].
+    pub fn serialize<T: JsonSerializable>(&mut self, value: &T) -> Result<(), WriteError> {
+        value.write_json(self)
+    }
+
+    /// Checks that exactly one complete value was written, flushes all
+    /// output and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W, WriteError> {
+        if !self.stack.is_empty() || !self.has_root {
+            return Err(WriteError::Incomplete);
+        }
+        self.flush_buf()?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn push_frame(&mut self, is_object: bool) {
+        let compact = !is_object
+            && self
+                .pretty
+                .as_ref()
+                .is_some_and(|printer| printer.compact_scalar_arrays);
+        self.stack.push(Frame {
+            is_object,
+            len: 0,
+            key_written: false,
+            pending: if compact { Some(Vec::new()) } else { None },
+            pending_width: "[]".len(),
+        });
+    }
+
+    fn scalar(&mut self, write: impl FnOnce(&mut String) -> fmt::Result) -> Result<(), WriteError> {
+        if let Some(Frame {
+            pending: Some(elements),
+            pending_width,
+            len,
+            ..
+        }) = self.stack.last_mut()
+        {
+            let mut element = String::new();
+            push(&mut element, write);
+            if !elements.is_empty() {
+                *pending_width += ", ".len();
+            }
+            *pending_width += element.len();
+            elements.push(element);
+            *len += 1;
+            // Too long for one line, so the array is written out expanded
+            // rather than held back any longer.
+            if *pending_width > MAX_COMPACT_ARRAY_WIDTH {
+                self.expand_pending();
+                return self.after_value();
+            }
+            return Ok(());
+        }
+        self.before_value()?;
+        push(&mut self.buf, write);
+        self.after_value()
+    }
+
+    /// Validates that a value may be written at this point and writes the
+    /// separator and indentation preceding it.
+    fn before_value(&mut self) -> Result<(), WriteError> {
+        self.expand_pending();
+        let level = self.stack.len();
+        match self.stack.last_mut() {
+            None if self.has_root => return Err(WriteError::MultipleRootValues),
+            None => self.has_root = true,
+            Some(frame) if frame.is_object => {
+                if !frame.key_written {
+                    return Err(WriteError::MissingKey);
+                }
+                frame.key_written = false;
+                frame.len += 1;
+            }
+            Some(frame) => {
+                frame.len += 1;
+                if frame.len > 1 {
+                    self.buf.push(',');
+                }
+                self.write_newline(level);
+            }
+        }
+        Ok(())
+    }
+
+    fn after_value(&mut self) -> Result<(), WriteError> {
+        if self.buf.len() >= FLUSH_THRESHOLD {
+            self.flush_buf()?;
+        }
+        Ok(())
+    }
+
+    /// Writes out the held-back elements of the innermost array one per
+    /// line, once it turns out to contain an array or object or gets too
+    /// long for a single line.
+    fn expand_pending(&mut self) {
+        let level = self.stack.len();
+        let elements = match self.stack.last_mut() {
+            Some(frame) => match frame.pending.take() {
+                Some(elements) => elements,
+                None => return,
+            },
+            None => return,
+        };
+        self.buf.push('[');
+        for (i, element) in elements.iter().enumerate() {
+            if i > 0 {
+                self.buf.push(',');
+            }
+            self.write_newline(level);
+            self.buf.push_str(element);
+        }
+    }
+
+    fn write_newline(&mut self, level: usize) {
+        if let Some(printer) = &self.pretty {
+            push(&mut self.buf, |out| printer.write_newline(out, level));
+        }
+    }
+
+    fn flush_buf(&mut self) -> Result<(), WriteError> {
+        self.out.write_all(self.buf.as_bytes())?;
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+fn push(buf: &mut String, write: impl FnOnce(&mut String) -> fmt::Result) {
+    write(buf).expect("writing to a String cannot fail");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::JsonParser;
+    use crate::JsonSerializable;
+
+    fn write(
+        mut writer: JsonWriter<Vec<u8>>,
+        calls: impl FnOnce(&mut JsonWriter<Vec<u8>>) -> Result<(), WriteError>,
+    ) -> Result<String, WriteError> {
+        calls(&mut writer)?;
+        Ok(String::from_utf8(writer.finish()?).unwrap())
+    }
+
+    fn write_sample(w: &mut JsonWriter<Vec<u8>>) -> Result<(), WriteError> {
+        w.begin_object()?;
+        w.key("name")?;
+        w.string("John \"J\"")?;
+        w.key("grades")?;
+        w.begin_array()?;
+        w.number(90.0)?;
+        w.number(85.5)?;
+        w.end_array()?;
+        w.key("courses")?;
+        w.begin_array()?;
+        w.number(1.0)?;
+        w.begin_object()?;
+        w.key("title")?;
+        w.string("Math")?;
+        w.end_object()?;
+        w.begin_array()?;
+        w.end_array()?;
+        w.end_array()?;
+        w.key("info")?;
+        w.value(
+            &JsonParser::new(r#"{"city": "NY", "tags": [true, null]}"#)
+                .parse()
+                .unwrap(),
+        )?;
+        w.key("empty")?;
+        w.begin_object()?;
+        w.end_object()?;
+        w.end_object()
+    }
+
+    #[test]
+    fn test_writer_compact() {
+        let text = write(JsonWriter::new(Vec::new()), write_sample).unwrap();
+
+        assert_eq!(
+            text,
+            r#"{"name":"John \"J\"","grades":[90,85.5],"courses":[1,{"title":"Math"},[]],"info":{"city":"NY","tags":[true,null]},"empty":{}}"#
+        );
+    }
+
+    #[test]
+    fn test_writer_matches_pretty_printer() {
+        for printer in [
+            PrettyPrinter::new(),
+            PrettyPrinter::new().with_compact_scalar_arrays(true),
+        ] {
+            let writer = JsonWriter::new(Vec::new()).with_pretty(printer.clone());
+            let text = write(writer, write_sample).unwrap();
+            let value = JsonParser::new(&text).parse().unwrap();
+
+            assert_eq!(text, printer.to_string(&value));
+        }
+    }
+
+    #[test]
+    fn test_writer_nesting_errors() {
+        type Calls = fn(&mut JsonWriter<Vec<u8>>) -> Result<(), WriteError>;
+        let cases: Vec<(Calls, &str)> = vec![
+            (|w| w.key("a"), "UnexpectedKey"),
+            (
+                |w| {
+                    w.begin_array()?;
+                    w.key("a")
+                },
+                "UnexpectedKey",
+            ),
+            (
+                |w| {
+                    w.begin_object()?;
+                    w.key("a")?;
+                    w.key("b")
+                },
+                "UnexpectedKey",
+            ),
+            (
+                |w| {
+                    w.begin_object()?;
+                    w.null()
+                },
+                "MissingKey",
+            ),
+            (
+                |w| {
+                    w.begin_object()?;
+                    w.end_array()
+                },
+                "MismatchedEnd",
+            ),
+            (
+                |w| {
+                    w.begin_object()?;
+                    w.key("a")?;
+                    w.end_object()
+                },
+                "MismatchedEnd",
+            ),
+            (|w| w.end_object(), "MismatchedEnd"),
+            (
+                |w| {
+                    w.null()?;
+                    w.null()
+                },
+                "MultipleRootValues",
+            ),
+            (|w| w.begin_array(), "Incomplete"),
+            (|_| Ok(()), "Incomplete"),
+        ];
+
+        for (calls, expected) in cases {
+            let err = write(JsonWriter::new(Vec::new()), calls).unwrap_err();
+            assert_eq!(format!("{:?}", err), expected);
+        }
+    }
+
+    #[derive(crate::JsonSerializable)]
+    struct Course {
+        title: String,
+        credits: u32,
+        is_required: bool,
+    }
+
+    #[test]
+    fn test_writer_serializable() {
+        let course = Course {
+            title: "Math".to_string(),
+            credits: 3,
+            is_required: true,
+        };
+        let text = write(JsonWriter::new(Vec::new()), |w| w.serialize(&course)).unwrap();
+
+        assert_eq!(text, course.to_json().to_string());
+    }
+
+    #[test]
+    fn test_writer_streams_long_scalar_arrays() {
+        let printer = PrettyPrinter::new().with_compact_scalar_arrays(true);
+        let mut writer = JsonWriter::new(Vec::new()).with_pretty(printer.clone());
+        writer.begin_array().unwrap();
+        for i in 0..10_000 {
+            writer.number(i).unwrap();
+        }
+        assert!(!writer.out.is_empty());
+        assert!(writer.stack[0].pending.is_none());
+        writer.end_array().unwrap();
+
+        let text = String::from_utf8(writer.finish().unwrap()).unwrap();
+        let value = JsonParser::new(&text).parse().unwrap();
+        assert_eq!(text, printer.to_string(&value));
+    }
+
+    #[test]
+    fn test_writer_flushes_large_output() {
+        let mut writer = JsonWriter::new(Vec::new());
+        writer.begin_array().unwrap();
+        for i in 0..10_000 {
+            writer.number(i).unwrap();
+        }
+        assert!(!writer.out.is_empty());
+        writer.end_array().unwrap();
+
+        let text = String::from_utf8(writer.finish().unwrap()).unwrap();
+        let value = JsonParser::new(&text).parse().unwrap();
+        assert_eq!(value.get_arr(9_999), Some(&JsonValue::Number(9_999.into())));
+    }
+}
@@ -0,0 +1,138 @@
+//! Runs the `test_parsing` corpus of JSONTestSuite
+//! (https://github.com/nst/JSONTestSuite) against the parser.
+//!
+//! Files prefixed with `y_` must be accepted and `n_` must be rejected in
+//! strict mode. `i_` files are implementation-defined; the decisions for those
+//! and for `n_` files in lenient mode are listed below so that any change in
+//! behaviour shows up as a test failure.
+
+use std::fs;
+use std::path::PathBuf;
+
+use yi_json::parser::JsonParser;
+use yi_json::{JsonValue, ParseError};
+
+fn test_files(prefix: &str) -> Vec<(String, Vec<u8>)> {
+    let dir =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/JSONTestSuite/test_parsing");
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, path)
+        })
+        .filter(|(name, _)| name.starts_with(prefix))
+        .map(|(name, path)| (name, fs::read(path).unwrap()))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty());
+    files
+}
+
+/// Input that is not valid UTF-8 is rejected before it reaches the parser.
+fn parse(input: &[u8], strict: bool) -> Option<Result<JsonValue, ParseError>> {
+    let input = std::str::from_utf8(input).ok()?;
+    Some(JsonParser::new(input).with_strict(strict).parse())
+}
+
+#[test]
+fn test_accepts_y_files() {
+    for (name, input) in test_files("y_") {
+        for strict in [true, false] {
+            match parse(&input, strict) {
+                Some(Ok(_)) => {}
+                Some(Err(err)) => panic!("{} rejected (strict: {}): {}", name, strict, err),
+                None => panic!("{} is not valid UTF-8", name),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_rejects_n_files_in_strict_mode() {
+    for (name, input) in test_files("n_") {
+        if let Some(Ok(value)) = parse(&input, true) {
+            panic!("{} accepted as {:?}", name, value);
+        }
+    }
+}
+
+/// The `i_` files this parser accepts; every other `i_` file is rejected.
+const ACCEPTED_I_FILES: &[&str] = &[
+    "i_number_double_huge_neg_exp.json",
+    "i_number_real_underflow.json",
+    "i_number_too_big_neg_int.json",
+    "i_number_too_big_pos_int.json",
+    "i_number_very_big_negative_int.json",
+];
+
+#[test]
+fn test_i_files_decisions() {
+    for (name, input) in test_files("i_") {
+        for strict in [true, false] {
+            let accepted = matches!(parse(&input, strict), Some(Ok(_)));
+            assert_eq!(
+                accepted,
+                ACCEPTED_I_FILES.contains(&name.as_str()),
+                "{} (strict: {})",
+                name,
+                strict
+            );
+        }
+    }
+}
+
+/// The `n_` files that are only rejected in strict mode.
+const LENIENT_ACCEPTED_N_FILES: &[&str] = &[
+    "n_number_+1.json",
+    "n_number_-01.json",
+    "n_number_-2..json",
+    "n_number_0.e1.json",
+    "n_number_2.e+3.json",
+    "n_number_2.e-3.json",
+    "n_number_2.e3.json",
+    "n_number_neg_int_starting_with_zero.json",
+    "n_number_neg_real_without_int_part.json",
+    "n_number_real_without_fractional_part.json",
+    "n_number_with_leading_zero.json",
+    "n_string_backslash_00.json",
+    "n_string_escape_x.json",
+    "n_string_escaped_ctrl_char_tab.json",
+    "n_string_escaped_emoji.json",
+    "n_string_invalid_backslash_esc.json",
+    "n_string_unescaped_ctrl_char.json",
+    "n_string_unescaped_newline.json",
+    "n_string_unescaped_tab.json",
+    "n_string_unicode_CapitalU.json",
+    "n_structure_whitespace_formfeed.json",
+];
+
+#[test]
+fn test_n_files_decisions_in_lenient_mode() {
+    for (name, input) in test_files("n_") {
+        let accepted = matches!(parse(&input, false), Some(Ok(_)));
+        assert_eq!(
+            accepted,
+            LENIENT_ACCEPTED_N_FILES.contains(&name.as_str()),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_reader_agrees_with_str_parser() {
+    for prefix in ["y_", "n_", "i_"] {
+        for (name, input) in test_files(prefix) {
+            for strict in [true, false] {
+                let from_str = parse(&input, strict).and_then(Result::ok);
+                let from_reader = JsonParser::from_reader(&input[..])
+                    .with_strict(strict)
+                    .parse()
+                    .ok();
+                assert_eq!(from_reader, from_str, "{} (strict: {})", name, strict);
+            }
+        }
+    }
+}